@@ -0,0 +1,80 @@
+//! Compares replaying games with `do_moves_checked`
+//! with a naive replay that generates every legal move and computes the game result before each move.
+//!
+//! Run with `cargo bench --bench do_moves_checked`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use board_game_traits::Position as PositionTrait;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use tiltak::position::{Move, Position};
+
+const GAMES: usize = 200;
+const ITERATIONS: u32 = 20;
+
+fn random_games(num_games: usize) -> Vec<Vec<Move<6>>> {
+    let mut rng = SmallRng::seed_from_u64(421);
+    let mut moves = vec![];
+    (0..num_games)
+        .map(|_| {
+            let mut position = <Position<6>>::start_position();
+            let mut game = vec![];
+            while position.game_result().is_none() && game.len() < 200 {
+                moves.clear();
+                position.generate_moves(&mut moves);
+                let mv = *moves.choose(&mut rng).unwrap();
+                position.do_move(mv);
+                game.push(mv);
+            }
+            game
+        })
+        .collect()
+}
+
+fn replay_naive(game: &[Move<6>], legal_moves: &mut Vec<Move<6>>) -> Position<6> {
+    let mut position = <Position<6>>::start_position();
+    for mv in game {
+        assert!(position.game_result().is_none());
+        legal_moves.clear();
+        position.generate_moves(legal_moves);
+        assert!(legal_moves.contains(mv));
+        position.do_move(*mv);
+    }
+    position
+}
+
+fn main() {
+    let games = random_games(GAMES);
+    let total_moves: usize = games.iter().map(Vec::len).sum();
+
+    let mut legal_moves = vec![];
+    let start_time = Instant::now();
+    for _ in 0..ITERATIONS {
+        for game in games.iter() {
+            black_box(replay_naive(game, &mut legal_moves));
+        }
+    }
+    let naive = start_time.elapsed();
+
+    let start_time = Instant::now();
+    for _ in 0..ITERATIONS {
+        for game in games.iter() {
+            let mut position = <Position<6>>::start_position();
+            position.do_moves_checked(game).unwrap();
+            black_box(position);
+        }
+    }
+    let checked = start_time.elapsed();
+
+    let replayed_moves = ITERATIONS as f64 * total_moves as f64;
+    println!(
+        "{} games, {} moves: {:.0}ns per move naive, {:.0}ns per move with do_moves_checked",
+        GAMES,
+        total_moves,
+        naive.as_nanos() as f64 / replayed_moves,
+        checked.as_nanos() as f64 / replayed_moves,
+    );
+}
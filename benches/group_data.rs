@@ -0,0 +1,55 @@
+//! Compares computing the group data from scratch after every legal move
+//! with `group_data_after_move`, which updates the group data from before the move.
+//!
+//! Run with `cargo bench --bench group_data`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use board_game_traits::Position as PositionTrait;
+use tiltak::move_gen::benchmark_positions;
+use tiltak::position::Position;
+
+const ITERATIONS: u32 = 2000;
+
+fn bench_group_data(name: &str, position: &Position<6>) {
+    let mut position = position.clone();
+    let group_data = position.group_data();
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+
+    let start_time = Instant::now();
+    for _ in 0..ITERATIONS {
+        for mv in moves.iter() {
+            let reverse_move = position.do_move(*mv);
+            black_box(position.group_data());
+            position.reverse_move(reverse_move);
+        }
+    }
+    let from_scratch = start_time.elapsed();
+
+    let start_time = Instant::now();
+    for _ in 0..ITERATIONS {
+        for mv in moves.iter() {
+            let reverse_move = position.do_move(*mv);
+            black_box(position.group_data_after_move(&group_data, *mv));
+            position.reverse_move(reverse_move);
+        }
+    }
+    let incremental = start_time.elapsed();
+
+    let calls = ITERATIONS as f64 * moves.len() as f64;
+    println!(
+        "{}: {} moves, {:.0}ns from scratch, {:.0}ns incremental",
+        name,
+        moves.len(),
+        from_scratch.as_nanos() as f64 / calls,
+        incremental.as_nanos() as f64 / calls,
+    );
+}
+
+fn main() {
+    for (name, position) in benchmark_positions() {
+        bench_group_data(name, &position);
+    }
+}
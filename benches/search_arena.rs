@@ -0,0 +1,38 @@
+//! Compares search speed with a fixed and a growable arena.
+//! Tree accesses in a growable arena go through its block table, while a fixed arena looks them up directly, so this measures the table's overhead.
+//!
+//! Run with `cargo bench --bench search_arena`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use board_game_traits::Position as PositionTrait;
+use tiltak::position::Position;
+use tiltak::search::{MctsSetting, MonteCarloTree};
+
+const NODES: u32 = 500_000;
+
+fn bench_search(name: &str, settings: MctsSetting<6>) {
+    let start_time = Instant::now();
+    let mut tree = MonteCarloTree::new(<Position<6>>::start_position(), settings);
+    for _ in 0..NODES {
+        tree.select().unwrap();
+    }
+    black_box(tree.best_move());
+    let elapsed = start_time.elapsed();
+    println!(
+        "{}: {} nodes in {:.2}s, {:.1} knps",
+        name,
+        NODES,
+        elapsed.as_secs_f64(),
+        NODES as f64 / elapsed.as_secs_f64() / 1000.0
+    );
+}
+
+fn main() {
+    let settings = MctsSetting::default().arena_size_for_nodes(NODES);
+    for _ in 0..3 {
+        bench_search("Fixed arena", settings.clone());
+        bench_search("Growable arena", settings.clone().growable_arena());
+    }
+}
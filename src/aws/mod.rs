@@ -1,8 +1,10 @@
 use std::time;
 
+use pgn_traits::PgnPosition;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::position::Position;
 use crate::search::TimeControl;
 
 #[cfg(feature = "aws-lambda-runtime")]
@@ -21,6 +23,28 @@ pub struct Event {
     pub rollout_temperature: f64,
 }
 
+impl Event {
+    /// A request to search `position`, with its size, TPS and komi filled in.
+    /// The TPS captures the whole position, so `moves` is left empty.
+    /// Noise and rollouts are disabled.
+    pub fn from_position<const S: usize>(
+        position: &Position<S>,
+        time_control: TimeControl,
+    ) -> Self {
+        Event {
+            size: S,
+            tps: Some(position.to_fen()),
+            moves: vec![],
+            time_control,
+            komi: position.komi().into(),
+            eval_komi: None,
+            dirichlet_noise: None,
+            rollout_depth: 0,
+            rollout_temperature: 0.2,
+        }
+    }
+}
+
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Output {
     pub pv: Vec<String>,
@@ -70,8 +70,7 @@ pub fn handle_aws_event_generic<const S: usize>(e: Event) -> Result<Output, Erro
     .add_rollout_depth(e.rollout_depth)
     .add_rollout_temperature(e.rollout_temperature)
     .mem_usage(2_usize.pow(30))
-    .add_value_params(<Position<S>>::value_params(eval_komi))
-    .add_policy_params(<Position<S>>::policy_params(eval_komi));
+    .eval_komi(eval_komi);
 
     let start_time = Instant::now();
 
@@ -98,12 +97,7 @@ pub fn handle_aws_event_generic<const S: usize>(e: Event) -> Result<Output, Erro
         }
         TimeControl::FixedNodes(nodes) => {
             let mut tree = search::MonteCarloTree::new(position, settings);
-            for _ in 0..nodes {
-                if let Err(err) = tree.select() {
-                    eprintln!("Warning: {err}");
-                    break;
-                }
-            }
+            tree.select_n(nodes).unwrap();
             let score = 1.0 - tree.best_move().unwrap().1;
             let pv = tree.pv().map(|mv| mv.to_string()).collect();
             Ok(Output {
@@ -25,7 +25,7 @@ use tiltak::position::{
 use tiltak::position::{Position, Stack};
 use tiltak::ptn::{Game, PtnMove};
 use tiltak::search::{cp_to_win_percentage, MctsSetting};
-use tiltak::{minmax, ptn};
+use tiltak::{minmax, move_gen, ptn};
 use tiltak::{position, search};
 
 #[cfg(test)]
@@ -181,6 +181,7 @@ fn main() {
             "bench2" => bench2(),
             "bench_mcts" => bench_mcts(),
             "bench_old" => bench_old(),
+            "bench_move_gen" => bench_move_gen(),
             "selfplay" => mcts_selfplay(time::Duration::from_secs(10)),
             "process_ptn" => process_ptn::<6>("games_6s_2komi_all.ptn"),
             "value_params" => {
@@ -565,11 +566,10 @@ fn generate_openings<const S: usize>(
     positions: &mut HashSet<Position<S>>,
     depth: u8,
 ) -> Vec<Vec<Move<S>>> {
-    use tiltak::position::ExpMove;
+    use tiltak::move_gen::MoveFilter;
 
     let mut moves = vec![];
-    position.generate_moves(&mut moves);
-    moves.retain(|mv| matches!(mv.expand(), ExpMove::Place(Role::Flat, _)));
+    position.generate_moves_filtered(&mut moves, MoveFilter::FLAT_PLACEMENTS);
     moves
         .into_iter()
         .flat_map(|mv| {
@@ -727,19 +727,8 @@ fn analyze_position<const S: usize>(position: &Position<S>) {
     println!("{:?}", position);
     println!("Komi: {}", position.komi());
 
-    // Change which sets of eval parameters to use in search
-    // Eval komi other than 0 or 2 will crash, since parameters are only trained for those two komi
-    // The raw search still uses the "real" komi to determine the game result at terminal nodes
-    let eval_komi = Komi::from_half_komi(match position.komi().half_komi() {
-        -1 => 0,
-        0 => 0,
-        1 => 0,
-        3 => 4,
-        4 => 4,
-        5 => 4,
-        _ => panic!("No static eval params for komi {}", position.komi()),
-    })
-    .unwrap();
+    // Parameters are only trained for 0 and 2 komi, other komis are evaluated with interpolated parameters
+    let eval_komi = position.komi();
 
     assert_eq!(position.game_result(), None, "Cannot analyze finished game");
 
@@ -982,6 +971,18 @@ fn bench_position<const S: usize>(position: Position<S>, nodes: u32) {
     );
 }
 
+fn bench_move_gen() {
+    for (name, position) in move_gen::benchmark_positions() {
+        let timing = move_gen::benchmark(&position, 1_000_000);
+        println!(
+            "{}: {:.0}ns per position, {:.1}M moves/s",
+            name,
+            timing.time_per_iteration().as_nanos(),
+            timing.moves_per_second() / 1_000_000.0
+        );
+    }
+}
+
 fn bench_old() {
     const NODES: u64 = 1_000_000;
     let start_time = time::Instant::now();
@@ -3,7 +3,7 @@ use std::io::{BufRead, Result, Write};
 use std::net::TcpStream;
 use std::str::FromStr;
 use std::time::Duration;
-use std::{io, net, thread};
+use std::{error, fmt, io, net, thread};
 
 use board_game_traits::{Color, GameResult, Position as PositionTrait};
 use bufstream::BufStream;
@@ -22,6 +22,9 @@ use tiltak::ptn::{Game, PtnMove};
 use tiltak::search;
 use tiltak::search::MctsSetting;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct PlaytakSettings {
     default_seek_size: usize,
@@ -517,37 +520,75 @@ struct PlaytakSession {
     ping_thread: Option<thread::JoinHandle<io::Result<()>>>,
 }
 
+/// The parameters of a new game, from the `Game Start` message sent by the server
 #[derive(Debug, PartialEq, Eq)]
-struct PlaytakGame<'a> {
+struct PlaytakGameStart<'a> {
     game_no: u64,
     size: usize,
     white_player: &'a str,
     black_player: &'a str,
     our_color: Color,
     time_left: Duration,
-    increment: Duration,
     komi: Komi,
 }
 
-impl<'a> PlaytakGame<'a> {
-    pub fn from_playtak_game_words(words: &[&'a str], increment: Duration) -> PlaytakGame<'a> {
-        PlaytakGame {
-            game_no: u64::from_str(words[2]).unwrap(),
-            size: usize::from_str(words[3]).unwrap(),
+/// An error from parsing a message from the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ParseError {
+    line: String,
+    reason: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Couldn't parse \"{}\": {}",
+            self.line.trim(),
+            self.reason
+        )
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl<'a> PlaytakGameStart<'a> {
+    /// Parse a line like `Game Start 12 6 alice vs bob white 900 4`.
+    /// Komi may be omitted, in which case it defaults to 0. Any fields after komi are ignored.
+    /// The server does not send the increment, so it must be tracked separately.
+    pub fn parse(line: &'a str) -> std::result::Result<Self, ParseError> {
+        let error = |reason: &str| ParseError {
+            line: line.to_string(),
+            reason: reason.to_string(),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.len() < 9 || words[0] != "Game" || words[1] != "Start" || words[5] != "vs" {
+            return Err(error(
+                "Expected \"Game Start <no> <size> <white> vs <black> <color> <time>\"",
+            ));
+        }
+        let komi = match words.get(9) {
+            Some(komi_str) => {
+                let half_komi = i8::from_str(komi_str).map_err(|err| error(&err.to_string()))?;
+                Komi::from_half_komi(half_komi).map_err(|err| error(&err))?
+            }
+            None => Komi::default(),
+        };
+        Ok(PlaytakGameStart {
+            game_no: u64::from_str(words[2]).map_err(|err| error(&err.to_string()))?,
+            size: usize::from_str(words[3]).map_err(|err| error(&err.to_string()))?,
             white_player: words[4],
             black_player: words[6],
             our_color: match words[7] {
                 "white" => Color::White,
                 "black" => Color::Black,
-                color => panic!("Bad color \"{}\"", color),
+                color => return Err(error(&format!("Bad color \"{}\"", color))),
             },
-            time_left: Duration::from_secs(u64::from_str(words[8]).unwrap()),
-            increment,
-            komi: words
-                .get(9)
-                .map(|komi_str| Komi::from_half_komi(i8::from_str(komi_str).unwrap()).unwrap())
-                .unwrap_or_default(),
-        }
+            time_left: Duration::from_secs(
+                u64::from_str(words[8]).map_err(|err| error(&err.to_string()))?,
+            ),
+            komi,
+        })
     }
 }
 
@@ -692,23 +733,25 @@ impl PlaytakSession {
             }
             match words[0] {
                 "Game" => {
-                    let playtak_game = PlaytakGame::from_playtak_game_words(
-                        &words,
-                        playtak_settings.seek_increment,
-                    );
+                    let playtak_game = PlaytakGameStart::parse(&input)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    let increment = playtak_settings.seek_increment;
                     let (updated_seek_size, updated_seek_color) = match playtak_game.size {
                         4 => self.play_game::<4>(
                             playtak_game,
+                            increment,
                             playtak_settings,
                             restoring_previous_session,
                         )?,
                         5 => self.play_game::<5>(
                             playtak_game,
+                            increment,
                             playtak_settings,
                             restoring_previous_session,
                         )?,
                         6 => self.play_game::<6>(
                             playtak_game,
+                            increment,
                             playtak_settings,
                             restoring_previous_session,
                         )?,
@@ -779,8 +822,9 @@ impl PlaytakSession {
             }
             match words[0] {
                 "Game" => {
-                    let playtak_game = PlaytakGame::from_playtak_game_words(&words, increment);
-                    self.play_game::<S>(playtak_game, playtak_settings, false)?;
+                    let playtak_game = PlaytakGameStart::parse(&input)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    self.play_game::<S>(playtak_game, increment, playtak_settings, false)?;
                     return Ok(());
                 }
 
@@ -807,7 +851,8 @@ impl PlaytakSession {
     /// Mutually recursive with `seek_game`, which places a new seek as soon as the game finishes.
     fn play_game<const S: usize>(
         &mut self,
-        game: PlaytakGame,
+        game: PlaytakGameStart,
+        increment: Duration,
         playtak_settings: PlaytakSettings,
         mut restoring_previous_session: bool,
     ) -> io::Result<(usize, Option<Color>)> {
@@ -818,7 +863,7 @@ impl PlaytakSession {
             game.black_player,
             game.our_color,
             game.time_left.as_secs(),
-            game.increment.as_secs_f32(),
+            increment.as_secs_f32(),
             game.komi
         );
         let mut next_seek_size = playtak_settings.default_seek_size;
@@ -850,12 +895,7 @@ impl PlaytakSession {
                             playtak_settings.to_mcts_setting(position.half_moves_played())
                             .arena_size_for_nodes(fixed_nodes as u32);
                         let mut tree = search::MonteCarloTree::new(position.clone(), settings);
-                        for _ in 0..fixed_nodes {
-                            if let Err(err) = tree.select() {
-                                eprintln!("Warning: {err}");
-                                break;
-                            }
-                        }
+                        tree.select_n(fixed_nodes).unwrap();
 
                         // Wait for a bit
                         let mut rng = rand::thread_rng();
@@ -868,15 +908,15 @@ impl PlaytakSession {
                             let maximum_time = if let Some(target_move_time) =  playtak_settings.target_move_time {
                                 if let Some((trigger_move, _)) = playtak_settings.extra_time {
                                     if position.half_moves_played() / 2 > trigger_move as usize {
-                                        (our_time_left / 6 + game.increment / 2).min(6 * target_move_time)
+                                        (our_time_left / 6 + increment / 2).min(6 * target_move_time)
                                     } else {
-                                        (our_time_left / 6 + game.increment / 2).min(2 * target_move_time)
+                                        (our_time_left / 6 + increment / 2).min(2 * target_move_time)
                                     }
                                 } else {
-                                (our_time_left / 6 + game.increment / 2).min(2 * target_move_time)
+                                (our_time_left / 6 + increment / 2).min(2 * target_move_time)
                                 }
                             } else {
-                                our_time_left / 6 + game.increment / 2
+                                our_time_left / 6 + increment / 2
                             };
 
                             // Give enough memory for a CPU calculating at roughly 200K nps.
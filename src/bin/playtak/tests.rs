@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use board_game_traits::Color;
+use tiltak::position::Komi;
+
+use super::PlaytakGameStart;
+
+#[test]
+fn parse_game_start_test() {
+    let line = "Game Start 12 6 alice vs bob white 900 4";
+    assert_eq!(
+        PlaytakGameStart::parse(line),
+        Ok(PlaytakGameStart {
+            game_no: 12,
+            size: 6,
+            white_player: "alice",
+            black_player: "bob",
+            our_color: Color::White,
+            time_left: Duration::from_secs(900),
+            komi: Komi::from_half_komi(4).unwrap(),
+        })
+    );
+
+    // Komi is optional, and any trailing fields are ignored
+    let game_start = PlaytakGameStart::parse("Game Start 3 5 alice vs bob black 180").unwrap();
+    assert_eq!(game_start.our_color, Color::Black);
+    assert_eq!(game_start.komi, Komi::default());
+    let game_start =
+        PlaytakGameStart::parse("Game Start 3 5 alice vs bob black 180 0 20 1 0\n").unwrap();
+    assert_eq!(game_start.time_left, Duration::from_secs(180));
+    assert_eq!(game_start.komi, Komi::default());
+}
+
+#[test]
+fn parse_malformed_game_start_test() {
+    for line in [
+        "",
+        "Game#12 Show",
+        "Game Over 12 6 alice vs bob white 900",
+        "Game Start 12 6 alice versus bob white 900",
+        "Game Start 12 6 alice vs bob green 900",
+    ] {
+        let err = PlaytakGameStart::parse(line).unwrap_err();
+        assert_eq!(err.line, line);
+    }
+}
+
+#[test]
+fn parse_game_start_bad_fields_test() {
+    for line in [
+        // Missing fields
+        "Game Start 12 6 alice vs bob white",
+        "Game Start 12 6 alice vs bob",
+        // Non-numeric fields
+        "Game Start twelve 6 alice vs bob white 900",
+        "Game Start 12 six alice vs bob white 900",
+        "Game Start 12 6 alice vs bob white 15:00",
+        "Game Start 12 6 alice vs bob white 900 two",
+        // Negative game number, and a komi that is out of range
+        "Game Start -12 6 alice vs bob white 900",
+        "Game Start 12 6 alice vs bob white 900 100",
+    ] {
+        let err = PlaytakGameStart::parse(line).unwrap_err();
+        assert!(err.to_string().contains(line), "{}", err);
+    }
+}
@@ -22,7 +22,11 @@ pub fn main() {
 
     println!("id name Tiltak");
     println!("id author Morten Lohne");
-    println!("option name HalfKomi type spin default 0 min -10 max 10");
+    println!(
+        "option name HalfKomi type spin default 0 min {} max {}",
+        Komi::half_komi_range().start(),
+        Komi::half_komi_range().end()
+    );
     println!("teiok");
 
     // Position stored in a `dyn Any` variable, because it can be any size
@@ -44,14 +48,25 @@ pub fn main() {
                 .join(" ")
                     == "name HalfKomi value"
                 {
-                    if let Some(k) = words
-                        .next()
-                        .and_then(|komi_string| komi_string.parse::<i8>().ok())
+                    let value = words.next().unwrap_or_default();
+                    match value
+                        .parse::<i8>()
+                        .map_err(|err| err.to_string())
                         .and_then(Komi::from_half_komi)
                     {
-                        komi = k;
-                    } else {
-                        panic!("Invalid komi setting \"{}\"", line);
+                        Ok(k) => {
+                            komi = k;
+                            // Also apply the komi to a position that has already been set up
+                            match size {
+                                Some(4) => set_position_komi::<4>(&mut position, komi),
+                                Some(5) => set_position_komi::<5>(&mut position, komi),
+                                Some(6) => set_position_komi::<6>(&mut position, komi),
+                                _ => (),
+                            }
+                        }
+                        Err(err) => {
+                            println!("info string Invalid HalfKomi value \"{}\": {}", value, err)
+                        }
                     }
                 } else {
                     panic!("Invalid setoption string \"{}\"", line);
@@ -100,6 +115,15 @@ pub fn main() {
     }
 }
 
+fn set_position_komi<const S: usize>(position: &mut Option<Box<dyn Any>>, komi: Komi) {
+    if let Some(position) = position
+        .as_mut()
+        .and_then(|position| position.downcast_mut::<Position<S>>())
+    {
+        position.set_komi(komi);
+    }
+}
+
 fn parse_position_string<const S: usize>(line: &str, komi: Komi) -> Position<S> {
     let mut words_iter = line.split_whitespace();
     words_iter.next(); // position
@@ -143,14 +167,7 @@ fn parse_go_string<const S: usize>(line: &str, position: &Position<S>, is_slateb
 
             for i in 0.. {
                 let nodes_to_search = (200.0 * f64::powf(1.26, i as f64)) as u64;
-                let mut oom = false;
-                for _ in 0..nodes_to_search {
-                    if let Err(err) = tree.select() {
-                        eprintln!("Warning: {err}");
-                        oom = true;
-                        break;
-                    }
-                }
+                let oom = tree.select_n(nodes_to_search).unwrap() < nodes_to_search;
                 let (best_move, best_score) = tree.best_move().unwrap();
                 let pv: Vec<_> = tree.pv().collect();
                 println!(
@@ -1,7 +1,7 @@
 use std::path::Path;
 use std::process::exit;
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
 
 use tiltak::evaluation::parameters::{
     self, NUM_POLICY_FEATURES_4S, NUM_POLICY_FEATURES_5S, NUM_POLICY_FEATURES_6S,
@@ -47,7 +47,12 @@ fn main() {
                     .help("Number of games per training batch. Eval parameters are re-tuned after each batch.")
                     .default_value("1000")
                     .num_args(1)
-                    .value_parser(clap::value_parser!(u64))))
+                    .value_parser(clap::value_parser!(u64)))
+            .arg(
+                Arg::new("opening")
+                    .long("opening")
+                    .help("Space-separated PTN moves that every selfplay game starts with, for example \"a1 e5\". Each pair of games plays the opening with colors swapped.")
+                    .num_args(1)))
         .subcommand(Command::new("selfplay-from-scratch")
             .about("Tune value and policy constants from randomly initialized values by playing against itself. Will write the games to text files in the working directory.")
             .arg(
@@ -63,7 +68,12 @@ fn main() {
                     .help("Number of games per training batch. Eval parameters are re-tuned after each batch.")
                     .default_value("1000")
                     .num_args(1)
-                    .value_parser(clap::value_parser!(u64))))
+                    .value_parser(clap::value_parser!(u64)))
+            .arg(
+                Arg::new("opening")
+                    .long("opening")
+                    .help("Space-separated PTN moves that every selfplay game starts with, for example \"a1 e5\". Each pair of games plays the opening with colors swapped.")
+                    .num_args(1)))
         .subcommand(Command::new("continue-selfplay")
             .about("Continue selfplay training")
             .arg(
@@ -80,6 +90,11 @@ fn main() {
                     .default_value("1000")
                     .num_args(1)
                     .value_parser(clap::value_parser!(u64)))
+            .arg(
+                Arg::new("opening")
+                    .long("opening")
+                    .help("Space-separated PTN moves that every selfplay game starts with, for example \"a1 e5\". Each pair of games plays the opening with colors swapped.")
+                    .num_args(1))
             .arg(Arg::new("training-id")
                 .long("training-id")
                 .num_args(1)
@@ -143,6 +158,7 @@ fn main() {
                         batch_size,
                         num_games_for_tuning,
                         nodes_per_game,
+                        fixed_opening: fixed_opening(arg),
                     };
                     match size {
                         4 => training::train_perpetually::<
@@ -206,6 +222,7 @@ fn main() {
                         batch_size,
                         num_games_for_tuning,
                         nodes_per_game,
+                        fixed_opening: fixed_opening(arg),
                     };
                     match size {
                         4 => training::train_from_scratch::<
@@ -243,6 +260,7 @@ fn main() {
                 batch_size,
                 num_games_for_tuning,
                 nodes_per_game,
+                fixed_opening: fixed_opening(arg),
             };
             match size {
                 4 => {
@@ -376,3 +394,9 @@ fn main() {
         }
     }
 }
+
+fn fixed_opening(arg: &ArgMatches) -> Vec<String> {
+    arg.get_one::<String>("opening")
+        .map(|opening| opening.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
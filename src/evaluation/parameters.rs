@@ -236,6 +236,106 @@ impl<const S: usize> ValueIndexes<S> {
     }
 }
 
+impl<const S: usize> ValueIndexes<S> {
+    /// The name and indexes of every value feature, in parameter order, excluding padding
+    pub fn named_features(&self) -> Vec<(&'static str, IndexPair)> {
+        vec![
+            ("first_ply", self.first_ply),
+            ("second_ply", self.second_ply),
+            ("flat_psqt_opening", self.flat_psqt_opening),
+            ("flat_psqt_middlegame", self.flat_psqt_middlegame),
+            ("flat_psqt_endgame", self.flat_psqt_endgame),
+            ("wall_psqt_opening", self.wall_psqt_opening),
+            ("wall_psqt_middlegame", self.wall_psqt_middlegame),
+            ("wall_psqt_endgame", self.wall_psqt_endgame),
+            ("cap_psqt_opening", self.cap_psqt_opening),
+            ("cap_psqt_middlegame", self.cap_psqt_middlegame),
+            ("cap_psqt_endgame", self.cap_psqt_endgame),
+            ("supports_psqt_opening", self.supports_psqt_opening),
+            ("supports_psqt_middlegame", self.supports_psqt_middlegame),
+            ("supports_psqt_endgame", self.supports_psqt_endgame),
+            ("captives_psqt_opening", self.captives_psqt_opening),
+            ("captives_psqt_middlegame", self.captives_psqt_middlegame),
+            ("captives_psqt_endgame", self.captives_psqt_endgame),
+            ("flat_win_this_ply", self.flat_win_this_ply),
+            ("flat_win_next_ply", self.flat_win_next_ply),
+            ("flat_win_two_ply", self.flat_win_two_ply),
+            ("flat_win_three_ply", self.flat_win_three_ply),
+            ("one_reserve_left_us", self.one_reserve_left_us),
+            ("one_reserve_left_them", self.one_reserve_left_them),
+            (
+                "shallow_supports_per_piece",
+                self.shallow_supports_per_piece,
+            ),
+            (
+                "shallow_supports_per_piece_mobility",
+                self.shallow_supports_per_piece_mobility,
+            ),
+            (
+                "shallow_supports_per_piece_mob_scaled",
+                self.shallow_supports_per_piece_mob_scaled,
+            ),
+            ("deep_supports_per_piece", self.deep_supports_per_piece),
+            (
+                "shallow_captives_per_piece",
+                self.shallow_captives_per_piece,
+            ),
+            (
+                "shallow_captives_per_piece_mobility",
+                self.shallow_captives_per_piece_mobility,
+            ),
+            (
+                "shallow_captives_per_piece_mob_scaled",
+                self.shallow_captives_per_piece_mob_scaled,
+            ),
+            ("deep_captives_per_piece", self.deep_captives_per_piece),
+            (
+                "to_move_opening_flatstone_lead",
+                self.to_move_opening_flatstone_lead,
+            ),
+            (
+                "to_move_middlegame_flatstone_lead",
+                self.to_move_middlegame_flatstone_lead,
+            ),
+            (
+                "to_move_endgame_flatstone_lead",
+                self.to_move_endgame_flatstone_lead,
+            ),
+            ("i_number_of_groups", self.i_number_of_groups),
+            ("critical_squares", self.critical_squares),
+            (
+                "critical_square_cap_attack",
+                self.critical_square_cap_attack,
+            ),
+            ("winning_spread_to_move", self.winning_spread_to_move),
+            (
+                "winning_flat_spread_not_to_move",
+                self.winning_flat_spread_not_to_move,
+            ),
+            (
+                "winning_cap_spread_not_to_move",
+                self.winning_cap_spread_not_to_move,
+            ),
+            ("flat_next_to_our_stack", self.flat_next_to_our_stack),
+            ("wall_next_to_our_stack", self.wall_next_to_our_stack),
+            ("cap_next_to_our_stack", self.cap_next_to_our_stack),
+            ("num_lines_occupied", self.num_lines_occupied),
+            ("line_control_empty", self.line_control_empty),
+            (
+                "line_control_their_blocking_piece",
+                self.line_control_their_blocking_piece,
+            ),
+            ("line_control_other", self.line_control_other),
+            ("line_control_guarded_flat", self.line_control_guarded_flat),
+            ("line_control_guarded_wall", self.line_control_guarded_wall),
+            ("line_control_guarded_cap", self.line_control_guarded_cap),
+            ("sidelined_cap", self.sidelined_cap),
+            ("fully_isolated_cap", self.fully_isolated_cap),
+            ("semi_isolated_cap", self.semi_isolated_cap),
+        ]
+    }
+}
+
 pub const VALUE_INDEXES_4S: ValueIndexes<4> = ValueIndexes::new();
 pub const VALUE_INDEXES_5S: ValueIndexes<5> = ValueIndexes::new();
 pub const VALUE_INDEXES_6S: ValueIndexes<6> = ValueIndexes::new();
@@ -551,6 +651,35 @@ impl<const S: usize> ValueApplier for IncrementalValue<S> {
     }
 }
 
+/// Keeps each parameter's contribution to the value separately.
+/// Unlike `Value`, the features are not accumulated as `f16`, so the contributions add up to the same value as `IncrementalValue`.
+#[derive(Debug, Clone)]
+pub struct ValueContributions<const S: usize> {
+    pub contributions: Vec<f32>,
+    parameters: &'static [f32],
+}
+
+impl<const S: usize> ValueApplier for ValueContributions<S> {
+    fn new(parameters: &'static [f32]) -> Self {
+        ValueContributions {
+            contributions: vec![0.0; num_value_features::<S>() / 2],
+            parameters,
+        }
+    }
+    fn eval(&mut self, index_pair: IndexPair, index: usize, val: f16) {
+        index_pair.as_mut_slice(&mut self.contributions)[index] +=
+            index_pair.as_slice(self.parameters)[index] * val.to_f32()
+    }
+
+    fn finish(&mut self) -> f32 {
+        let total_value = self.contributions.iter().sum::<f32>();
+
+        self.contributions.fill(0.0);
+
+        total_value
+    }
+}
+
 pub trait PolicyApplier {
     fn new(parameters: &'static [f32]) -> Self;
     fn eval(&mut self, index_pair: IndexPair, index: usize, val: f16);
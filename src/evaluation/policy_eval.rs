@@ -8,14 +8,14 @@ use crate::position::bitboard::BitBoard;
 use crate::position::color_trait::{BlackTr, ColorTr, WhiteTr};
 use crate::position::Role::{Cap, Flat, Wall};
 use crate::position::{
-    lookup_square_symmetries, GroupData, MovementSynopsis, Piece, Position, Role,
+    lookup_square_symmetries, GroupData, Komi, MovementSynopsis, Piece, Position, Role,
 };
 use crate::position::{squares_iterator, Move};
 use crate::position::{AbstractBoard, Direction};
 use crate::position::{Direction::*, ExpMove};
 use crate::position::{GroupEdgeConnection, Square};
 
-use super::parameters::{policy_indexes, PolicyApplier};
+use super::parameters::{policy_indexes, IncrementalPolicy, PolicyApplier};
 
 const POLICY_BASELINE: f32 = 0.05;
 
@@ -98,6 +98,66 @@ impl<const S: usize> Position<S> {
         }
     }
 
+    /// The policy's prior probability for `mv`, scored among all legal moves in the position, or 0.0 if the move is illegal.
+    ///
+    /// Some policy features depend on the other moves being scored: Whether a spread has the highest
+    /// flat count differential (FCD) among the moves, and whether any of the other moves wins immediately.
+    /// The probabilities are also normalized over all the moves. Scoring a move alone, or among a subset of the moves,
+    /// with `features_for_moves` therefore gives a different result. This always scores the full list of legal moves,
+    /// so the result is the same prior that the search uses.
+    pub fn policy_prior_for_move(&self, mv: Move<S>, komi: Komi) -> f32 {
+        self.moves_by_policy(komi)
+            .into_iter()
+            .find(|(legal_move, _)| *legal_move == mv)
+            .map_or(0.0, |(_, prior)| prior)
+    }
+
+    /// All legal moves with their normalized policy priors, sorted from most to least likely.
+    ///
+    /// This allocates new buffers and recomputes the group data on every call, so it is meant for displays and analysis.
+    /// Hot loops should call `generate_moves_with_probabilities` with re-used buffers instead.
+    pub fn moves_by_policy(&self, komi: Komi) -> Vec<(Move<S>, f32)> {
+        let mut moves: Vec<(Move<S>, f32)> = self
+            .moves_with_policy(komi)
+            .into_iter()
+            .map(|(mv, prior)| (mv, prior.to_f32()))
+            .collect();
+        moves.sort_by(|(_, prior1), (_, prior2)| prior1.total_cmp(prior2).reverse());
+        moves
+    }
+
+    /// The `k` legal moves with the highest policy priors, sorted from most to least likely.
+    /// Only the selected moves are sorted, which is cheaper than `moves_by_policy` when `k` is small.
+    pub fn top_policy_moves(&self, k: usize, komi: Komi) -> Vec<(Move<S>, f16)> {
+        if k == 0 {
+            return vec![];
+        }
+        let mut moves = self.moves_with_policy(komi);
+        let by_descending_prior = |(_, prior1): &(Move<S>, f16), (_, prior2): &(Move<S>, f16)| {
+            prior1.to_f32().total_cmp(&prior2.to_f32()).reverse()
+        };
+        if k < moves.len() {
+            moves.select_nth_unstable_by(k - 1, by_descending_prior);
+            moves.truncate(k);
+        }
+        moves.sort_by(by_descending_prior);
+        moves
+    }
+
+    fn moves_with_policy(&self, komi: Komi) -> Vec<(Move<S>, f16)> {
+        let group_data = self.group_data();
+        let mut moves = vec![];
+        self.generate_moves_with_params::<IncrementalPolicy<S>>(
+            Self::policy_params(komi),
+            &group_data,
+            &mut vec![],
+            &mut moves,
+            &mut vec![],
+            &mut vec![],
+        );
+        moves
+    }
+
     pub fn features_for_moves<P: PolicyApplier>(
         &self,
         policies: &mut [P],
@@ -203,7 +263,7 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, P: PolicyApplier, const
 ) {
     let indexes = policy_indexes::<S>();
     // If it's the first move, give every move equal probability
-    if position.half_moves_played() < 2 {
+    if position.is_placement_phase() {
         return;
     }
 
@@ -237,14 +297,24 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, P: PolicyApplier, const
                     their_flatcount,
                     policy,
                 );
-            }
-            // TODO: These two bonuses don't take komi into account, but they should
-            else if Us::stones_left(position) == 2 && Us::caps_left(position) == 0 {
+            } else if Us::stones_left(position) == 2 && Us::caps_left(position) == 0 {
                 policy.eval_one(indexes.two_flats_left, 0);
-                policy.eval_i8(indexes.two_flats_left, 1, our_flat_lead_after_move);
+                policy.eval_i8(
+                    indexes.two_flats_left,
+                    1,
+                    position
+                        .komi()
+                        .komi_adjusted_flat_lead(Us::color(), our_flat_lead_after_move),
+                );
             } else if Us::stones_left(position) == 3 && Us::caps_left(position) == 0 {
                 policy.eval_one(indexes.three_flats_left, 0);
-                policy.eval_i8(indexes.three_flats_left, 1, our_flat_lead_after_move);
+                policy.eval_i8(
+                    indexes.three_flats_left,
+                    1,
+                    position
+                        .komi()
+                        .komi_adjusted_flat_lead(Us::color(), our_flat_lead_after_move),
+                );
             }
 
             let their_open_critical_squares =
@@ -427,7 +497,7 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, P: PolicyApplier, const
                 // If square is next to a road stone laid on our last turn
                 if let Some((last_role, last_square)) = our_last_placement(position) {
                     if last_role == Flat || last_role == Cap {
-                        if square.neighbors_bitboard().get_square(last_square) {
+                        if square.neighbors_bitboard().contains(last_square) {
                             policy.eval_one(indexes.next_to_our_last_stone, 0);
                         } else if square.rank().abs_diff(last_square.rank()) == 1
                             && square.file().abs_diff(last_square.file()) == 1
@@ -440,7 +510,7 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, P: PolicyApplier, const
                 // If square is next to a road stone laid on their last turn
                 if let Some((last_role, last_square)) = their_last_placement(position) {
                     if last_role == Flat {
-                        if square.neighbors_bitboard().get_square(last_square) {
+                        if square.neighbors_bitboard().contains(last_square) {
                             policy.eval_one(indexes.next_to_their_last_stone, 0);
                         } else if square.rank().abs_diff(last_square.rank()) == 1
                             && square.file().abs_diff(last_square.file()) == 1
@@ -506,15 +576,8 @@ fn features_for_move_colortr<Us: ColorTr, Them: ColorTr, P: PolicyApplier, const
                     {
                         let stack = position.get_stack(neighbour);
                         let top_stone = stack.top_stone().unwrap();
-                        let mut captives: u8 = 0;
-                        let mut reserves: u8 = 0;
-                        for piece in stack.into_iter().take(stack.len() as usize - 1) {
-                            if Us::is_our_piece(piece) {
-                                captives += 1;
-                            } else {
-                                reserves += 1;
-                            }
-                        }
+                        let captives = stack.captives(Them::color());
+                        let reserves = stack.reserves_of(Them::color());
                         let index = top_stone.role().disc() * 2;
                         match role {
                             Flat => unreachable!(),
@@ -569,8 +569,8 @@ pub fn static_eval_game_phase<const S: usize, V: ValueApplier>(
     let mut num_files_occupied_black = 0;
 
     for i in 0..(S as u8) {
-        let rank = BitBoard::full().rank::<S>(i);
-        let file = BitBoard::full().file::<S>(i);
+        let rank = BitBoard::full::<S>().rank::<S>(i);
+        let file = BitBoard::full::<S>().file::<S>(i);
         line_score::<WhiteTr, BlackTr, V, S>(position, group_data, rank, i, white_value);
         line_score::<BlackTr, WhiteTr, V, S>(position, group_data, rank, i, black_value);
         line_score::<WhiteTr, BlackTr, V, S>(position, group_data, file, i, white_value);
@@ -825,8 +825,8 @@ fn critical_squares_eval<Us: ColorTr, Them: ColorTr, V: ValueApplier, const S: u
     let file = critical_square.file();
 
     let capstone_square_in_line = {
-        let capstone_in_rank = BitBoard::full().rank::<S>(rank) & Us::caps(group_data);
-        let capstone_in_file = BitBoard::full().file::<S>(file) & Us::caps(group_data);
+        let capstone_in_rank = BitBoard::full::<S>().rank::<S>(rank) & Us::caps(group_data);
+        let capstone_in_file = BitBoard::full::<S>().file::<S>(file) & Us::caps(group_data);
         capstone_in_rank
             .occupied_square()
             .or(capstone_in_file.occupied_square())
@@ -897,7 +897,7 @@ fn line_score<Us: ColorTr, Them: ColorTr, V: ValueApplier, const S: usize>(
             for square in (Them::flats(group_data) & line).into_iter() {
                 let (direction, neighbor) = square
                     .direction_neighbors()
-                    .find(|(_, neigh)| !line.get_square(*neigh))
+                    .find(|(_, neigh)| !line.contains(*neigh))
                     .unwrap();
                 if let Some(neighbor_piece) = position.top_stones()[neighbor].filter(|piece| {
                     Them::is_our_piece(*piece)
@@ -1,13 +1,373 @@
-use crate::position::color_trait::ColorTr;
+use crate::position::color_trait::{BlackTr, ColorTr, WhiteTr};
 use crate::position::{
     squares_iterator, Direction, Movement, Position, Role::*, Square, StackMovement,
 };
-use crate::position::{Move, Piece};
+use crate::position::{
+    BitBoard, ExpMove, GroupData, GroupEdgeConnection, IllegalMoveReason, Move, Piece, RoadOutcome,
+};
 use arrayvec::ArrayVec;
-use board_game_traits::Position as PositionTrait;
-use std::iter;
+use board_game_traits::{Color, GameResult, Position as PositionTrait};
+use pgn_traits::PgnPosition;
+use std::time::{Duration, Instant};
+use std::{hint, iter, ops};
 
 impl<const S: usize> Position<S> {
+    /// Lazily iterates over all legal moves, in the same order as `generate_moves`.
+    /// Moves are generated one square at a time, so stopping early skips the work for the remaining squares.
+    pub fn moves_iter(&self) -> MovesIter<'_, S> {
+        MovesIter {
+            position: self,
+            next_square: 0,
+            square_moves: vec![],
+        }
+    }
+
+    /// Adds all legal moves originating from `square` to `moves`: The placements if the square is empty,
+    /// or the spreads of the stack if the side to move controls it.
+    /// In the first two plies, the only legal moves are flat placements.
+    pub fn generate_moves_for_square(&self, square: Square<S>, moves: &mut Vec<Move<S>>) {
+        match self.side_to_move() {
+            _ if self.is_placement_phase() => {
+                if self.stack_heights()[square] == 0 {
+                    moves.push(Move::placement(Flat, square));
+                }
+            }
+            Color::White => {
+                self.generate_moves_for_square_colortr::<_, WhiteTr, BlackTr>(moves, square)
+            }
+            Color::Black => {
+                self.generate_moves_for_square_colortr::<_, BlackTr, WhiteTr>(moves, square)
+            }
+        }
+    }
+
+    /// Like `generate_moves`, but writes into a fixed-capacity `ArrayVec`, so no heap allocation occurs.
+    ///
+    /// To fit the moves of any position, `N` must be at least `max_legal_moves(S)`,
+    /// which is 108, 448, 1500, 4464, 12348 and 32512 for sizes 3 through 8.
+    /// Panics if the position has more legal moves than the remaining capacity.
+    pub fn generate_moves_into_arrayvec<const N: usize>(&self, moves: &mut ArrayVec<Move<S>, N>) {
+        self.generate_moves(moves)
+    }
+
+    /// Like `generate_moves`, but only generates the kinds of moves included in `filter`.
+    /// Spreads are not generated at all unless requested, which makes placement-only generation much cheaper.
+    pub fn generate_moves_filtered<E: Extend<Move<S>>>(&self, moves: &mut E, filter: MoveFilter) {
+        if filter == MoveFilter::ALL {
+            self.generate_moves(moves);
+            return;
+        }
+        match self.side_to_move() {
+            _ if self.is_placement_phase() => {
+                if filter.contains(MoveFilter::FLAT_PLACEMENTS) {
+                    moves.extend(
+                        squares_iterator::<S>()
+                            .filter(|square| self.stack_heights()[*square] == 0)
+                            .map(|square| Move::placement(Flat, square)),
+                    )
+                }
+            }
+            Color::White => {
+                self.generate_moves_filtered_colortr::<E, WhiteTr, BlackTr>(moves, filter)
+            }
+            Color::Black => {
+                self.generate_moves_filtered_colortr::<E, BlackTr, WhiteTr>(moves, filter)
+            }
+        }
+    }
+
+    fn generate_moves_filtered_colortr<E: Extend<Move<S>>, Us: ColorTr, Them: ColorTr>(
+        &self,
+        moves: &mut E,
+        filter: MoveFilter,
+    ) {
+        for square in squares_iterator::<S>() {
+            match self.top_stones()[square] {
+                None => {
+                    if Us::stones_left(self) > 0 {
+                        if filter.contains(MoveFilter::FLAT_PLACEMENTS) {
+                            moves.extend(iter::once(Move::placement(Flat, square)));
+                        }
+                        if filter.contains(MoveFilter::WALL_PLACEMENTS) {
+                            moves.extend(iter::once(Move::placement(Wall, square)));
+                        }
+                    }
+                    if Us::caps_left(self) > 0 && filter.contains(MoveFilter::CAP_PLACEMENTS) {
+                        moves.extend(iter::once(Move::placement(Cap, square)));
+                    }
+                }
+                Some(_) if filter.contains(MoveFilter::SPREADS) => {
+                    self.generate_moves_for_square_colortr::<E, Us, Them>(moves, square)
+                }
+                Some(_) => (),
+            }
+        }
+    }
+
+    /// The number of legal moves in the position, without generating them into a `Vec`.
+    /// Placements are counted from the empty squares and the pieces in hand,
+    /// so only the spreads need to be enumerated.
+    pub fn count_legal_moves(&self) -> usize {
+        match self.side_to_move() {
+            _ if self.is_placement_phase() => self.empty_square_count() as usize,
+            Color::White => self.count_legal_moves_colortr::<WhiteTr, BlackTr>(),
+            Color::Black => self.count_legal_moves_colortr::<BlackTr, WhiteTr>(),
+        }
+    }
+
+    fn count_legal_moves_colortr<Us: ColorTr, Them: ColorTr>(&self) -> usize {
+        let roles_in_hand = if Us::stones_left(self) > 0 { 2 } else { 0 }
+            + if Us::caps_left(self) > 0 { 1 } else { 0 };
+        let mut counter = MoveCounter(self.empty_square_count() as usize * roles_in_hand);
+        for square in squares_iterator::<S>() {
+            if self.top_stones()[square].is_some() {
+                self.generate_moves_for_square_colortr::<_, Us, Them>(&mut counter, square);
+            }
+        }
+        counter.0
+    }
+
+    /// Like `move_is_legal`, but returns the reason an illegal move is rejected
+    pub fn check_move_legal(&self, mv: Move<S>) -> Result<(), IllegalMoveReason> {
+        if self.is_placement_phase() {
+            return match mv.expand() {
+                ExpMove::Place(_, square) if self.stack_heights()[square] != 0 => {
+                    Err(IllegalMoveReason::SquareOccupied)
+                }
+                ExpMove::Place(Flat, _) => Ok(()),
+                _ => Err(IllegalMoveReason::OpeningMoveNotFlatPlacement),
+            };
+        }
+        let us = self.side_to_move();
+        match mv.expand() {
+            ExpMove::Place(role, square) => {
+                if self.stack_heights()[square] != 0 {
+                    return Err(IllegalMoveReason::SquareOccupied);
+                }
+                let (stones_left, caps_left) = match us {
+                    Color::White => (self.white_reserves_left(), self.white_caps_left()),
+                    Color::Black => (self.black_reserves_left(), self.black_caps_left()),
+                };
+                match role {
+                    Flat | Wall if stones_left == 0 => Err(IllegalMoveReason::NoReservesLeft),
+                    Cap if caps_left == 0 => Err(IllegalMoveReason::NoCapstoneLeft),
+                    _ => Ok(()),
+                }
+            }
+            ExpMove::Move(square, direction, stack_movement) => match self.top_stones()[square] {
+                None => Err(IllegalMoveReason::NoStackAtOrigin),
+                Some(piece) if piece.color() != us => Err(IllegalMoveReason::WrongTurn),
+                Some(piece) => {
+                    let movements: ArrayVec<Movement, 8> = stack_movement.into_iter().collect();
+                    if movements.first().is_some_and(|movement| {
+                        movement.pieces_to_take <= self.stack_heights()[square]
+                    }) {
+                        let mut to = square;
+                        for (i, movement) in movements.iter().enumerate() {
+                            let Some(next_square) = to.go_direction(direction) else {
+                                return Err(IllegalMoveReason::StackMovementInvalid);
+                            };
+                            to = next_square;
+                            // A wall can only be flattened by a capstone moving alone onto the last square
+                            if self.top_stones()[to].map(Piece::role) == Some(Wall)
+                                && !(i == movements.len() - 1
+                                    && piece.role() == Cap
+                                    && movement.pieces_to_take == 1)
+                            {
+                                return Err(IllegalMoveReason::CannotFlattenWall);
+                            }
+                        }
+                    }
+                    let mut legal_moves = vec![];
+                    match us {
+                        Color::White => self
+                            .generate_moves_for_square_colortr::<_, WhiteTr, BlackTr>(
+                                &mut legal_moves,
+                                square,
+                            ),
+                        Color::Black => self
+                            .generate_moves_for_square_colortr::<_, BlackTr, WhiteTr>(
+                                &mut legal_moves,
+                                square,
+                            ),
+                    }
+                    if legal_moves.contains(&mv) {
+                        Ok(())
+                    } else {
+                        Err(IllegalMoveReason::StackMovementInvalid)
+                    }
+                }
+            },
+        }
+    }
+
+    /// Generate only the spreads that capture an opponent's stone or flatten a wall
+    pub fn generate_captures(&self, moves: &mut Vec<Move<S>>) {
+        let mut all_moves = vec![];
+        self.generate_moves(&mut all_moves);
+        moves.extend(all_moves.into_iter().filter(|mv| self.is_capture(*mv)));
+    }
+
+    fn is_capture(&self, mv: Move<S>) -> bool {
+        match mv.expand() {
+            ExpMove::Place(_, _) => false,
+            ExpMove::Move(origin, direction, stack_movement) => {
+                let mut square = origin;
+                (0..stack_movement.len()).any(|_| {
+                    square = square.go_direction(direction).unwrap();
+                    self.top_stones()[square].is_some_and(|piece| {
+                        piece.color() != self.side_to_move() || piece.role() == Wall
+                    })
+                })
+            }
+        }
+    }
+
+    /// Whether `mv` is a spread that flattens a wall, written with a `*` suffix in PTN.
+    /// This only checks the destination square, so `mv` is assumed to be legal.
+    /// A legal crush is always a capstone moving alone onto the wall.
+    /// Spreads that go off the board are never crushes.
+    pub fn is_crush_move(&self, mv: Move<S>) -> bool {
+        match mv.expand() {
+            ExpMove::Place(_, _) => false,
+            ExpMove::Move(origin, direction, stack_movement) => {
+                Self::spread_destination(origin, direction, &stack_movement)
+                    .and_then(|destination| self.top_stones()[destination])
+                    .is_some_and(|piece| piece.role() == Wall)
+            }
+        }
+    }
+
+    /// The square where the last piece of a spread from `square` is dropped,
+    /// or `None` if the spread goes off the board.
+    /// Every piece that is picked up is dropped on the squares in front of `square`,
+    /// so this is the same whether the whole stack is moved, or only part of it.
+    pub fn spread_destination(
+        square: Square<S>,
+        direction: Direction,
+        movement: &StackMovement<S>,
+    ) -> Option<Square<S>> {
+        square.jump_direction(direction, movement.len() as u8)
+    }
+
+    /// Whether `mv` fails to win the game, when the side to move has an immediate win available.
+    /// Both road wins and flat wins are considered, including spreads that complete a road.
+    pub fn declines_available_win(&self, mv: Move<S>) -> bool {
+        let mut position = self.clone();
+        if position.move_wins_immediately(mv) {
+            return false;
+        }
+        self.moves_iter()
+            .any(|legal_move| position.move_wins_immediately(legal_move))
+    }
+
+    /// Whether the side to move can complete a road this turn, either by placement or by spread.
+    /// Placement wins are found from the critical squares, like in the policy.
+    /// Spreads are first filtered with the group data, see `spread_may_complete_road`,
+    /// and only the remaining ones are played to check whether they complete a road for the side to move,
+    /// including double roads.
+    pub fn has_winning_move(&self) -> bool {
+        self.has_winning_move_with_group_data(&self.group_data())
+    }
+
+    pub(crate) fn has_winning_move_with_group_data(&self, group_data: &GroupData<S>) -> bool {
+        let us = self.side_to_move();
+        let (our_critical_squares, our_road_pieces) = match us {
+            Color::White => (
+                group_data.white_critical_squares,
+                group_data.white_road_pieces(),
+            ),
+            Color::Black => (
+                group_data.black_critical_squares,
+                group_data.black_road_pieces(),
+            ),
+        };
+        let can_place = self.pieces_in_hand(us, Flat) + self.pieces_in_hand(us, Cap) > 0;
+        if can_place && !(our_critical_squares & !group_data.all_pieces()).is_empty() {
+            return true;
+        }
+        let mut position = self.clone();
+        self.moves_iter()
+            .filter(|mv| Self::spread_may_complete_road(group_data, our_road_pieces, *mv))
+            .any(|mv| {
+                let reverse_move = position.do_move(mv);
+                let road_outcome = position.road_winner_detailed();
+                position.reverse_move(reverse_move);
+                matches!(
+                    road_outcome,
+                    Some(RoadOutcome::SingleRoad(color) | RoadOutcome::DoubleRoad(color))
+                        if color == us
+                )
+            })
+    }
+
+    /// Whether `mv` is a spread that can complete a road for the side to move, judged from the group data alone.
+    /// Any new road has to go through the squares the spread touches, so the edges reached by those squares,
+    /// and by our groups next to them, must connect opposite sides of the board.
+    /// This never rejects a winning spread, but may accept spreads that do not win.
+    fn spread_may_complete_road(
+        group_data: &GroupData<S>,
+        our_road_pieces: BitBoard,
+        mv: Move<S>,
+    ) -> bool {
+        let ExpMove::Move(origin, direction, stack_movement) = mv.expand() else {
+            return false;
+        };
+        let mut edge_connection = GroupEdgeConnection::default();
+        for distance in 0..=stack_movement.len() as u8 {
+            let Some(square) = origin.jump_direction(direction, distance) else {
+                return false;
+            };
+            edge_connection |= square.group_edge_connection();
+            for neighbor in (square.neighbors_bitboard() & our_road_pieces).into_iter() {
+                edge_connection |=
+                    group_data.amount_in_group[group_data.groups[neighbor] as usize].1;
+            }
+        }
+        edge_connection.is_winning()
+    }
+
+    /// Whether playing `mv` ends the game with a win for the side to move, either by road or on flats.
+    /// Unlike the policy's immediate win detection, this plays the move and checks the actual game result.
+    pub fn move_is_immediate_win(&self, mv: Move<S>) -> bool {
+        self.clone().move_wins_immediately(mv)
+    }
+
+    /// Whether the side to move can end the game in a draw, and has nothing better, by a one-ply lookahead.
+    /// Checks that exactly one square is empty, that no legal move completes a road for either player
+    /// or wins on flats, and that some legal move fills the board with the flat count tied after komi.
+    ///
+    /// The side to move may still choose not to fill the board, so this does not prove that the game is drawn.
+    pub fn is_dead_drawn(&self) -> bool {
+        if self.empty_square_count() != 1 {
+            return false;
+        }
+        let win = GameResult::win_by(self.side_to_move());
+        let mut position = self.clone();
+        let mut can_draw = false;
+        for mv in self.moves_iter() {
+            let reverse_move = position.do_move(mv);
+            let road_outcome = position.road_winner_detailed();
+            let result = position.game_result();
+            position.reverse_move(reverse_move);
+            if road_outcome.is_some() || result == Some(win) {
+                return false;
+            }
+            can_draw |= result == Some(GameResult::Draw);
+        }
+        can_draw
+    }
+
+    /// Whether `mv` ends the game with a win for the side to move.
+    /// The move is undone before returning, so the position is unchanged.
+    fn move_wins_immediately(&mut self, mv: Move<S>) -> bool {
+        let win = GameResult::win_by(self.side_to_move());
+        let reverse_move = self.do_move(mv);
+        let result = self.game_result();
+        self.reverse_move(reverse_move);
+        result == Some(win)
+    }
+
     pub(crate) fn generate_moves_colortr<
         E: Extend<<Self as PositionTrait>::Move>,
         Us: ColorTr,
@@ -169,3 +529,156 @@ impl<const S: usize> Position<S> {
         }
     }
 }
+
+/// An upper bound on the number of legal moves in any position on a board of size `size`,
+/// assuming every square holds a stack of at least `size` pieces controlled by the side to move.
+pub const fn max_legal_moves(size: usize) -> usize {
+    let mut total = 0;
+    let mut rank = 0;
+    while rank < size {
+        let mut file = 0;
+        while file < size {
+            let distances = [file, size - 1 - file, rank, size - 1 - rank];
+            let mut spreads = 0;
+            let mut i = 0;
+            while i < 4 {
+                // Each spread drops pieces on between 1 and `distance` squares,
+                // and is identified by which of the `size` carried pieces start a new square
+                let mut binomial = 1;
+                let mut k = 1;
+                while k <= distances[i] && k <= size {
+                    binomial = binomial * (size + 1 - k) / k;
+                    spreads += binomial;
+                    k += 1;
+                }
+                i += 1;
+            }
+            total += if spreads > 3 { spreads } else { 3 };
+            file += 1;
+        }
+        rank += 1;
+    }
+    total
+}
+
+/// A set of move kinds to generate, see `Position::generate_moves_filtered`.
+/// Sets can be combined with `|`, for example `MoveFilter::FLAT_PLACEMENTS | MoveFilter::SPREADS`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MoveFilter {
+    bits: u8,
+}
+
+impl MoveFilter {
+    pub const NONE: Self = MoveFilter { bits: 0 };
+    pub const FLAT_PLACEMENTS: Self = MoveFilter { bits: 1 };
+    pub const WALL_PLACEMENTS: Self = MoveFilter { bits: 2 };
+    pub const CAP_PLACEMENTS: Self = MoveFilter { bits: 4 };
+    pub const SPREADS: Self = MoveFilter { bits: 8 };
+    pub const PLACEMENTS: Self = MoveFilter { bits: 7 };
+    pub const ALL: Self = MoveFilter { bits: 15 };
+
+    /// Whether every move kind in `other` is also in `self`
+    pub fn contains(self, other: Self) -> bool {
+        self.bits & other.bits == other.bits
+    }
+}
+
+impl ops::BitOr for MoveFilter {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        MoveFilter {
+            bits: self.bits | rhs.bits,
+        }
+    }
+}
+
+/// Sink for move generation that only counts the moves it receives
+struct MoveCounter(usize);
+
+impl<T> Extend<T> for MoveCounter {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.0 += iter.into_iter().count();
+    }
+}
+
+/// Iterator over the legal moves of a position, created by `Position::moves_iter`.
+pub struct MovesIter<'a, const S: usize> {
+    position: &'a Position<S>,
+    next_square: usize,
+    // Moves for the most recently visited square, in reverse order
+    square_moves: Vec<Move<S>>,
+}
+
+impl<const S: usize> Iterator for MovesIter<'_, S> {
+    type Item = Move<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.square_moves.is_empty() {
+            if self.next_square >= S * S {
+                return None;
+            }
+            let square = Square::from_u8(self.next_square as u8);
+            self.next_square += 1;
+            self.position
+                .generate_moves_for_square(square, &mut self.square_moves);
+            self.square_moves.reverse();
+        }
+        self.square_moves.pop()
+    }
+}
+
+/// Timing results from `benchmark`.
+#[derive(Clone, Debug)]
+pub struct MoveGenTiming {
+    pub iterations: u32,
+    pub moves_generated: u64,
+    pub total_time: Duration,
+}
+
+impl MoveGenTiming {
+    pub fn time_per_iteration(&self) -> Duration {
+        self.total_time / self.iterations.max(1)
+    }
+
+    pub fn moves_per_second(&self) -> f64 {
+        self.moves_generated as f64 / self.total_time.as_secs_f64()
+    }
+}
+
+/// Time `iterations` calls to `generate_moves` on `position`, without any evaluation or search.
+pub fn benchmark<const S: usize>(position: &Position<S>, iterations: u32) -> MoveGenTiming {
+    let mut moves = Vec::with_capacity(512);
+    let mut moves_generated = 0;
+    let start_time = Instant::now();
+    for _ in 0..iterations {
+        moves.clear();
+        hint::black_box(position).generate_moves(&mut moves);
+        moves_generated += hint::black_box(&moves).len() as u64;
+    }
+    MoveGenTiming {
+        iterations,
+        moves_generated,
+        total_time: start_time.elapsed(),
+    }
+}
+
+/// A set of 6s positions that exercise different parts of the move generator:
+/// Mostly placements in the opening, a mix of placements and short spreads in the midgame,
+/// and long spreads from tall stacks.
+pub fn benchmark_positions() -> Vec<(&'static str, Position<6>)> {
+    [
+        ("opening", "x6/x6/x2,2,x3/x3,1,x2/x6/x6 1 2"),
+        (
+            "midgame",
+            "x2,2,x2,1/x,2,2,1,1,x/x,2,2C,1,x2/x,2,1S,12,1C,x/x2,2,1,x2/x6 1 11",
+        ),
+        (
+            "tall stacks",
+            "2,2,22221C,2,x2/x,1,1,x,1,2/x,1,111212C,1212,x,2/x,112S,1,x,112S,2/11212,x,1,112S,1112,2/x2,1,1,1,1 1 40",
+        ),
+    ]
+    .into_iter()
+    .map(|(name, tps)| (name, Position::from_fen(tps).unwrap()))
+    .collect()
+}
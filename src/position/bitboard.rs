@@ -1,3 +1,10 @@
+//! A set of squares, stored as one bit per square in a `u64`.
+//!
+//! Bit `i` is the square whose `Square::into_inner()` is `i`, which is `file * S + rank`,
+//! counting ranks from the top of the board. Only the lowest `S * S` bits are used by board squares,
+//! so the layout depends on the board size, and bitboards from different sizes should not be mixed.
+//! Note that `!` also sets the unused high bits, so mask the result with `BitBoard::full::<S>()` before counting or iterating.
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{fmt, ops};
@@ -6,8 +13,8 @@ use super::{Direction, Square};
 
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub(crate) struct BitBoard {
-    pub board: u64,
+pub struct BitBoard {
+    pub(crate) board: u64,
 }
 
 impl ops::BitOr for BitBoard {
@@ -53,22 +60,30 @@ impl BitBoard {
     pub const fn empty() -> Self {
         BitBoard { board: 0 }
     }
+
+    /// Every square on an `S`x`S` board
     #[inline]
-    pub const fn full() -> Self {
-        BitBoard { board: u64::MAX }
+    pub const fn full<const S: usize>() -> Self {
+        BitBoard::from_u64(u64::MAX >> (64 - S * S))
+    }
+
+    pub fn from_squares<const S: usize, I: IntoIterator<Item = Square<S>>>(squares: I) -> Self {
+        squares
+            .into_iter()
+            .fold(Self::empty(), |board, square| board.set_square(square))
     }
 
     pub fn lines_for_square<const S: usize>(square: Square<S>) -> [Self; 2] {
         [
-            Self::full().rank::<S>(square.rank()),
-            Self::full().file::<S>(square.file()),
+            Self::full::<S>().rank::<S>(square.rank()),
+            Self::full::<S>().file::<S>(square.file()),
         ]
     }
 
     #[inline]
     pub fn lower_n_bits(n: u8) -> Self {
         if n >= 64 {
-            Self::full()
+            Self::from_u64(u64::MAX)
         } else {
             BitBoard {
                 board: (1 << n as u64) - 1,
@@ -88,7 +103,7 @@ impl BitBoard {
     }
 
     #[inline]
-    pub fn get_square<const S: usize>(self, square: Square<S>) -> bool {
+    pub fn contains<const S: usize>(self, square: Square<S>) -> bool {
         debug_assert!(square.into_inner() < 64);
         self.board & (1 << square.into_inner()) != 0
     }
@@ -188,6 +203,7 @@ impl BitBoard {
         }
     }
 
+    #[allow(clippy::should_implement_trait)] // `IntoIterator` cannot take the board size
     pub fn into_iter<const S: usize>(self) -> BitBoardIter<S> {
         BitBoardIter::new(self)
     }
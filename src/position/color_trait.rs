@@ -100,7 +100,7 @@ impl ColorTr for WhiteTr {
     }
 
     fn is_critical_square<const S: usize>(group_data: &GroupData<S>, square: Square<S>) -> bool {
-        group_data.white_critical_squares.get_square(square)
+        group_data.white_critical_squares.contains(square)
     }
 
     fn critical_squares<const S: usize>(group_data: &GroupData<S>) -> BitBoard {
@@ -164,7 +164,7 @@ impl ColorTr for BlackTr {
     }
 
     fn is_critical_square<const S: usize>(group_data: &GroupData<S>, square: Square<S>) -> bool {
-        group_data.black_critical_squares.get_square(square)
+        group_data.black_critical_squares.contains(square)
     }
 
     fn critical_squares<const S: usize>(group_data: &GroupData<S>) -> BitBoard {
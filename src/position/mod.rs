@@ -15,10 +15,14 @@ use rand::{Rng, SeedableRng};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use bitboard::BitBoard;
 use color_trait::{BlackTr, WhiteTr};
 
-pub use utils::{Direction, Komi, Movement, Piece, Piece::*, Role, Role::*, Stack, StackMovement};
+pub use utils::{
+    Direction, Komi, Movement, Piece, Piece::*, Role, Role::*, Stack, StackComposition,
+    StackMovement,
+};
+
+pub use bitboard::{BitBoard, BitBoardIter};
 
 pub use square::{squares_iterator, Square, SquareCacheEntry};
 
@@ -26,7 +30,9 @@ pub use utils::AbstractBoard;
 
 pub use mv::{ExpMove, Move, ReverseMove};
 
-use crate::evaluation::parameters::{self, IncrementalValue, PolicyApplier, ValueApplier};
+use crate::evaluation::parameters::{
+    self, IncrementalValue, IndexPair, PolicyApplier, ValueApplier, ValueContributions,
+};
 use crate::evaluation::value_eval;
 use crate::position::color_trait::ColorTr;
 
@@ -43,6 +49,10 @@ pub(crate) static ZOBRIST_KEYS_6S: OnceLock<Box<ZobristKeys<6>>> = OnceLock::new
 pub(crate) static ZOBRIST_KEYS_7S: OnceLock<Box<ZobristKeys<7>>> = OnceLock::new();
 pub(crate) static ZOBRIST_KEYS_8S: OnceLock<Box<ZobristKeys<8>>> = OnceLock::new();
 
+static INTERPOLATED_PARAMS_4S: OnceLock<InterpolatedParams> = OnceLock::new();
+static INTERPOLATED_PARAMS_5S: OnceLock<InterpolatedParams> = OnceLock::new();
+static INTERPOLATED_PARAMS_6S: OnceLock<InterpolatedParams> = OnceLock::new();
+
 pub const MAX_BOARD_SIZE: usize = 8;
 
 pub const fn starting_stones(size: usize) -> u8 {
@@ -236,7 +246,7 @@ impl ops::BitAnd for GroupEdgeConnection {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct MovementSynopsis<const S: usize> {
     pub origin: Square<S>,
     pub destination: Square<S>,
@@ -259,14 +269,18 @@ fn get_movement_in_history<const S: usize>(
         .get(position.moves().len().overflowing_sub(i).0)
         .and_then(|mv| match mv.expand() {
             ExpMove::Place(_, _) => None,
-            ExpMove::Move(origin, direction, stack_movement) => Some(MovementSynopsis {
-                origin,
-                destination: origin.jump_valid_direction(direction, stack_movement.len() as u8),
-            }),
+            ExpMove::Move(origin, direction, stack_movement) => {
+                Position::spread_destination(origin, direction, &stack_movement).map(
+                    |destination| MovementSynopsis {
+                        origin,
+                        destination,
+                    },
+                )
+            }
         })
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct GroupData<const S: usize> {
     pub(crate) groups: AbstractBoard<u8, S>,
     pub(crate) amount_in_group: ArrayVec<(u8, GroupEdgeConnection), 65>, // Size is max_size^2 + 1
@@ -456,11 +470,223 @@ impl<const S: usize> ZobristKeys<S> {
     }
 }
 
-#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+/// One of the 8 dihedral transformations of the board, in the same order as `Position::symmetries`.
+/// The rotations are quarter turns as in `Position::rotate_board`, and the reflections are the ones in `Position::flip_board_x` and `Position::flip_board_y`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum BoardSymmetry {
+    Identity,
+    FlipX,
+    FlipY,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// `Rotate90`, followed by `FlipX`
+    RotateFlipX,
+    /// `Rotate90`, followed by `FlipY`
+    RotateFlipY,
+}
+
+impl BoardSymmetry {
+    pub const ALL: [BoardSymmetry; 8] = [
+        BoardSymmetry::Identity,
+        BoardSymmetry::FlipX,
+        BoardSymmetry::FlipY,
+        BoardSymmetry::Rotate90,
+        BoardSymmetry::Rotate180,
+        BoardSymmetry::Rotate270,
+        BoardSymmetry::RotateFlipX,
+        BoardSymmetry::RotateFlipY,
+    ];
+
+    /// Index into `Position::symmetries`, as used by `Move::apply_symmetry`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn inverse(self) -> BoardSymmetry {
+        match self {
+            BoardSymmetry::Rotate90 => BoardSymmetry::Rotate270,
+            BoardSymmetry::Rotate270 => BoardSymmetry::Rotate90,
+            symmetry => symmetry,
+        }
+    }
+
+    /// Map a move in the original position to the same move in the transformed position
+    pub fn transform_move<const S: usize>(self, mv: Move<S>) -> Move<S> {
+        mv.apply_symmetry(self.index())
+    }
+
+    /// Map a move in the transformed position back to the same move in the original position
+    pub fn inverse_transform_move<const S: usize>(self, mv: Move<S>) -> Move<S> {
+        mv.apply_symmetry(self.inverse().index())
+    }
+}
+
+/// One of the 16 transformations of a position: One of the 8 board symmetries, optionally combined with swapping the colors
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Symmetry {
+    pub board_symmetry: BoardSymmetry,
+    pub swap_colors: bool,
+}
+
+impl Symmetry {
+    /// Map a move in the original position to the same move in the transformed position
+    pub fn transform_move<const S: usize>(self, mv: Move<S>) -> Move<S> {
+        self.board_symmetry.transform_move(mv)
+    }
+
+    /// Map a move in the transformed position back to the same move in the original position
+    pub fn inverse_transform_move<const S: usize>(self, mv: Move<S>) -> Move<S> {
+        self.board_symmetry.inverse_transform_move(mv)
+    }
+}
+
+/// How running out of reserves would affect the side to move, see `Position::reserve_pressure_assessment`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReservePressure {
+    /// Flats and capstones left for the side to move
+    pub our_reserves: u8,
+    pub their_reserves: u8,
+    /// The side to move's lead in flats, adjusted for komi. Half-flat leads are rounded away from zero.
+    pub komi_adjusted_flat_lead: i8,
+    /// Whether the side to move would win if the game ended on flats right now
+    pub filling_favors_us: bool,
+}
+
+/// An error from parsing a TPS string with `Position::parse_tps`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TpsError {
+    /// The byte offset in the TPS string where parsing failed
+    pub offset: usize,
+    pub message: String,
+}
+
+impl TpsError {
+    fn new(offset: usize, message: impl Into<String>) -> Self {
+        TpsError {
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for TpsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for TpsError {}
+
+/// The reason a move is illegal, see `Position::check_move_legal`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IllegalMoveReason {
+    /// Placement on a square that already has a stack
+    SquareOccupied,
+    /// Flat or wall placement without any stones left in the reserves
+    NoReservesLeft,
+    /// Capstone placement without any capstones left
+    NoCapstoneLeft,
+    /// Stack movement from an empty square
+    NoStackAtOrigin,
+    /// Stack movement from a stack controlled by the opponent
+    WrongTurn,
+    /// Stack movement with an invalid drop pattern, for example by taking more pieces than the carry limit or the stack height,
+    /// moving off the board, or moving onto a capstone
+    StackMovementInvalid,
+    /// Stack movement onto a wall, other than a lone capstone flattening it on the last square
+    CannotFlattenWall,
+    /// Any move other than a flat placement in the first two plies, where each player places one of the opponent's flats
+    OpeningMoveNotFlatPlacement,
+}
+
+impl fmt::Display for IllegalMoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IllegalMoveReason::SquareOccupied => write!(f, "Square is already occupied"),
+            IllegalMoveReason::NoReservesLeft => write!(f, "No stones left in reserves"),
+            IllegalMoveReason::NoCapstoneLeft => write!(f, "No capstones left"),
+            IllegalMoveReason::NoStackAtOrigin => write!(f, "No stack to move"),
+            IllegalMoveReason::WrongTurn => write!(f, "Stack is controlled by the opponent"),
+            IllegalMoveReason::StackMovementInvalid => write!(f, "Invalid stack movement"),
+            IllegalMoveReason::CannotFlattenWall => {
+                write!(f, "Only a lone capstone can flatten a wall")
+            }
+            IllegalMoveReason::OpeningMoveNotFlatPlacement => {
+                write!(f, "Only flat placements are allowed in the first two plies")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IllegalMoveReason {}
+
+/// The roads on the board, see `Position::road_winner_detailed`.
+/// Each variant holds the color of the winning player.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoadOutcome {
+    /// The player who made the last move completed their own road
+    SingleRoad(Color),
+    /// Both players have a road. The player who made the last move wins
+    DoubleRoad(Color),
+    /// The player who made the last move only completed the opponent's road, so the opponent wins
+    Suicide(Color),
+}
+
+/// Road threats for both players, see `Position::threat_summary`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ThreatSummary {
+    /// Empty squares where a white road piece would complete a road
+    pub white_open_threats: u8,
+    pub black_open_threats: u8,
+    /// Occupied squares where a white road piece would complete a road, if White could get one on top
+    pub white_closed_threats: u8,
+    pub black_closed_threats: u8,
+    /// Whether the opponent threatens to win by placement, so the side to move must respond
+    pub in_tak: bool,
+    /// Whether the side to move can complete a road this turn, by placement or by spread, like `Position::has_winning_move`
+    pub has_immediate_win: bool,
+}
+
+/// A census of every piece in the game, see `Position::piece_counts`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PieceCounts {
+    /// Flats and walls that White has not yet placed
+    pub white_reserves: u8,
+    pub black_reserves: u8,
+    /// Capstones that White has not yet placed
+    pub white_caps_in_hand: u8,
+    pub black_caps_in_hand: u8,
+    /// White flats on the board, including flats buried in stacks
+    pub white_flats: u8,
+    pub black_flats: u8,
+    pub white_walls: u8,
+    pub black_walls: u8,
+    /// White capstones on the board
+    pub white_caps: u8,
+    pub black_caps: u8,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Settings {
     pub komi: Komi,
+    /// Number of occurrences of the same position that draws the game, three-fold by default.
+    /// If `None`, repetitions never force a draw. Must be at least 2.
+    pub repetition_draw_count: Option<u8>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            komi: Komi::default(),
+            repetition_draw_count: Some(DEFAULT_REPETITION_DRAW_COUNT),
+        }
+    }
 }
 
+/// The game is drawn on the third occurrence of the same position, unless configured otherwise
+pub const DEFAULT_REPETITION_DRAW_COUNT: u8 = 3;
+
 enum DetailedGameResult {
     WhiteRoadWin,
     BlackRoadWin,
@@ -492,6 +718,14 @@ impl DetailedGameResult {
 }
 
 /// Complete representation of a Tak position
+///
+/// With the `serde` feature, the position is serialized with its board as a TPS string, alongside the move history and the other fields.
+/// The zobrist hash is recomputed when deserializing, and checked against the serialized value.
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "SerializedPosition<S>", try_from = "SerializedPosition<S>")
+)]
 pub struct Position<const S: usize> {
     stacks: AbstractBoard<BitBoard, S>,
     stack_heights: AbstractBoard<u8, S>,
@@ -504,8 +738,11 @@ pub struct Position<const S: usize> {
     half_moves_played: usize,
     moves: Vec<Move<S>>,
     komi: Komi,
+    repetition_draw_count: Option<u8>,
     hash: u64,              // Zobrist hash of current position
     hash_history: Vec<u64>, // Zobrist hashes of previous board states, up to the last irreversible move. Does not include the corrent position
+    // Values of `half_moves_played` before each move in `moves` that flattened a wall
+    wall_flattening_plies: Vec<usize>,
 }
 
 impl<const S: usize> Clone for Position<S> {
@@ -522,8 +759,10 @@ impl<const S: usize> Clone for Position<S> {
             half_moves_played: self.half_moves_played,
             moves: self.moves.clone(),
             komi: self.komi,
+            repetition_draw_count: self.repetition_draw_count,
             hash: self.hash,
             hash_history: self.hash_history.clone(),
+            wall_flattening_plies: self.wall_flattening_plies.clone(),
         }
     }
     fn clone_from(&mut self, source: &Self) {
@@ -538,8 +777,11 @@ impl<const S: usize> Clone for Position<S> {
         self.half_moves_played = source.half_moves_played;
         self.moves.clone_from(&source.moves);
         self.komi = source.komi;
+        self.repetition_draw_count = source.repetition_draw_count;
         self.hash = source.hash;
         self.hash_history.clone_from(&source.hash_history);
+        self.wall_flattening_plies
+            .clone_from(&source.wall_flattening_plies);
         debug_assert_eq!(self, source);
         debug_assert_eq!(self.moves, source.moves);
         debug_assert_eq!(self.hash_history, source.hash_history);
@@ -558,6 +800,7 @@ impl<const S: usize> PartialEq for Position<S> {
             && self.black_caps_left == other.black_caps_left
             && self.half_moves_played == other.half_moves_played
             && self.komi == other.komi
+            && self.repetition_draw_count == other.repetition_draw_count
     }
 }
 
@@ -575,6 +818,56 @@ impl<const S: usize> Hash for Position<S> {
         self.black_caps_left.hash(state);
         self.half_moves_played.hash(state);
         self.komi.hash(state);
+        self.repetition_draw_count.hash(state);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SerializedPosition<const S: usize> {
+    tps: String,
+    komi: Komi,
+    repetition_draw_count: Option<u8>,
+    moves: Vec<Move<S>>,
+    hash: u64,
+    hash_history: Vec<u64>,
+    #[serde(default)]
+    wall_flattening_plies: Vec<usize>,
+}
+
+#[cfg(feature = "serde")]
+impl<const S: usize> From<Position<S>> for SerializedPosition<S> {
+    fn from(position: Position<S>) -> Self {
+        SerializedPosition {
+            tps: position.to_fen(),
+            komi: position.komi,
+            repetition_draw_count: position.repetition_draw_count,
+            moves: position.moves,
+            hash: position.hash,
+            hash_history: position.hash_history,
+            wall_flattening_plies: position.wall_flattening_plies,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const S: usize> TryFrom<SerializedPosition<S>> for Position<S> {
+    type Error = String;
+
+    fn try_from(serialized: SerializedPosition<S>) -> Result<Self, Self::Error> {
+        let mut position =
+            Position::parse_tps(&serialized.tps, serialized.komi).map_err(|err| err.to_string())?;
+        if position.hash != serialized.hash {
+            return Err(format!(
+                "Serialized hash {} does not match hash {} of position {}",
+                serialized.hash, position.hash, serialized.tps
+            ));
+        }
+        position.set_repetition_draw_count(serialized.repetition_draw_count)?;
+        position.moves = serialized.moves;
+        position.hash_history = serialized.hash_history;
+        position.wall_flattening_plies = serialized.wall_flattening_plies;
+        Ok(position)
     }
 }
 
@@ -628,6 +921,44 @@ impl<const S: usize> fmt::Debug for Position<S> {
     }
 }
 
+/// A compact board diagram with one character per top stone, in the same orientation as TPS.
+/// Flats are `w` and `b`, walls are `W` and `B`, and capstones are `C` and `c`, for white and black respectively.
+impl<const S: usize> fmt::Display for Position<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rank in 0..S {
+            write!(f, "{}", S - rank)?;
+            for file in 0..S {
+                let symbol = match self.top_stones[Square::from_rank_file(rank as u8, file as u8)] {
+                    None => '.',
+                    Some(WhiteFlat) => 'w',
+                    Some(WhiteWall) => 'W',
+                    Some(WhiteCap) => 'C',
+                    Some(BlackFlat) => 'b',
+                    Some(BlackWall) => 'B',
+                    Some(BlackCap) => 'c',
+                };
+                write!(f, " {}", symbol)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, " ")?;
+        for file in (b'a'..).take(S) {
+            write!(f, " {}", file as char)?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{} to move. Stones left: {}/{}, capstones left: {}/{}.",
+            self.side_to_move(),
+            self.white_stones_left,
+            self.black_stones_left,
+            self.white_caps_left,
+            self.black_caps_left
+        )?;
+        write!(f, "w/b: flats, W/B: walls, C/c: capstones")
+    }
+}
+
 impl<const S: usize> Position<S> {
     pub fn start_position_with_komi(komi: Komi) -> Self {
         Position {
@@ -642,8 +973,199 @@ impl<const S: usize> Position<S> {
             half_moves_played: 0,
             moves: vec![],
             komi,
+            repetition_draw_count: Some(DEFAULT_REPETITION_DRAW_COUNT),
             hash: zobrist_to_move::<S>(Color::White),
             hash_history: vec![],
+            wall_flattening_plies: vec![],
+        }
+    }
+
+    /// Parse a TPS string, like `from_fen_with_komi`.
+    /// On failure, the error includes the byte offset in `tps` where parsing failed.
+    pub fn parse_tps(tps: &str, komi: Komi) -> Result<Self, TpsError> {
+        // The words of the TPS string, with their byte offsets
+        let mut words: Vec<(usize, &str)> = vec![];
+        let mut word_start = None;
+        for (i, ch) in tps.char_indices().chain(iter::once((tps.len(), ' '))) {
+            match (word_start, ch.is_whitespace()) {
+                (None, false) => word_start = Some(i),
+                (Some(start), true) => {
+                    words.push((start, &tps[start..i]));
+                    word_start = None;
+                }
+                _ => (),
+            }
+        }
+
+        if words.len() < 3 {
+            return Err(TpsError::new(tps.len(), "missing move counter"));
+        }
+        if words.len() > 3 {
+            return Err(TpsError::new(
+                words[3].0,
+                format!("unexpected \"{}\"", words[3].1),
+            ));
+        }
+
+        let (board_offset, board_str) = words[0];
+        let num_rows = board_str.split('/').count();
+        if num_rows != S {
+            return Err(TpsError::new(
+                board_offset,
+                format!("had {} rows instead of {}", num_rows, S),
+            ));
+        }
+
+        let mut rows: Vec<[Stack; S]> = Vec::with_capacity(S);
+        let mut row_offset = board_offset;
+        for row_str in board_str.split('/') {
+            rows.push(parse_row(row_str, row_offset)?);
+            row_offset += row_str.len() + 1;
+        }
+
+        let mut position = Position::start_position_with_komi(komi);
+        for square in square::squares_iterator::<S>() {
+            let (file, rank) = (square.file(), square.rank());
+            let stack = rows[rank as usize][file as usize];
+            for piece in stack.into_iter() {
+                match piece {
+                    WhiteFlat | WhiteWall => position.white_stones_left -= 1,
+                    WhiteCap => position.white_caps_left -= 1,
+                    BlackFlat | BlackWall => position.black_stones_left -= 1,
+                    BlackCap => position.black_caps_left -= 1,
+                }
+            }
+            position.set_stack(square, stack);
+        }
+
+        let (side_to_move_offset, side_to_move_str) = words[1];
+        match side_to_move_str {
+            "1" => position.to_move = Color::White,
+            "2" => position.to_move = Color::Black,
+            s => {
+                return Err(TpsError::new(
+                    side_to_move_offset,
+                    format!("got bad side to move \"{}\"", s),
+                ))
+            }
+        }
+
+        let (move_number_offset, move_number_str) = words[2];
+        match move_number_str.parse::<usize>() {
+            Ok(n) if n > 0 => match position.side_to_move() {
+                Color::White => position.half_moves_played = 2 * n - 2,
+                Color::Black => position.half_moves_played = 2 * n - 1,
+            },
+            _ => {
+                return Err(TpsError::new(
+                    move_number_offset,
+                    format!("got bad move number \"{}\"", move_number_str),
+                ))
+            }
+        }
+
+        position.hash = position.zobrist_hash_from_scratch();
+
+        return Ok(position);
+
+        fn parse_row<const S: usize>(row_str: &str, offset: usize) -> Result<[Stack; S], TpsError> {
+            let mut column_id = 0;
+            let mut row = [Stack::default(); S];
+            let mut row_str_iter = row_str.char_indices().peekable();
+            let end_offset = offset + row_str.len();
+            while column_id < S as u8 {
+                match row_str_iter.peek().copied() {
+                    None => {
+                        return Err(TpsError::new(
+                            end_offset,
+                            format!("not enough pieces on row \"{}\"", row_str),
+                        ))
+                    }
+                    Some((_, 'x')) => {
+                        row_str_iter.next();
+                        if let Some(n) = row_str_iter.peek().and_then(|(_, ch)| ch.to_digit(10)) {
+                            row_str_iter.next();
+                            column_id += n as u8;
+                        } else {
+                            column_id += 1;
+                        }
+                        match row_str_iter.next() {
+                            Some((_, ',')) | None => (),
+                            Some((i, ch)) => {
+                                return Err(TpsError::new(
+                                    offset + i,
+                                    format!("expected ',' on row \"{}\", found '{}'", row_str, ch),
+                                ))
+                            }
+                        }
+                    }
+                    Some((_, '1')) | Some((_, '2')) => {
+                        let stack = &mut row[column_id as usize];
+                        loop {
+                            if let Some((i, '1' | '2')) = row_str_iter.peek() {
+                                if stack.len() == Stack::MAX_HEIGHT {
+                                    return Err(TpsError::new(
+                                        offset + i,
+                                        format!(
+                                            "stack on row \"{}\" is taller than the maximum of {} pieces",
+                                            row_str,
+                                            Stack::MAX_HEIGHT
+                                        ),
+                                    ));
+                                }
+                            }
+                            match row_str_iter.next() {
+                                Some((_, '1')) => {
+                                    stack.push(Piece::from_role_color(Flat, Color::White))
+                                }
+                                Some((_, '2')) => {
+                                    stack.push(Piece::from_role_color(Flat, Color::Black))
+                                }
+                                Some((_, 'S')) => {
+                                    let piece = stack.pop().unwrap();
+                                    stack.push(Piece::from_role_color(Wall, piece.color()));
+                                }
+                                Some((_, 'C')) => {
+                                    let piece = stack.pop().unwrap();
+                                    stack.push(Piece::from_role_color(Cap, piece.color()));
+                                }
+                                Some((_, ',')) | None => {
+                                    column_id += 1;
+                                    break;
+                                }
+                                Some((i, ch)) => {
+                                    return Err(TpsError::new(
+                                        offset + i,
+                                        format!(
+                                        "expected '1', '2', 'S' or 'C' on row \"{}\", found '{}'",
+                                        row_str, ch
+                                    ),
+                                    ))
+                                }
+                            }
+                        }
+                    }
+                    Some((i, ch)) => {
+                        return Err(TpsError::new(
+                            offset + i,
+                            format!("unexpected '{}' on row \"{}\"", ch, row_str),
+                        ))
+                    }
+                }
+            }
+            if let Some((i, _)) = row_str_iter.next() {
+                return Err(TpsError::new(
+                    offset + i,
+                    format!("too many pieces on row \"{}\"", row_str),
+                ));
+            }
+            if column_id > S as u8 {
+                return Err(TpsError::new(
+                    end_offset,
+                    format!("too many squares on row \"{}\"", row_str),
+                ));
+            }
+            Ok(row)
         }
     }
 
@@ -664,6 +1186,35 @@ impl<const S: usize> Position<S> {
         }
     }
 
+    /// The number of squares with each stack height, with empty squares counted at index 0.
+    /// Stacks taller than 63 pieces, only possible on the largest boards, are counted at index 63.
+    pub fn stack_height_histogram(&self) -> [u16; 64] {
+        let mut histogram = [0; 64];
+        for square in squares_iterator::<S>() {
+            histogram[(self.stack_heights[square] as usize).min(63)] += 1;
+        }
+        histogram
+    }
+
+    /// The number of squares without any pieces on them
+    pub fn empty_square_count(&self) -> u8 {
+        squares_iterator::<S>()
+            .filter(|square| self.stack_heights[*square] == 0)
+            .count() as u8
+    }
+
+    /// The fraction of squares that are occupied, between 0.0 and 1.0. The game ends when this reaches 1.0
+    pub fn board_fill_fraction(&self) -> f32 {
+        (S * S - self.empty_square_count() as usize) as f32 / (S * S) as f32
+    }
+
+    /// The composition of every non-empty stack on the board
+    pub fn stack_compositions(&self) -> Vec<(Square<S>, StackComposition)> {
+        squares_iterator::<S>()
+            .filter_map(|square| Some((square, self.get_stack(square).composition()?)))
+            .collect()
+    }
+
     pub fn set_stack(&mut self, square: Square<S>, stack: Stack) {
         self.stacks[square] = stack.bitboard;
         self.stack_heights[square] = stack.height;
@@ -694,8 +1245,21 @@ impl<const S: usize> Position<S> {
         self.black_caps_left
     }
 
-    #[cfg(test)]
-    pub fn zobrist_hash(&self) -> u64 {
+    /// The number of pieces of `role` that `color` can still place.
+    /// Flats and walls are drawn from the same pool of stones, so they always return the same value.
+    pub fn pieces_in_hand(&self, color: Color, role: Role) -> u8 {
+        match (color, role) {
+            (Color::White, Flat | Wall) => self.white_stones_left,
+            (Color::Black, Flat | Wall) => self.black_stones_left,
+            (Color::White, Cap) => self.white_caps_left,
+            (Color::Black, Cap) => self.black_caps_left,
+        }
+    }
+
+    /// The zobrist hash of the position, which is updated incrementally as moves are played.
+    /// Covers the board and the side to move, but not komi, the move count or the game history,
+    /// so it can be used to detect transpositions.
+    pub fn hash(&self) -> u64 {
         self.hash
     }
 
@@ -707,18 +1271,289 @@ impl<const S: usize> Position<S> {
         self.komi = komi
     }
 
+    /// Number of occurrences of the same position that draws the game, or `None` if repetitions never do
+    pub fn repetition_draw_count(&self) -> Option<u8> {
+        self.repetition_draw_count
+    }
+
+    /// Set the number of occurrences of the same position that draws the game.
+    /// Fails for `Some(0)` and `Some(1)`, since every position has already occurred once, so they would draw any game immediately.
+    pub fn set_repetition_draw_count(
+        &mut self,
+        repetition_draw_count: Option<u8>,
+    ) -> Result<(), String> {
+        if let Some(count @ (0 | 1)) = repetition_draw_count {
+            return Err(format!(
+                "Repetition draw count must be at least 2, got {}",
+                count
+            ));
+        }
+        self.repetition_draw_count = repetition_draw_count;
+        Ok(())
+    }
+
     /// Number of moves/plies played in the game
     pub fn half_moves_played(&self) -> usize {
         self.half_moves_played
     }
 
+    /// Whether the game is in the first two plies, where each player places one of the opponent's flats.
+    /// Spreads, walls and capstones are not allowed until both players have done so.
+    pub fn is_placement_phase(&self) -> bool {
+        self.half_moves_played < 2
+    }
+
     /// All the moves played in the game
     pub fn moves(&self) -> &Vec<Move<S>> {
         &self.moves
     }
 
+    /// Apply a sequence of moves, checking that each one is legal and that the game is not already over.
+    /// On failure, returns the index of the first move that could not be played.
+    /// The moves before it are still applied.
+    pub fn do_moves_checked(&mut self, moves: &[Move<S>]) -> Result<(), usize> {
+        for (i, mv) in moves.iter().enumerate() {
+            if (self.could_be_decided() && self.game_result().is_some()) || !self.move_is_legal(*mv)
+            {
+                return Err(i);
+            }
+            self.do_move(*mv);
+        }
+        Ok(())
+    }
+
+    /// Take back the last move played, without needing the `ReverseMove` returned by `do_move`.
+    /// Komi and the rest of the game history are preserved.
+    /// Returns false if there are no moves to undo.
+    ///
+    /// Like `reverse_move`, this does not restore the repetition history from before a placement.
+    pub fn undo_last_move(&mut self) -> bool {
+        let Some(mv) = self.moves.last().copied() else {
+            return false;
+        };
+        let reverse_move = match mv.expand() {
+            ExpMove::Place(_, square) => ReverseMove::Place(square),
+            ExpMove::Move(square, direction, stack_movement) => {
+                let mut movements: ArrayVec<u8, 8> = stack_movement
+                    .into_iter()
+                    .map(|movement| movement.pieces_to_take)
+                    .collect();
+                movements.push(0);
+                let pieces_left_behind: ArrayVec<u8, 8> = movements
+                    .windows(2)
+                    .map(|window| window[0] - window[1])
+                    .collect();
+
+                // Whether the spread flattened a wall cannot be seen from the board alone, so `do_move` records it
+                let flattens_wall =
+                    self.wall_flattening_plies.last() == Some(&(self.half_moves_played - 1));
+                ReverseMove::Move(
+                    square,
+                    direction,
+                    stack_movement,
+                    pieces_left_behind,
+                    flattens_wall,
+                )
+            }
+        };
+        self.reverse_move(reverse_move);
+        true
+    }
+
+    /// The position after playing `moves`, written in PTN notation, from the start position with the given komi.
+    /// Returns an error with the index and notation of the first move that cannot be parsed or is not legal,
+    /// including any move after the game is over.
+    pub fn from_san_moves(komi: Komi, moves: &[&str]) -> Result<Self, pgn_traits::Error> {
+        let mut position = Self::start_position_with_komi(komi);
+        for (i, move_string) in moves.iter().enumerate() {
+            let mv = position.move_from_san(move_string).map_err(|err| {
+                pgn_traits::Error::new_caused_by(
+                    pgn_traits::ErrorKind::ParseError,
+                    format!("Couldn't parse move #{} \"{}\"", i, move_string),
+                    err,
+                )
+            })?;
+            if position.do_moves_checked(&[mv]).is_err() {
+                return Err(pgn_traits::Error::new(
+                    pgn_traits::ErrorKind::IllegalMove,
+                    format!("Move #{} \"{}\" is not legal", i, move_string),
+                ));
+            }
+        }
+        Ok(position)
+    }
+
+    /// Whether the game could possibly be over, without computing the group data.
+    /// A road needs at least `S` pieces of one color, and running out of reserves or squares needs even more.
+    /// Repetitions are only possible after spreads.
+    fn could_be_decided(&self) -> bool {
+        let starting_pieces = starting_stones(S) + starting_capstones(S);
+        !self.hash_history.is_empty()
+            || starting_pieces - self.white_stones_left - self.white_caps_left >= S as u8
+            || starting_pieces - self.black_stones_left - self.black_caps_left >= S as u8
+    }
+
+    /// Number of moves played since the last irreversible move.
+    /// Placements are irreversible, and reset the count to zero.
+    pub fn reversible_moves_count(&self) -> usize {
+        self.hash_history.len()
+    }
+
+    /// Number of times the current position has occurred before, since the last irreversible move.
+    /// With the default three-fold rule, the game is drawn when this reaches 2, i.e. on the third occurrence.
+    pub fn repetition_count(&self) -> usize {
+        self.hash_history
+            .iter()
+            .filter(|hash| **hash == self.hash)
+            .count()
+    }
+
+    /// Whether `color` has a completed road on the board, regardless of whose turn it is.
+    /// Both players can have a road at the same time, after a spread that completes both.
+    pub fn has_road(&self, color: Color) -> bool {
+        let group_data = self.group_data();
+        let road_pieces = match color {
+            Color::White => group_data.white_road_pieces(),
+            Color::Black => group_data.black_road_pieces(),
+        };
+        road_pieces.into_iter::<S>().any(|square| {
+            group_data.amount_in_group[group_data.groups[square] as usize]
+                .1
+                .is_winning()
+        })
+    }
+
+    /// Count the road threats of both players.
+    /// Only placement wins are considered for `in_tak`, since the opponent's spread wins cannot be seen from the critical squares alone.
+    /// `has_immediate_win` also considers the side to move's spreads, and always agrees with `has_winning_move`.
+    pub fn threat_summary(&self) -> ThreatSummary {
+        let group_data = self.group_data();
+        let empty_squares = !group_data.all_pieces() & BitBoard::full::<S>();
+        let white_open_threats = (group_data.white_critical_squares & empty_squares).count();
+        let black_open_threats = (group_data.black_critical_squares & empty_squares).count();
+        let white_can_place = self.white_stones_left + self.white_caps_left > 0;
+        let black_can_place = self.black_stones_left + self.black_caps_left > 0;
+        let white_has_placement_win = white_open_threats > 0 && white_can_place;
+        let black_has_placement_win = black_open_threats > 0 && black_can_place;
+
+        ThreatSummary {
+            white_open_threats,
+            black_open_threats,
+            white_closed_threats: group_data.white_critical_squares.count() - white_open_threats,
+            black_closed_threats: group_data.black_critical_squares.count() - black_open_threats,
+            in_tak: match self.side_to_move() {
+                Color::White => black_has_placement_win,
+                Color::Black => white_has_placement_win,
+            },
+            has_immediate_win: self.has_winning_move_with_group_data(&group_data),
+        }
+    }
+
+    /// The number of flats `color` has on top of stacks, which is their score in a flat win.
+    /// Walls and capstones are not counted. This is much cheaper than computing the group data.
+    pub fn flat_count(&self, color: Color) -> u8 {
+        let flat = Piece::from_role_color(Flat, color);
+        self.top_stones
+            .raw
+            .iter()
+            .flatten()
+            .filter(|top_stone| **top_stone == Some(flat))
+            .count() as u8
+    }
+
+    /// Every piece in the game, both in hand and on the board.
+    /// Buried pieces are always flats, so for each color, reserves plus on-board flats and walls
+    /// add up to `starting_stones`, and capstones in hand plus on board add up to `starting_capstones`.
+    pub fn piece_counts(&self) -> PieceCounts {
+        let mut counts = PieceCounts {
+            white_reserves: self.white_stones_left,
+            black_reserves: self.black_stones_left,
+            white_caps_in_hand: self.white_caps_left,
+            black_caps_in_hand: self.black_caps_left,
+            ..Default::default()
+        };
+        for square in squares_iterator::<S>() {
+            let Some(top_stone) = self.top_stones[square] else {
+                continue;
+            };
+            match top_stone {
+                WhiteFlat => counts.white_flats += 1,
+                BlackFlat => counts.black_flats += 1,
+                WhiteWall => counts.white_walls += 1,
+                BlackWall => counts.black_walls += 1,
+                WhiteCap => counts.white_caps += 1,
+                BlackCap => counts.black_caps += 1,
+            }
+            let buried = self.stack_heights[square] - 1;
+            let buried_white = (self.stacks[square] & BitBoard::lower_n_bits(buried)).count();
+            counts.white_flats += buried_white;
+            counts.black_flats += buried - buried_white;
+        }
+        counts
+    }
+
+    /// Whether rushing to end the game on flats favors the side to move, with the given komi
+    pub fn reserve_pressure_assessment(&self, komi: Komi) -> ReservePressure {
+        let white_flats = self.flat_count(Color::White) as i8;
+        let black_flats = self.flat_count(Color::Black) as i8;
+        let white_reserves = self.white_stones_left + self.white_caps_left;
+        let black_reserves = self.black_stones_left + self.black_caps_left;
+        let result = komi.game_result_with_flatcounts(white_flats, black_flats);
+
+        match self.side_to_move() {
+            Color::White => ReservePressure {
+                our_reserves: white_reserves,
+                their_reserves: black_reserves,
+                komi_adjusted_flat_lead: komi
+                    .komi_adjusted_flat_lead(Color::White, white_flats - black_flats),
+                filling_favors_us: result == GameResult::WhiteWin,
+            },
+            Color::Black => ReservePressure {
+                our_reserves: black_reserves,
+                their_reserves: white_reserves,
+                komi_adjusted_flat_lead: komi
+                    .komi_adjusted_flat_lead(Color::Black, black_flats - white_flats),
+                filling_favors_us: result == GameResult::BlackWin,
+            },
+        }
+    }
+
+    /// The result string, `F-0`, `0-F` or `1/2-1/2`, that the game would get if it were decided by flat count right now, with the given komi.
+    /// This is a provisional result for display in ongoing games. It ignores roads, and does not mean that the game is over.
+    /// For the result of a finished game, use `pgn_game_result`.
+    pub fn result_string_if_adjudicated(&self, komi: Komi) -> &'static str {
+        let white_flats = self.flat_count(Color::White) as i8;
+        let black_flats = self.flat_count(Color::Black) as i8;
+        let result = match komi.game_result_with_flatcounts(white_flats, black_flats) {
+            GameResult::WhiteWin => DetailedGameResult::WhiteFlatWin,
+            GameResult::BlackWin => DetailedGameResult::BlackFlatWin,
+            GameResult::Draw => DetailedGameResult::Draw,
+        };
+        result.result_str()
+    }
+
+    /// Whether the two positions have the same pieces on the board, the same reserves, and the same side to move.
+    ///
+    /// Unlike `==`, this ignores the number of moves played and the komi,
+    /// so it is suitable for detecting transpositions reached through different move orders.
+    /// Note that the two positions may still differ in move generation during the first two plies,
+    /// where the players place their opponent's pieces. Use `==` when the positions must be fully interchangeable.
+    pub fn same_board(&self, other: &Self) -> bool {
+        self.stacks == other.stacks
+            && self.stack_heights == other.stack_heights
+            && self.top_stones == other.top_stones
+            && self.to_move == other.to_move
+            && self.white_stones_left == other.white_stones_left
+            && self.black_stones_left == other.black_stones_left
+            && self.white_caps_left == other.white_caps_left
+            && self.black_caps_left == other.black_caps_left
+    }
+
+    /// Pass the turn to the opponent, without changing the board. The Zobrist hash is updated accordingly
     pub fn null_move(&mut self) {
+        self.hash ^= zobrist_to_move::<S>(self.to_move);
         self.to_move = !self.to_move;
+        self.hash ^= zobrist_to_move::<S>(self.to_move);
     }
 
     pub(crate) fn zobrist_hash_from_scratch(&self) -> u64 {
@@ -735,6 +1570,8 @@ impl<const S: usize> Position<S> {
         let mut hash = 0;
         if let Some(top_stone) = self.top_stones[square] {
             hash ^= zobrist_top_stones::<S>(square, top_stone);
+            // Hash the pieces under the top stone in slices of 8, with separate keys for each slice.
+            // The tallest supported stack has 64 pieces under the top stone, which uses all 8 slices.
             // Only enter this loop if stack.len() is 2 or more
             for i in 0..(self.stack_heights[square] as usize + 6) / 8 {
                 hash ^= zobrist_stones_in_stack::<S>(
@@ -766,6 +1603,78 @@ impl<const S: usize> Position<S> {
         sum_of_connections.is_winning()
     }
 
+    /// The critical squares of `color`, where a road piece would complete a road, along with the number of distinct roads it would complete.
+    /// A road is counted for every minimal combination of neighboring groups that connects two opposite edges through the square,
+    /// so a square joining a group on the north edge to two separate groups on the south edge completes two roads.
+    /// Sorted with the most roads first.
+    pub fn ranked_threat_squares(&self, color: Color) -> Vec<(Square<S>, u8)> {
+        let group_data = self.group_data();
+        let road_pieces = match color {
+            Color::White => group_data.white_road_pieces(),
+            Color::Black => group_data.black_road_pieces(),
+        };
+        let mut threats: Vec<(Square<S>, u8)> = group_data
+            .critical_squares(color)
+            .map(|square| {
+                (
+                    square,
+                    Self::num_roads_through_square(&group_data, road_pieces, square),
+                )
+            })
+            .collect();
+        threats.sort_by_key(|(square, num_roads)| (u8::MAX - num_roads, square.into_inner()));
+        threats
+    }
+
+    /// The critical squares of (white, black), where a road piece of that color would complete a road.
+    /// Occupied squares are included, since a road piece could still get there by spreading.
+    /// A square can be critical for both colors at once.
+    pub fn critical_squares(&self) -> (Vec<Square<S>>, Vec<Square<S>>) {
+        let group_data = self.group_data();
+        (
+            group_data.critical_squares(Color::White).collect(),
+            group_data.critical_squares(Color::Black).collect(),
+        )
+    }
+
+    fn num_roads_through_square(
+        group_data: &GroupData<S>,
+        road_pieces: BitBoard,
+        square: Square<S>,
+    ) -> u8 {
+        let mut neighbor_groups: ArrayVec<GroupEdgeConnection, 4> = ArrayVec::new();
+        let mut seen_group_ids: ArrayVec<u8, 4> = ArrayVec::new();
+        for neighbor in square.neighbors() {
+            let group_id = group_data.groups[neighbor];
+            if road_pieces.contains(neighbor) && !seen_group_ids.contains(&group_id) {
+                seen_group_ids.push(group_id);
+                neighbor_groups.push(group_data.amount_in_group[group_id as usize].1);
+            }
+        }
+
+        // Each subset of the neighboring groups is a bitmask
+        let winning_subsets: ArrayVec<usize, 16> = (0..1 << neighbor_groups.len())
+            .filter(|subset| {
+                neighbor_groups
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *subset & (1 << *i) != 0)
+                    .fold(square.group_edge_connection(), |connection, (_, group)| {
+                        connection | *group
+                    })
+                    .is_winning()
+            })
+            .collect();
+        winning_subsets
+            .iter()
+            .filter(|subset| {
+                !winning_subsets
+                    .iter()
+                    .any(|other| other != *subset && *other & **subset == *other)
+            })
+            .count() as u8
+    }
+
     pub fn flip_board_y(&self) -> Position<S> {
         let mut new_board = self.clone();
         for file in 0..S as u8 {
@@ -850,6 +1759,52 @@ impl<const S: usize> Position<S> {
         ]
     }
 
+    /// Returns the board transformed by `symmetry`, the same as `self.symmetries()[symmetry.index()]`
+    pub fn transform_board(&self, symmetry: BoardSymmetry) -> Position<S> {
+        match symmetry {
+            BoardSymmetry::Identity => self.clone(),
+            BoardSymmetry::FlipX => self.flip_board_x(),
+            BoardSymmetry::FlipY => self.flip_board_y(),
+            BoardSymmetry::Rotate90 => self.rotate_board(),
+            BoardSymmetry::Rotate180 => self.rotate_board().rotate_board(),
+            BoardSymmetry::Rotate270 => self.rotate_board().rotate_board().rotate_board(),
+            BoardSymmetry::RotateFlipX => self.rotate_board().flip_board_x(),
+            BoardSymmetry::RotateFlipY => self.rotate_board().flip_board_y(),
+        }
+    }
+
+    /// Returns a canonical representative of the position's 8 symmetries, the one with the lexicographically smallest TPS,
+    /// and the symmetry that transforms this position into it. The colors are never swapped.
+    /// Symmetric positions always have the same canonical form.
+    ///
+    /// The move history is transformed along with the board, so the moves can still be taken back with `undo_last_move`.
+    /// The repetition history is always cleared, also when the position is already canonical,
+    /// so that the canonical position does not depend on the orientation of the input.
+    /// Repetitions of positions from before the call are not detected.
+    ///
+    /// Moves are mapped to the canonical position with `Symmetry::transform_move`, and back with `Symmetry::inverse_transform_move`.
+    pub fn canonical_form(&self) -> (Position<S>, Symmetry) {
+        let (symmetry, mut canonical_position) = BoardSymmetry::ALL
+            .into_iter()
+            .zip(self.symmetries())
+            .min_by_key(|(_, position)| position.to_fen())
+            .unwrap();
+        if symmetry != BoardSymmetry::Identity {
+            for mv in canonical_position.moves.iter_mut() {
+                *mv = symmetry.transform_move(*mv);
+            }
+            canonical_position.hash = canonical_position.zobrist_hash_from_scratch();
+        }
+        canonical_position.hash_history.clear();
+        (
+            canonical_position,
+            Symmetry {
+                board_symmetry: symmetry,
+                swap_colors: false,
+            },
+        )
+    }
+
     /// Returns all 16 symmetries of the board, where swapping the colors is also a symmetry
     pub fn symmetries_with_swapped_colors(&self) -> Vec<Position<S>> {
         self.symmetries()
@@ -858,6 +1813,19 @@ impl<const S: usize> Position<S> {
             .collect()
     }
 
+    /// Returns the transformation that maps this position to `other`, if there is one.
+    /// The positions are compared with `same_board`, so the move history and komi are ignored.
+    /// If several transformations match, the first one in the order of `symmetries_with_swapped_colors` is returned.
+    pub fn is_symmetric_to(&self, other: &Position<S>) -> Option<Symmetry> {
+        self.symmetries_with_swapped_colors()
+            .iter()
+            .position(|position| position.same_board(other))
+            .map(|i| Symmetry {
+                board_symmetry: BoardSymmetry::ALL[i / 2],
+                swap_colors: i % 2 == 1,
+            })
+    }
+
     fn count_all_pieces(&self) -> u8 {
         squares_iterator::<S>()
             .map(|square| self.stack_heights[square])
@@ -892,6 +1860,185 @@ impl<const S: usize> Position<S> {
             }
         }
 
+        Self::compute_groups(&mut group_data);
+
+        for square in square::squares_iterator::<S>() {
+            if self.is_critical_square_from_scratch::<WhiteTr>(&group_data, square) {
+                group_data.white_critical_squares =
+                    group_data.white_critical_squares.set_square(square);
+            }
+            if self.is_critical_square_from_scratch::<BlackTr>(&group_data, square) {
+                group_data.black_critical_squares =
+                    group_data.black_critical_squares.set_square(square);
+            }
+        }
+        group_data
+    }
+
+    /// The group data for this position, where `mv` was just played, given `prev`, the group data from before `mv`.
+    /// Gives the same result as `group_data`.
+    ///
+    /// Only the squares that `mv` touched are updated in the piece bitboards.
+    /// Groups that neither contain nor border a touched square keep their squares and edge connections,
+    /// and are only given new ids. The other groups are flood filled again.
+    /// Critical squares are only re-checked next to pieces whose group's edge connections changed.
+    pub fn group_data_after_move(&self, prev: &GroupData<S>, mv: Move<S>) -> GroupData<S> {
+        let mut group_data = GroupData {
+            groups: prev.groups.clone(),
+            white_flat_stones: prev.white_flat_stones,
+            black_flat_stones: prev.black_flat_stones,
+            white_walls: prev.white_walls,
+            black_walls: prev.black_walls,
+            white_caps: prev.white_caps,
+            black_caps: prev.black_caps,
+            white_critical_squares: prev.white_critical_squares,
+            black_critical_squares: prev.black_critical_squares,
+            last_movement: their_last_movement(self),
+            second_to_last_movement: our_last_movement(self),
+            ..Default::default()
+        };
+
+        let touched_squares = match mv.expand() {
+            ExpMove::Place(_, square) => BitBoard::empty().set_square(square),
+            ExpMove::Move(origin, direction, stack_movement) => (0..=stack_movement.len() as u8)
+                .map(|distance| origin.jump_valid_direction(direction, distance))
+                .fold(BitBoard::empty(), |squares, square| {
+                    squares.set_square(square)
+                }),
+        };
+        for square in touched_squares.into_iter::<S>() {
+            group_data.groups[square] = 0;
+            group_data.white_flat_stones = group_data.white_flat_stones.clear_square(square);
+            group_data.black_flat_stones = group_data.black_flat_stones.clear_square(square);
+            group_data.white_walls = group_data.white_walls.clear_square(square);
+            group_data.black_walls = group_data.black_walls.clear_square(square);
+            group_data.white_caps = group_data.white_caps.clear_square(square);
+            group_data.black_caps = group_data.black_caps.clear_square(square);
+            let pieces = match self.top_stones[square] {
+                Some(WhiteFlat) => &mut group_data.white_flat_stones,
+                Some(BlackFlat) => &mut group_data.black_flat_stones,
+                Some(WhiteWall) => &mut group_data.white_walls,
+                Some(BlackWall) => &mut group_data.black_walls,
+                Some(WhiteCap) => &mut group_data.white_caps,
+                Some(BlackCap) => &mut group_data.black_caps,
+                None => continue,
+            };
+            *pieces = pieces.set_square(square);
+        }
+
+        // White groups are numbered before black groups, like in `compute_groups`
+        let mut next_group_id = 1;
+        let white_recheck_squares = Self::update_groups::<WhiteTr>(
+            prev,
+            &mut group_data,
+            touched_squares,
+            &mut next_group_id,
+        );
+        let black_recheck_squares = Self::update_groups::<BlackTr>(
+            prev,
+            &mut group_data,
+            touched_squares,
+            &mut next_group_id,
+        );
+
+        for square in white_recheck_squares.into_iter::<S>() {
+            group_data.white_critical_squares =
+                if self.is_critical_square_from_scratch::<WhiteTr>(&group_data, square) {
+                    group_data.white_critical_squares.set_square(square)
+                } else {
+                    group_data.white_critical_squares.clear_square(square)
+                };
+        }
+        for square in black_recheck_squares.into_iter::<S>() {
+            group_data.black_critical_squares =
+                if self.is_critical_square_from_scratch::<BlackTr>(&group_data, square) {
+                    group_data.black_critical_squares.set_square(square)
+                } else {
+                    group_data.black_critical_squares.clear_square(square)
+                };
+        }
+
+        group_data
+    }
+
+    /// Update the groups of `Us`'s road pieces from `prev`, after the pieces on `touched_squares` changed.
+    /// Group ids are given out from `next_group_id` in order of each group's lowest square, like in `compute_groups`.
+    /// Returns the squares whose critical status for `Us` may have changed.
+    fn update_groups<Us: ColorTr>(
+        prev: &GroupData<S>,
+        group_data: &mut GroupData<S>,
+        touched_squares: BitBoard,
+        next_group_id: &mut u8,
+    ) -> BitBoard {
+        let old_road_pieces = Us::road_stones(prev);
+        let road_pieces = Us::road_stones(group_data);
+
+        // Groups that may have been split, merged or shrunk by the move
+        let touched_area = touched_squares
+            .into_iter::<S>()
+            .fold(touched_squares, |squares, square| {
+                squares | utils::lookup_neighbor_table::<S>(square)
+            });
+        let mut dirty_groups = 0_u128;
+        for square in (old_road_pieces & touched_area).into_iter::<S>() {
+            dirty_groups |= 1 << prev.groups[square];
+        }
+
+        // New ids of the unchanged groups, indexed by their id in `prev`
+        let mut new_group_ids = [0_u8; 65];
+        let mut unvisited = road_pieces;
+        let mut flooded = BitBoard::empty();
+        while let Some(square) = unvisited.occupied_square::<S>() {
+            let old_group_id = prev.groups[square];
+            if touched_squares.contains(square) || dirty_groups & (1 << old_group_id) != 0 {
+                let before_flooding = unvisited;
+                connect_component(
+                    &mut unvisited,
+                    &mut group_data.groups,
+                    square,
+                    *next_group_id,
+                );
+                flooded |= before_flooding & !unvisited;
+                *next_group_id += 1;
+            } else {
+                let group_id = &mut new_group_ids[old_group_id as usize];
+                if *group_id == 0 {
+                    *group_id = *next_group_id;
+                    *next_group_id += 1;
+                    group_data.amount_in_group[*group_id as usize] =
+                        prev.amount_in_group[old_group_id as usize];
+                }
+                group_data.groups[square] = *group_id;
+                unvisited = unvisited.clear_square(square);
+            }
+        }
+
+        for square in flooded.into_iter::<S>() {
+            let group_id = group_data.groups[square] as usize;
+            group_data.amount_in_group[group_id].0 += 1;
+            group_data.amount_in_group[group_id].1 |= square.group_edge_connection();
+        }
+
+        // A square's critical status only depends on the edge connections of its neighbors' groups,
+        // which can only have changed for the flooded groups and the removed pieces
+        let group_connection = |group_data: &GroupData<S>, road_pieces: BitBoard, square| {
+            road_pieces
+                .contains(square)
+                .then(|| group_data.amount_in_group[group_data.groups[square] as usize].1)
+        };
+        (flooded | (old_road_pieces & touched_squares))
+            .into_iter::<S>()
+            .filter(|square| {
+                group_connection(prev, old_road_pieces, *square)
+                    != group_connection(group_data, road_pieces, *square)
+            })
+            .fold(BitBoard::empty(), |squares, square| {
+                squares | utils::lookup_neighbor_table::<S>(square)
+            })
+    }
+
+    /// Compute the connected road groups, with their sizes and edge connections, from the piece bitboards
+    fn compute_groups(group_data: &mut GroupData<S>) {
         let mut highest_component_id = 1;
 
         connected_components_graph(
@@ -911,18 +2058,6 @@ impl<const S: usize> Position<S> {
             group_data.amount_in_group[group_id].0 += 1;
             group_data.amount_in_group[group_id].1 |= square.group_edge_connection();
         }
-
-        for square in square::squares_iterator::<S>() {
-            if self.is_critical_square_from_scratch::<WhiteTr>(&group_data, square) {
-                group_data.white_critical_squares =
-                    group_data.white_critical_squares.set_square(square);
-            }
-            if self.is_critical_square_from_scratch::<BlackTr>(&group_data, square) {
-                group_data.black_critical_squares =
-                    group_data.black_critical_squares.set_square(square);
-            }
-        }
-        group_data
     }
 
     /// An iterator over the top stones left behind after a stack movement
@@ -944,9 +2079,57 @@ impl<const S: usize> Position<S> {
             .chain(std::iter::once(self.top_stones[square]))
     }
 
-    pub(crate) fn fcd_for_move(&self, mv: Move<S>) -> i8 {
+    /// All squares that the side to move can reach by spreading the stack on `square`,
+    /// along with the maximum number of pieces that can be dropped on each of them.
+    /// Returns an empty vector if the side to move cannot move the stack.
+    pub fn spread_reach(&self, square: Square<S>) -> Vec<(Square<S>, u8)> {
+        if self.is_placement_phase() {
+            return vec![];
+        }
+        let mut moves = vec![];
+        match self.side_to_move() {
+            Color::White => {
+                self.generate_moves_for_square_colortr::<_, WhiteTr, BlackTr>(&mut moves, square)
+            }
+            Color::Black => {
+                self.generate_moves_for_square_colortr::<_, BlackTr, WhiteTr>(&mut moves, square)
+            }
+        }
+
+        let mut reach: Vec<(Square<S>, u8)> = vec![];
+        for mv in moves {
+            if let ExpMove::Move(origin, direction, stack_movement) = mv.expand() {
+                let mut destination = origin;
+                let mut pieces_held = stack_movement.get_first().pieces_to_take;
+                for Movement { pieces_to_take } in stack_movement
+                    .into_iter()
+                    .skip(1)
+                    .chain(iter::once(Movement { pieces_to_take: 0 }))
+                {
+                    destination = destination.go_direction(direction).unwrap();
+                    let pieces_dropped = pieces_held - pieces_to_take;
+                    match reach.iter_mut().find(|(sq, _)| *sq == destination) {
+                        Some((_, max_dropped)) => *max_dropped = pieces_dropped.max(*max_dropped),
+                        None => reach.push((destination, pieces_dropped)),
+                    }
+                    pieces_held = pieces_to_take;
+                }
+            }
+        }
+        reach.sort_by_key(|(sq, _)| sq.into_inner());
+        reach
+    }
+
+    /// The change in flat count difference from playing `mv`, from the perspective of the side to move.
+    /// This is the number of flats the side to move gains on top, plus the number of flats the opponent loses.
+    ///
+    /// Flat placements give +1, except in the opening, where the opponent's flat is placed and they give -1.
+    /// Other placements give 0. Spreads count every flat that is covered or uncovered,
+    /// including the opponent's flats that are captured. Flattening a wall does not change the count by itself,
+    /// since the wall is covered by the capstone. `mv` is assumed to be legal.
+    pub fn fcd_for_move(&self, mv: Move<S>) -> i8 {
         match mv.expand() {
-            ExpMove::Place(Role::Flat, _) if self.half_moves_played() > 1 => 1,
+            ExpMove::Place(Role::Flat, _) if !self.is_placement_phase() => 1,
             ExpMove::Place(Role::Flat, _) => -1,
             ExpMove::Place(_, _) => 0,
             ExpMove::Move(square, direction, stack_movement) => {
@@ -1007,17 +2190,28 @@ impl<const S: usize> Position<S> {
             .map(|result| result.game_result())
     }
 
-    fn detailed_game_result(&self, group_data: &GroupData<S>) -> Option<DetailedGameResult> {
-        let repetitions = self
-            .hash_history
-            .iter()
-            .filter(|hash| **hash == self.hash)
-            .count();
+    /// The game result, as if repeating a position was not a draw
+    pub(crate) fn game_result_with_group_data_ignoring_repetitions(
+        &self,
+        group_data: &GroupData<S>,
+    ) -> Option<GameResult> {
+        self.detailed_game_result_ignoring_repetitions(group_data)
+            .map(|result| result.game_result())
+    }
 
-        if repetitions >= 2 {
-            return Some(DetailedGameResult::Draw);
+    fn detailed_game_result(&self, group_data: &GroupData<S>) -> Option<DetailedGameResult> {
+        if let Some(repetition_draw_count) = self.repetition_draw_count {
+            if self.repetition_count() + 1 >= repetition_draw_count as usize {
+                return Some(DetailedGameResult::Draw);
+            }
         }
+        self.detailed_game_result_ignoring_repetitions(group_data)
+    }
 
+    fn detailed_game_result_ignoring_repetitions(
+        &self,
+        group_data: &GroupData<S>,
+    ) -> Option<DetailedGameResult> {
         if group_data
             .amount_in_group
             .iter()
@@ -1076,7 +2270,19 @@ impl<const S: usize> Position<S> {
     ) -> Option<Square<S>> {
         // If the side to move is already winning,
         // the last move was either a suicide, or a double win
-        let mut suicide_win_square = None;
+        let (last_mover_road_square, suicide_road_square) =
+            self.road_squares(components, highest_component_id);
+        last_mover_road_square.or(suicide_road_square)
+    }
+
+    /// One square from a road for the player who made the last move, and one from a road for the side to move, if they have them
+    fn road_squares(
+        &self,
+        components: &AbstractBoard<u8, S>,
+        highest_component_id: u8,
+    ) -> (Option<Square<S>>, Option<Square<S>>) {
+        let mut last_mover_road_square = None;
+        let mut suicide_road_square = None;
 
         // TODO: Include highest id?
         for id in 1..highest_component_id {
@@ -1089,13 +2295,35 @@ impl<const S: usize> Position<S> {
                     .find(|&sq| components[sq] == id)
                     .unwrap();
                 if self.top_stones[square].unwrap().color() == self.side_to_move() {
-                    suicide_win_square = Some(square)
+                    suicide_road_square = Some(square)
                 } else {
-                    return Some(square);
+                    last_mover_road_square = Some(square);
                 }
             }
         }
-        suicide_win_square
+        (last_mover_road_square, suicide_road_square)
+    }
+
+    /// Which roads are on the board, and who wins by them.
+    /// The verdict always agrees with `game_result`, but this also reports double roads and suicides.
+    /// Returns `None` if neither player has a road.
+    pub fn road_winner_detailed(&self) -> Option<RoadOutcome> {
+        let group_data = self.group_data();
+        let highest_component_id = group_data
+            .amount_in_group
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find(|(_i, v)| v.0 == 0)
+            .map(|(i, _v)| i)
+            .unwrap_or(S * S + 1) as u8;
+        let last_mover = !self.side_to_move();
+        match self.road_squares(&group_data.groups, highest_component_id) {
+            (Some(_), Some(_)) => Some(RoadOutcome::DoubleRoad(last_mover)),
+            (Some(_), None) => Some(RoadOutcome::SingleRoad(last_mover)),
+            (None, Some(_)) => Some(RoadOutcome::Suicide(self.side_to_move())),
+            (None, None) => None,
+        }
     }
 
     pub(crate) fn static_eval_with_params_and_data(
@@ -1115,19 +2343,33 @@ impl<const S: usize> Position<S> {
         white_value_features.finish() + black_value_features.finish()
     }
 
+    /// Value parameters for the given komi. Parameters are only trained for 0 and 2 komi,
+    /// and the komis in between use parameters linearly interpolated between those two.
+    /// There is nothing to interpolate towards outside that range,
+    /// so negative komis use the 0 komi parameters, and komis above 2 use the 2 komi parameters.
     pub fn value_params(komi: Komi) -> &'static [f32] {
         match komi.half_komi() {
-            0 => Self::value_params_0komi(),
-            4 => Self::value_params_2komi(),
-            _ => unimplemented!("{} komi not supported in {}s", komi, S),
+            ..=0 => Self::value_params_0komi(),
+            half_komi @ 1..=3 => &Self::interpolated_params().value_params[half_komi as usize - 1],
+            4.. => Self::value_params_2komi(),
         }
     }
 
+    /// Policy parameters for the given komi, chosen or interpolated like `value_params`
     pub fn policy_params(komi: Komi) -> &'static [f32] {
         match komi.half_komi() {
-            0 => Self::policy_params_0komi(),
-            4 => Self::policy_params_2komi(),
-            _ => unimplemented!("{} komi not supported in {}s", komi, S),
+            ..=0 => Self::policy_params_0komi(),
+            half_komi @ 1..=3 => &Self::interpolated_params().policy_params[half_komi as usize - 1],
+            4.. => Self::policy_params_2komi(),
+        }
+    }
+
+    fn interpolated_params() -> &'static InterpolatedParams {
+        match S {
+            4 => INTERPOLATED_PARAMS_4S.get_or_init(InterpolatedParams::new::<S>),
+            5 => INTERPOLATED_PARAMS_5S.get_or_init(InterpolatedParams::new::<S>),
+            6 => INTERPOLATED_PARAMS_6S.get_or_init(InterpolatedParams::new::<S>),
+            _ => unimplemented!("{}s is not supported for interpolated komi.", S),
         }
     }
 
@@ -1286,6 +2528,74 @@ impl<const S: usize> Position<S> {
         }
     }
 
+    /// The perft counts for every depth from 0 to `max_depth` inclusive, for comparing against reference tables.
+    /// Once a depth has no positions, the remaining depths are 0 without being searched.
+    pub fn perft_sweep(&mut self, max_depth: u16) -> Vec<u64> {
+        let mut counts = Vec::with_capacity(max_depth as usize + 1);
+        for depth in 0..=max_depth {
+            if counts.last() == Some(&0) {
+                counts.push(0);
+            } else {
+                counts.push(self.bulk_perft(depth));
+            }
+        }
+        counts
+    }
+
+    /// The perft count of each legal move's subtree, in move generation order.
+    /// Useful for finding move generation bugs by comparing against a reference implementation.
+    /// Returns an empty vector for `depth == 0`, and a count of 1 for every move for `depth == 1`.
+    pub fn perft_divide(&mut self, depth: u16) -> Vec<(Move<S>, u64)> {
+        if depth == 0 || self.game_result().is_some() {
+            return vec![];
+        }
+        let mut moves = Vec::with_capacity(S * S * 4);
+        self.generate_moves(&mut moves);
+        moves
+            .into_iter()
+            .map(|mv| {
+                let reverse_move = self.do_move(mv);
+                let num_moves = self.bulk_perft(depth - 1);
+                self.reverse_move(reverse_move);
+                (mv, num_moves)
+            })
+            .collect()
+    }
+
+    /// Each value feature's contribution to the static evaluation with the given komi,
+    /// summing white's and black's terms. The contributions add up to `static_eval`.
+    pub fn eval_breakdown(&self, komi: Komi) -> Vec<(&'static str, f32)> {
+        let params = Self::value_params(komi);
+        let (white_params, black_params) = params.split_at(params.len() / 2);
+        let mut white_value: ValueContributions<S> = ValueContributions::new(white_params);
+        let mut black_value: ValueContributions<S> = ValueContributions::new(black_params);
+
+        self.static_eval_features(&mut white_value, &mut black_value);
+
+        let contribution = |index_pair: IndexPair, value: &ValueContributions<S>| {
+            index_pair
+                .as_slice(&value.contributions)
+                .iter()
+                .sum::<f32>()
+        };
+
+        parameters::value_indexes::<S>()
+            .named_features()
+            .into_iter()
+            .map(|(name, index_pair)| {
+                (
+                    name,
+                    contribution(index_pair, &white_value) + contribution(index_pair, &black_value),
+                )
+            })
+            .collect()
+    }
+
+    /// Each value feature's contribution to `static_eval`, using the position's own komi.
+    pub fn static_eval_breakdown(&self) -> Vec<(&'static str, f32)> {
+        self.eval_breakdown(self.komi())
+    }
+
     pub fn static_eval_with_params(&self, params: &'static [f32]) -> f32 {
         let (white_params, black_params) = params.split_at(params.len() / 2);
         let mut white_value: IncrementalValue<S> = IncrementalValue::new(white_params);
@@ -1297,13 +2607,54 @@ impl<const S: usize> Position<S> {
     }
 }
 
+/// Value and policy parameters for half komi 1, 2 and 3, interpolated between the 0 and 2 komi parameters
+struct InterpolatedParams {
+    value_params: [Box<[f32]>; 3],
+    policy_params: [Box<[f32]>; 3],
+}
+
+impl InterpolatedParams {
+    fn new<const S: usize>() -> Self {
+        let interpolate = |params_0komi: &[f32], params_2komi: &[f32], half_komi: usize| {
+            let weight = half_komi as f32 / 4.0;
+            params_0komi
+                .iter()
+                .zip(params_2komi)
+                .map(|(param_0komi, param_2komi)| {
+                    param_0komi * (1.0 - weight) + param_2komi * weight
+                })
+                .collect()
+        };
+        InterpolatedParams {
+            value_params: array::from_fn(|i| {
+                interpolate(
+                    <Position<S>>::value_params_0komi(),
+                    <Position<S>>::value_params_2komi(),
+                    i + 1,
+                )
+            }),
+            policy_params: array::from_fn(|i| {
+                interpolate(
+                    <Position<S>>::policy_params_0komi(),
+                    <Position<S>>::policy_params_2komi(),
+                    i + 1,
+                )
+            }),
+        }
+    }
+}
+
 impl<const S: usize> PositionTrait for Position<S> {
     type Move = Move<S>;
     type ReverseMove = ReverseMove<S>;
     type Settings = Settings;
 
     fn start_position_with_settings(settings: &Self::Settings) -> Self {
-        Self::start_position_with_komi(settings.komi)
+        let mut position = Self::start_position_with_komi(settings.komi);
+        position
+            .set_repetition_draw_count(settings.repetition_draw_count)
+            .unwrap();
+        position
     }
 
     fn side_to_move(&self) -> Color {
@@ -1319,50 +2670,19 @@ impl<const S: usize> PositionTrait for Position<S> {
     ///
     /// * A game is considered a draw after a three-fold repetition of the same position.
     fn generate_moves<E: Extend<Self::Move>>(&self, moves: &mut E) {
-        match self.half_moves_played() {
-            0 | 1 => moves.extend(
+        match self.side_to_move() {
+            _ if self.is_placement_phase() => moves.extend(
                 square::squares_iterator::<S>()
                     .filter(|square| self.stack_heights[*square] == 0)
                     .map(|square| Move::placement(Flat, square)),
             ),
-            _ => match self.side_to_move() {
-                Color::White => self.generate_moves_colortr::<E, WhiteTr, BlackTr>(moves),
-                Color::Black => self.generate_moves_colortr::<E, BlackTr, WhiteTr>(moves),
-            },
+            Color::White => self.generate_moves_colortr::<E, WhiteTr, BlackTr>(moves),
+            Color::Black => self.generate_moves_colortr::<E, BlackTr, WhiteTr>(moves),
         }
     }
 
     fn move_is_legal(&self, mv: Self::Move) -> bool {
-        match (mv.expand(), self.side_to_move()) {
-            (ExpMove::Place(Flat | Wall, square), Color::White) => {
-                self.stack_heights[square] == 0 && self.white_reserves_left() > 0
-            }
-            (ExpMove::Place(Flat | Wall, square), Color::Black) => {
-                self.stack_heights[square] == 0 && self.black_reserves_left() > 0
-            }
-            (ExpMove::Place(Cap, square), Color::White) => {
-                self.stack_heights[square] == 0 && self.white_caps_left() > 0
-            }
-            (ExpMove::Place(Cap, square), Color::Black) => {
-                self.stack_heights[square] == 0 && self.black_caps_left() > 0
-            }
-            (ExpMove::Move(square, _, _), Color::White) => {
-                let mut legal_moves = vec![];
-                self.generate_moves_for_square_colortr::<_, WhiteTr, BlackTr>(
-                    &mut legal_moves,
-                    square,
-                );
-                legal_moves.contains(&mv)
-            }
-            (ExpMove::Move(square, _, _), Color::Black) => {
-                let mut legal_moves = vec![];
-                self.generate_moves_for_square_colortr::<_, BlackTr, WhiteTr>(
-                    &mut legal_moves,
-                    square,
-                );
-                legal_moves.contains(&mv)
-            }
-        }
+        self.check_move_legal(mv).is_ok()
     }
 
     fn do_move(&mut self, mv: Self::Move) -> Self::ReverseMove {
@@ -1371,10 +2691,10 @@ impl<const S: usize> PositionTrait for Position<S> {
             ExpMove::Place(role, to) => {
                 debug_assert!(self.stack_heights[to] == 0);
                 // On the first move, the players place the opponent's color
-                let color_to_place = if self.half_moves_played() > 1 {
-                    self.side_to_move()
-                } else {
+                let color_to_place = if self.is_placement_phase() {
                     !self.side_to_move()
+                } else {
+                    self.side_to_move()
                 };
                 let piece = Piece::from_role_color(role, color_to_place);
                 let mut to_stack = self.get_stack(to);
@@ -1432,6 +2752,10 @@ impl<const S: usize> PositionTrait for Position<S> {
                     self.hash ^= self.zobrist_hash_for_square(to);
                 }
 
+                if flattens_stone {
+                    self.wall_flattening_plies.push(self.half_moves_played);
+                }
+
                 ReverseMove::Move(
                     square,
                     direction,
@@ -1511,6 +2835,8 @@ impl<const S: usize> PositionTrait for Position<S> {
                 }
 
                 if flattens_wall {
+                    let flattening_ply = self.wall_flattening_plies.pop();
+                    debug_assert_eq!(flattening_ply, Some(self.half_moves_played - 1));
                     debug_assert_eq!(self.top_stones[to].map(Piece::role), Some(Flat));
                     match self.top_stones[to].unwrap().color() {
                         Color::White => self.top_stones[to].replace(WhiteWall),
@@ -1628,156 +2954,17 @@ impl<const S: usize> pgn_traits::PgnPosition for Position<S> {
         fen: &str,
         settings: &Self::Settings,
     ) -> Result<Self, pgn_traits::Error> {
-        let fen_words: Vec<&str> = fen.split_whitespace().collect();
-
-        if fen_words.len() < 3 {
-            return Err(pgn_traits::Error::new_parse_error(format!(
-                "Couldn't parse TPS string \"{}\", missing move counter.",
-                fen
-            )));
-        }
-        if fen_words.len() > 3 {
-            return Err(pgn_traits::Error::new_parse_error(format!(
-                "Couldn't parse TPS string \"{}\", unexpected \"{}\"",
-                fen, fen_words[3]
-            )));
-        }
-
-        let fen_rows: Vec<&str> = fen_words[0].split('/').collect();
-        if fen_rows.len() != S {
-            return Err(pgn_traits::Error::new_parse_error(format!(
-                "Couldn't parse TPS string \"{}\", had {} rows instead of {}.",
-                fen,
-                fen_rows.len(),
-                S
-            )));
-        }
-
-        let rows: Vec<[Stack; S]> = fen_rows
-            .into_iter()
-            .map(parse_row)
-            .collect::<Result<_, _>>()
-            .map_err(|e| {
-                pgn_traits::Error::new_caused_by(
-                    pgn_traits::ErrorKind::ParseError,
-                    format!("Couldn't parse TPS string \"{}\"", fen),
-                    e,
-                )
-            })?;
-        let mut position = Position::start_position_with_settings(settings);
-        for square in square::squares_iterator::<S>() {
-            let (file, rank) = (square.file(), square.rank());
-            let stack = rows[rank as usize][file as usize];
-            for piece in stack.into_iter() {
-                match piece {
-                    WhiteFlat | WhiteWall => position.white_stones_left -= 1,
-                    WhiteCap => position.white_caps_left -= 1,
-                    BlackFlat | BlackWall => position.black_stones_left -= 1,
-                    BlackCap => position.black_caps_left -= 1,
-                }
-            }
-            position.set_stack(square, stack);
-        }
-
-        match fen_words[1] {
-            "1" => position.to_move = Color::White,
-            "2" => position.to_move = Color::Black,
-            s => {
-                return Err(pgn_traits::Error::new_parse_error(format!(
-                    "Error parsing TPS \"{}\": Got bad side to move \"{}\"",
-                    fen, s
-                )))
-            }
-        }
-
-        match fen_words[2].parse::<usize>() {
-            Ok(n) => match position.side_to_move() {
-                Color::White => position.half_moves_played = 2 * n - 2,
-                Color::Black => position.half_moves_played = 2 * n - 1,
-            },
-            Err(e) => {
-                return Err(pgn_traits::Error::new_caused_by(
-                    pgn_traits::ErrorKind::ParseError,
-                    format!(
-                        "Error parsing TPS \"{}\": Got bad move number \"{}\"",
-                        fen, fen_words[2]
-                    ),
-                    e,
-                ))
-            }
-        }
-
-        position.hash = position.zobrist_hash_from_scratch();
-
-        return Ok(position);
-
-        fn parse_row<const S: usize>(row_str: &str) -> Result<[Stack; S], pgn_traits::Error> {
-            let mut column_id = 0;
-            let mut row = [Stack::default(); S];
-            let mut row_str_iter = row_str.chars().peekable();
-            while column_id < S as u8 {
-                match row_str_iter.peek() {
-                    None => {
-                        return Err(pgn_traits::Error::new_parse_error(format!(
-                            "Couldn't parse row \"{}\": not enough pieces",
-                            row_str
-                        )))
-                    }
-                    Some('x') => {
-                        row_str_iter.next();
-                        if let Some(n) = row_str_iter.peek().and_then(|ch| ch.to_digit(10)) {
-                            row_str_iter.next();
-                            column_id += n as u8;
-                        } else {
-                            column_id += 1;
-                        }
-                        if let Some(',') | None = row_str_iter.peek() {
-                            row_str_iter.next();
-                        } else {
-                            return Err(pgn_traits::Error::new_parse_error(format!(
-                                "Expected ',' on row \"{}\", found {:?}",
-                                row_str,
-                                row_str_iter.next()
-                            )));
-                        }
-                    }
-                    Some('1') | Some('2') => {
-                        let stack = &mut row[column_id as usize];
-                        loop {
-                            match row_str_iter.next() {
-                                Some('1') => stack.push(Piece::from_role_color(Flat, Color::White)),
-                                Some('2') => stack.push(Piece::from_role_color(Flat, Color::Black)),
-                                Some('S') => {
-                                    let piece = stack.pop().unwrap();
-                                    stack.push(Piece::from_role_color(Wall, piece.color()));
-                                }
-                                Some('C') => {
-                                    let piece = stack.pop().unwrap();
-                                    stack.push(Piece::from_role_color(Cap, piece.color()));
-                                }
-                                Some(',') | None => {
-                                    column_id += 1;
-                                    break;
-                                }
-                                Some(ch) => {
-                                    return Err(pgn_traits::Error::new_parse_error(format!(
-                                        "Expected '1', '2', 'S' or 'C' on row \"{}\", found {}",
-                                        row_str, ch
-                                    )))
-                                }
-                            }
-                        }
-                    }
-                    Some(x) => {
-                        return Err(pgn_traits::Error::new_parse_error(format!(
-                            "Unexpected '{}' in row \"{}\".",
-                            x, row_str
-                        )))
-                    }
-                }
-            }
-            Ok(row)
-        }
+        let mut position = Self::parse_tps(fen, settings.komi).map_err(|err| {
+            pgn_traits::Error::new_caused_by(
+                pgn_traits::ErrorKind::ParseError,
+                format!("Couldn't parse TPS string \"{}\"", fen),
+                err,
+            )
+        })?;
+        position
+            .set_repetition_draw_count(settings.repetition_draw_count)
+            .map_err(pgn_traits::Error::new_parse_error)?;
+        Ok(position)
     }
 
     fn to_fen(&self) -> String {
@@ -6,7 +6,6 @@ use arrayvec::ArrayVec;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::position::utils::Direction::{East, North, South, West};
 use crate::position::utils::Role::{Cap, Flat, Wall};
 use crate::position::utils::{Direction, Movement, Role, StackMovement};
 use std::cmp::Ordering;
@@ -56,6 +55,49 @@ impl<const S: usize> Move<S> {
         }
     }
 
+    /// The corresponding move in `position.symmetries()[symmetry]`, where `self` is a move in `position`.
+    /// # Panics
+    /// Panics if `symmetry` is not smaller than 8.
+    pub fn apply_symmetry(self, symmetry: usize) -> Move<S> {
+        let transform_square = |square: Square<S>| match symmetry {
+            0 => square,
+            1 => square.flip_x(),
+            2 => square.flip_y(),
+            3 => square.rotate(),
+            4 => square.rotate().rotate(),
+            5 => square.rotate().rotate().rotate(),
+            6 => square.rotate().flip_x(),
+            7 => square.rotate().flip_y(),
+            _ => panic!("Invalid symmetry {}", symmetry),
+        };
+        let transform_direction = |direction: Direction| match symmetry {
+            0 => direction,
+            1 => direction.flip_x(),
+            2 => direction.flip_y(),
+            3 => direction.rotate(),
+            4 => direction.rotate().rotate(),
+            5 => direction.rotate().rotate().rotate(),
+            6 => direction.rotate().flip_x(),
+            7 => direction.rotate().flip_y(),
+            _ => panic!("Invalid symmetry {}", symmetry),
+        };
+        match self.expand() {
+            ExpMove::Place(role, square) => Move::placement(role, transform_square(square)),
+            ExpMove::Move(square, direction, stack_movement) => Move::movement(
+                transform_square(square),
+                transform_direction(direction),
+                stack_movement,
+            ),
+        }
+    }
+
+    /// The inverse of `apply_symmetry`, mapping a move in `position.symmetries()[symmetry]` back to `position`
+    pub fn apply_inverse_symmetry(self, symmetry: usize) -> Move<S> {
+        // Quarter turns are inverted by turning the other way, the other symmetries are their own inverse
+        const INVERSE_SYMMETRIES: [usize; 8] = [0, 1, 2, 5, 4, 3, 6, 7];
+        self.apply_symmetry(INVERSE_SYMMETRIES[symmetry])
+    }
+
     pub fn expand(self) -> ExpMove<S> {
         if self.inner >> 8 == 0 {
             unsafe {
@@ -81,6 +123,23 @@ impl<const S: usize> Move<S> {
         Square::from_u8(self.inner as u8 & 63)
     }
 
+    /// The move's compact 16-bit encoding. The encoding is stable, and can be stored and read back with `from_u16`.
+    pub fn to_u16(self) -> u16 {
+        self.inner
+    }
+
+    /// Decode a move from `to_u16`. Returns `None` if `n` is not a well-formed move on a board of this size,
+    /// but does not check that the move is legal in any position.
+    pub fn from_u16(n: u16) -> Option<Self> {
+        let square = n as u8 & 63;
+        let role_or_direction = (n as u8 >> 6) & 3;
+        if square as usize >= S * S || (n >> 8 == 0 && role_or_direction > 2) {
+            None
+        } else {
+            Some(Move { inner: n })
+        }
+    }
+
     pub fn from_string(input: &str) -> Result<Self, pgn_traits::Error> {
         // Trim crush notation
         let input = input.trim_end_matches('*');
@@ -231,6 +290,21 @@ impl<const S: usize> Move<S> {
     pub fn to_string_playtak(self) -> String {
         self.expand().to_string_playtak()
     }
+
+    /// Like `to_string`, but never omits the number of pieces taken or dropped, e.g. `1a1>1` instead of `a1>`.
+    /// Some external tools only accept this form.
+    pub fn to_string_verbose(self) -> String {
+        self.expand().to_string_verbose()
+    }
+}
+
+impl<const S: usize> FromStr for Move<S> {
+    type Err = pgn_traits::Error;
+
+    /// Parse a move in PTN, like `Move::from_string`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::from_string(input)
+    }
 }
 
 impl<const S: usize> fmt::Display for Move<S> {
@@ -295,6 +369,14 @@ impl<const S: usize> ExpMove<S> {
 
 impl<const S: usize> fmt::Display for ExpMove<S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_ptn(f, false)
+    }
+}
+
+impl<const S: usize> ExpMove<S> {
+    /// Write the move in PTN. If `verbose` is set, the number of pieces taken and dropped is always written,
+    /// even when it could be omitted.
+    fn write_ptn<W: fmt::Write>(&self, f: &mut W, verbose: bool) -> fmt::Result {
         match self {
             ExpMove::Place(role, square) => match role {
                 Cap => write!(f, "C{}", square),
@@ -303,19 +385,14 @@ impl<const S: usize> fmt::Display for ExpMove<S> {
             },
             ExpMove::Move(square, direction, stack_movements) => {
                 let mut pieces_held = stack_movements.get_first().pieces_to_take;
-                if pieces_held == 1 {
-                    write!(f, "{}", square).unwrap();
+                if pieces_held == 1 && !verbose {
+                    write!(f, "{}", square)?;
                 } else {
-                    write!(f, "{}{}", pieces_held, square).unwrap();
-                }
-                match direction {
-                    North => f.write_char('+')?,
-                    West => f.write_char('<')?,
-                    East => f.write_char('>')?,
-                    South => f.write_char('-')?,
+                    write!(f, "{}{}", pieces_held, square)?;
                 }
+                f.write_char(direction.to_char())?;
                 // Omit number of pieces dropped, if all stones are dropped immediately
-                if stack_movements.len() > 1 {
+                if stack_movements.len() > 1 || verbose {
                     for movement in stack_movements.into_iter().skip(1) {
                         let pieces_to_drop = pieces_held - movement.pieces_to_take;
                         write!(f, "{}", pieces_to_drop)?;
@@ -328,6 +405,14 @@ impl<const S: usize> fmt::Display for ExpMove<S> {
             }
         }
     }
+
+    /// Like `to_string`, but never omits the number of pieces taken or dropped, e.g. `1a1>1` instead of `a1>`.
+    /// Placements are written the same way as in `to_string`.
+    pub fn to_string_verbose(&self) -> String {
+        let mut output = String::new();
+        self.write_ptn(&mut output, true).unwrap();
+        output
+    }
 }
 
 /// The counterpart of `Move`. When applied to a `Board`, it fully reverses the accompanying `Move`.
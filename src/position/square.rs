@@ -51,6 +51,21 @@ impl<const S: usize> Square<S> {
         self.inner / S as u8
     }
 
+    /// Mirror the square across the vertical axis, matching `Position::flip_board_x`
+    pub const fn flip_x(self) -> Self {
+        Self::from_rank_file(self.rank(), S as u8 - self.file() - 1)
+    }
+
+    /// Mirror the square across the horizontal axis, matching `Position::flip_board_y`
+    pub const fn flip_y(self) -> Self {
+        Self::from_rank_file(S as u8 - self.rank() - 1, self.file())
+    }
+
+    /// Rotate the square a quarter turn, matching `Position::rotate_board`
+    pub const fn rotate(self) -> Self {
+        Self::from_rank_file(self.file(), S as u8 - self.rank() - 1)
+    }
+
     pub fn downcast_size<const N: usize>(self) -> Square<N> {
         if S == N {
             unsafe { mem::transmute::<Square<S>, Square<N>>(self) }
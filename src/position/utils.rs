@@ -17,23 +17,49 @@ use crate::position::Role::{Cap, Flat, Wall};
 use super::{GroupEdgeConnection, Square, SquareCacheEntry};
 
 #[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Komi {
     half_komi: i8,
 }
 
 impl Komi {
-    pub fn from_half_komi(half_komi: i8) -> Option<Self> {
-        if (-10..=10).contains(&half_komi) {
-            Some(Komi { half_komi })
+    /// Returns an error if `half_komi` is outside `half_komi_range()`.
+    pub fn from_half_komi(half_komi: i8) -> Result<Self, String> {
+        let range = Self::half_komi_range();
+        if range.contains(&half_komi) {
+            Ok(Komi { half_komi })
         } else {
-            None
+            Err(format!(
+                "Invalid half komi {}, must be between {} and {} inclusive",
+                half_komi,
+                range.start(),
+                range.end()
+            ))
         }
     }
 
+    /// The supported values for half komi, i.e. komi from -5 to 5 flats.
+    /// The evaluation only has trained parameters for 0 and 2 komi. Komis in between are evaluated with interpolated parameters,
+    /// and komis outside that range with the parameters of the closest trained komi, see `Position::value_params`.
+    pub fn half_komi_range() -> ops::RangeInclusive<i8> {
+        -10..=10
+    }
+
     pub fn half_komi(self) -> i8 {
         self.half_komi
     }
 
+    /// Adjust a player's flat lead for komi, which is in black's favor.
+    /// With half-flat komi, the adjusted lead is rounded away from zero,
+    /// so its sign always agrees with `game_result_with_flatcounts`.
+    pub fn komi_adjusted_flat_lead(self, color: Color, flat_lead: i8) -> i8 {
+        let half_flat_lead = match color {
+            Color::White => 2 * flat_lead - self.half_komi,
+            Color::Black => 2 * flat_lead + self.half_komi,
+        };
+        (half_flat_lead + half_flat_lead.signum()) / 2
+    }
+
     pub fn game_result_with_flatcounts(self, white_flats: i8, black_flats: i8) -> GameResult {
         match (2 * (white_flats - black_flats) - self.half_komi).signum() {
             -1 => GameResult::BlackWin,
@@ -42,6 +68,49 @@ impl Komi {
             _ => unreachable!(),
         }
     }
+
+    /// White's flat lead minus komi, in full flats. For example, a 1 flat lead with 2.5 komi gives -1.5.
+    /// The sign always agrees with `game_result_with_flatcounts`: Positive is a White win, negative a Black win, and zero a draw.
+    pub fn flat_margin(self, white_flats: i8, black_flats: i8) -> f32 {
+        (2 * (white_flats - black_flats) - self.half_komi) as f32 / 2.0
+    }
+
+    /// Describe how a flat win was decided, for end-of-game displays.
+    /// For example, `"White 21 vs Black 18 +2.5 komi → White wins by 0.5 flats"`.
+    /// All arithmetic is done in half flats, so the result is exact.
+    pub fn explain_flat_result(self, white_flats: i8, black_flats: i8) -> String {
+        let result = self.game_result_with_flatcounts(white_flats, black_flats);
+        let decided_by_komi =
+            result != Komi::default().game_result_with_flatcounts(white_flats, black_flats);
+        let margin = format_half_flats((2 * (white_flats - black_flats) - self.half_komi).abs());
+
+        let outcome = match (result, decided_by_komi) {
+            (GameResult::WhiteWin, false) => format!("White wins by {} flats", margin),
+            (GameResult::BlackWin, false) => format!("Black wins by {} flats", margin),
+            (GameResult::WhiteWin, true) => "White wins by komi".to_string(),
+            (GameResult::BlackWin, true) => "Black wins by komi".to_string(),
+            (GameResult::Draw, false) => "Draw".to_string(),
+            (GameResult::Draw, true) => "Draw by komi".to_string(),
+        };
+
+        format!(
+            "White {} vs Black {} {}{} komi → {}",
+            white_flats,
+            black_flats,
+            if self.half_komi < 0 { '-' } else { '+' },
+            format_half_flats(self.half_komi.abs()),
+            outcome
+        )
+    }
+}
+
+/// Formats a non-negative number of half flats with exactly one decimal, e.g. `5` as `"2.5"`
+fn format_half_flats(half_flats: i8) -> String {
+    format!(
+        "{}.{}",
+        half_flats / 2,
+        if half_flats % 2 == 0 { 0 } else { 5 }
+    )
 }
 
 impl TryFrom<f64> for Komi {
@@ -55,7 +124,7 @@ impl TryFrom<f64> for Komi {
             2.5, 3.0, 3.5, 4.0, 4.5, 5.0,
         ]
         .iter()
-        .zip(-10..=10)
+        .zip(Self::half_komi_range())
         .find(|(komi, _)| **komi == value)
         {
             Ok(Komi { half_komi })
@@ -199,6 +268,9 @@ impl ops::Not for Piece {
 }
 
 /// The contents of a square on the board, consisting of zero or more pieces
+///
+/// The pieces under the top stone are stored as a 64-bit bitboard, so a stack can hold at most `Stack::MAX_HEIGHT` pieces.
+/// This covers every possible stack on 6s and smaller. On 7s and 8s, taller stacks are legal in theory, but are not supported.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stack {
@@ -208,6 +280,8 @@ pub struct Stack {
 }
 
 impl Stack {
+    pub const MAX_HEIGHT: u8 = 65;
+
     /// Get a piece by index. 0 is the bottom of the stack
     pub fn get(&self, i: u8) -> Option<Piece> {
         if i >= self.height {
@@ -225,10 +299,17 @@ impl Stack {
         self.top_stone
     }
 
+    /// Iterate over the pieces from the top stone down to the bottom of the stack.
+    /// This is the reverse order of `into_iter`.
+    pub fn iter_top_down(&self) -> impl Iterator<Item = Piece> + '_ {
+        (0..self.height).rev().map(|i| self.get(i).unwrap())
+    }
+
     /// Push a new piece to the top of the stack
     ///
     /// Any piece already on the stack will be flattened, including capstones
     pub fn push(&mut self, piece: Piece) {
+        debug_assert!(self.height < Self::MAX_HEIGHT);
         if self.height > 0 && self.top_stone.unwrap().color() == Color::White {
             self.bitboard = self.bitboard.set(self.height - 1);
         }
@@ -288,6 +369,49 @@ impl Stack {
     pub fn len(&self) -> u8 {
         self.height
     }
+
+    /// Counts of the pieces buried under the top stone, or `None` for an empty stack
+    pub fn composition(&self) -> Option<StackComposition> {
+        let top_stone = self.top_stone?;
+        let buried_white = (self.bitboard & BitBoard::lower_n_bits(self.height - 1)).count();
+        let buried_black = self.height - 1 - buried_white;
+        let (supports, captives) = match top_stone.color() {
+            Color::White => (buried_white, buried_black),
+            Color::Black => (buried_black, buried_white),
+        };
+        Some(StackComposition {
+            top_stone,
+            supports,
+            captives,
+        })
+    }
+
+    /// The number of opponent pieces buried in the stack, if `color` controls it. Otherwise 0
+    pub fn captives(&self, color: Color) -> u8 {
+        match self.composition() {
+            Some(composition) if composition.top_stone.color() == color => composition.captives,
+            _ => 0,
+        }
+    }
+
+    /// The number of `color`'s own pieces buried in the stack, if `color` controls it. Otherwise 0
+    pub fn reserves_of(&self, color: Color) -> u8 {
+        match self.composition() {
+            Some(composition) if composition.top_stone.color() == color => composition.supports,
+            _ => 0,
+        }
+    }
+}
+
+/// The makeup of a non-empty stack, from the perspective of the player controlling it
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StackComposition {
+    pub top_stone: Piece,
+    /// The controlling player's own pieces under the top stone
+    pub supports: u8,
+    /// The opponent's pieces under the top stone
+    pub captives: u8,
 }
 
 /// An iterator over the pieces in a stack, from the bottom up
@@ -327,11 +451,44 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// All four directions, in the order of their discriminants
+    pub const fn all() -> [Direction; 4] {
+        [North, West, East, South]
+    }
+
     pub(crate) fn from_disc(disc: u8) -> Self {
         assert!(disc < 4);
         unsafe { mem::transmute(disc) }
     }
 
+    /// Mirror the direction across the vertical axis, matching `Position::flip_board_x`
+    pub fn flip_x(self) -> Direction {
+        match self {
+            West => East,
+            East => West,
+            direction => direction,
+        }
+    }
+
+    /// Mirror the direction across the horizontal axis, matching `Position::flip_board_y`
+    pub fn flip_y(self) -> Direction {
+        match self {
+            North => South,
+            South => North,
+            direction => direction,
+        }
+    }
+
+    /// Rotate the direction a quarter turn, matching `Position::rotate_board`
+    pub fn rotate(self) -> Direction {
+        match self {
+            North => East,
+            West => North,
+            East => South,
+            South => West,
+        }
+    }
+
     pub(crate) fn reverse(self) -> Direction {
         match self {
             North => South,
@@ -357,6 +514,16 @@ impl Direction {
             _ => None,
         }
     }
+
+    /// The character for the direction in PTN move notation. The inverse of `parse`
+    pub fn to_char(self) -> char {
+        match self {
+            North => '+',
+            West => '<',
+            East => '>',
+            South => '-',
+        }
+    }
 }
 
 /// One or more `Movement`s, storing how many pieces are dropped off at each step
@@ -0,0 +1,167 @@
+//! A compact binary format for analyzed games, storing each move together with an evaluation.
+//!
+//! All integers are little-endian. The layout of version 1 is:
+//!
+//! | Bytes | Content |
+//! |-------|---------|
+//! | 4     | Magic bytes `TKG` followed by the format version, currently 1 |
+//! | 1     | Board size |
+//! | 1     | Half komi, as an `i8` |
+//! | 1     | Game result, as an index into `Position::POSSIBLE_GAME_RESULTS`, or 255 if the result is unknown |
+//! | 2     | Length `n` of the start position's TPS string, or 0 for the standard start position |
+//! | n     | The start position's TPS string, in UTF-8 |
+//! | 4     | Number of moves `m` |
+//! | 4 * m | Each move, as its `Move::to_u16` encoding followed by the bits of an `f16` evaluation |
+//!
+//! Tags, move annotations and comments are not stored.
+
+use std::io::{self, Read, Write};
+
+use board_game_traits::Position as PositionTrait;
+use half::f16;
+use pgn_traits::PgnPosition;
+
+use crate::position::{Komi, Move, Position};
+use crate::ptn::{Game, PtnMove};
+
+const MAGIC: &[u8; 3] = b"TKG";
+const VERSION: u8 = 1;
+const UNKNOWN_RESULT: u8 = 255;
+
+/// Write `game` in the binary format, with one evaluation per move.
+///
+/// # Panics
+/// Panics if `evals` does not have the same length as `game.moves`.
+pub fn write_game<W: Write, const S: usize>(
+    writer: &mut W,
+    game: &Game<Position<S>>,
+    evals: &[f16],
+) -> io::Result<()> {
+    assert_eq!(
+        game.moves.len(),
+        evals.len(),
+        "Got {} evals for {} moves",
+        evals.len(),
+        game.moves.len()
+    );
+    let komi = game.start_position.komi();
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION, S as u8, komi.half_komi() as u8])?;
+
+    let result_index = match game.game_result_str {
+        Some(result_str) => <Position<S>>::POSSIBLE_GAME_RESULTS
+            .iter()
+            .position(|(possible_result, _)| *possible_result == result_str)
+            .ok_or_else(|| invalid_data(format!("Invalid game result {}", result_str)))?
+            as u8,
+        None => UNKNOWN_RESULT,
+    };
+    writer.write_all(&[result_index])?;
+
+    let tps = if game.start_position == <Position<S>>::start_position_with_komi(komi) {
+        String::new()
+    } else {
+        game.start_position.to_fen()
+    };
+    writer.write_all(&(tps.len() as u16).to_le_bytes())?;
+    writer.write_all(tps.as_bytes())?;
+
+    writer.write_all(&(game.moves.len() as u32).to_le_bytes())?;
+    for (PtnMove { mv, .. }, eval) in game.moves.iter().zip(evals) {
+        writer.write_all(&mv.to_u16().to_le_bytes())?;
+        writer.write_all(&eval.to_bits().to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Read a game written by `write_game`, returning the game and the evaluation of each move.
+/// Returns an error if the data is malformed, is for a different board size, or contains illegal moves.
+pub fn read_game<R: Read, const S: usize>(
+    reader: &mut R,
+) -> io::Result<(Game<Position<S>>, Vec<f16>)> {
+    let mut header = [0; 7];
+    reader.read_exact(&mut header)?;
+    if &header[0..3] != MAGIC {
+        return Err(invalid_data("Not a binary game file".to_string()));
+    }
+    if header[3] != VERSION {
+        return Err(invalid_data(format!(
+            "Unsupported binary game version {}",
+            header[3]
+        )));
+    }
+    if header[4] as usize != S {
+        return Err(invalid_data(format!(
+            "Expected a {}s game, got size {}",
+            S, header[4]
+        )));
+    }
+    let komi = Komi::from_half_komi(header[5] as i8).map_err(invalid_data)?;
+    let game_result_str = match header[6] {
+        UNKNOWN_RESULT => None,
+        i => Some(
+            <Position<S>>::POSSIBLE_GAME_RESULTS
+                .get(i as usize)
+                .ok_or_else(|| invalid_data(format!("Invalid game result index {}", i)))?
+                .0,
+        ),
+    };
+
+    let tps_len = read_u16(reader)? as usize;
+    let start_position = if tps_len == 0 {
+        <Position<S>>::start_position_with_komi(komi)
+    } else {
+        let mut tps = vec![0; tps_len];
+        reader.read_exact(&mut tps)?;
+        let tps = String::from_utf8(tps).map_err(|err| invalid_data(err.to_string()))?;
+        <Position<S>>::from_fen_with_komi(&tps, komi)
+            .map_err(|err| invalid_data(err.to_string()))?
+    };
+
+    let mut num_moves_bytes = [0; 4];
+    reader.read_exact(&mut num_moves_bytes)?;
+    let num_moves = u32::from_le_bytes(num_moves_bytes);
+
+    let mut position = start_position.clone();
+    let mut moves = vec![];
+    let mut evals = vec![];
+    for _ in 0..num_moves {
+        let move_bits = read_u16(reader)?;
+        let eval = f16::from_bits(read_u16(reader)?);
+        let mv = <Move<S>>::from_u16(move_bits)
+            .filter(|mv| position.move_is_legal(*mv))
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "Illegal move {:#06x} in position {}",
+                    move_bits,
+                    position.to_fen()
+                ))
+            })?;
+        position.do_move(mv);
+        moves.push(PtnMove {
+            mv,
+            annotations: vec![],
+            comment: String::new(),
+        });
+        evals.push(eval);
+    }
+
+    let game = Game {
+        start_position,
+        moves,
+        game_result_str,
+        tags: vec![],
+    };
+    Ok((game, evals))
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,78 @@
+use board_game_traits::Position;
+
+use super::Game;
+
+/// Steps back and forth through the moves of a game, for replaying it.
+/// Reverse moves are cached when stepping forward, so stepping back never replays the game.
+pub struct GameCursor<'a, B: Position> {
+    game: &'a Game<B>,
+    position: B,
+    reverse_moves: Vec<B::ReverseMove>,
+}
+
+impl<'a, B: Position + Clone> GameCursor<'a, B> {
+    /// Create a cursor at the start position of the game
+    pub fn new(game: &'a Game<B>) -> Self {
+        GameCursor {
+            game,
+            position: game.start_position.clone(),
+            reverse_moves: Vec::with_capacity(game.moves.len()),
+        }
+    }
+}
+
+impl<'a, B: Position> GameCursor<'a, B> {
+    pub fn current_position(&self) -> &B {
+        &self.position
+    }
+
+    /// The number of moves played from the game's start position
+    pub fn ply(&self) -> usize {
+        self.reverse_moves.len()
+    }
+
+    pub fn num_plies(&self) -> usize {
+        self.game.moves.len()
+    }
+
+    /// Play the next move of the game. Returns false if already at the end of the game.
+    pub fn forward(&mut self) -> bool {
+        match self.game.moves.get(self.ply()) {
+            Some(ptn_move) => {
+                let reverse_move = self.position.do_move(ptn_move.mv.clone());
+                self.reverse_moves.push(reverse_move);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take back the last move. Returns false if already at the start of the game.
+    pub fn back(&mut self) -> bool {
+        match self.reverse_moves.pop() {
+            Some(reverse_move) => {
+                self.position.reverse_move(reverse_move);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the cursor to the position after `ply` moves.
+    /// # Panics
+    /// Panics if `ply` is greater than the number of moves in the game.
+    pub fn goto(&mut self, ply: usize) {
+        assert!(
+            ply <= self.num_plies(),
+            "Cannot go to ply {}, game only has {} plies",
+            ply,
+            self.num_plies()
+        );
+        while self.ply() < ply {
+            self.forward();
+        }
+        while self.ply() > ply {
+            self.back();
+        }
+    }
+}
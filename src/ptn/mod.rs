@@ -2,9 +2,13 @@ use board_game_traits::{GameResult, Position};
 use pgn_traits::PgnPosition;
 use std::error;
 
+pub mod binary;
+mod game_cursor;
 pub mod ptn_parser;
 pub mod ptn_writer;
 
+pub use game_cursor::GameCursor;
+
 type ParseError = Box<dyn error::Error + Send + Sync>;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1,5 +1,8 @@
 use crate::ptn::{Game, ParseError, PtnMove};
 use pgn_traits::PgnPosition;
+use std::io::{self, BufRead};
+use std::marker::PhantomData;
+use std::mem;
 use std::str::FromStr;
 
 pub fn parse_ptn<B: PgnPosition>(input: &str) -> Result<Vec<Game<B>>, ParseError> {
@@ -24,6 +27,88 @@ pub fn parse_ptn<B: PgnPosition>(input: &str) -> Result<Vec<Game<B>>, ParseError
     }
 }
 
+/// Parse the games in `reader` one at a time, only keeping one game in memory.
+/// A game that fails to parse yields an error, and parsing continues with the next game.
+/// The stream stops after the first IO error.
+pub fn parse_ptn_stream<B: PgnPosition, R: BufRead>(reader: R) -> PtnStream<B, R> {
+    PtnStream {
+        lines: reader.lines(),
+        game_text: String::new(),
+        has_moves: false,
+        in_comment: false,
+        finished: false,
+        phantom: PhantomData,
+    }
+}
+
+/// Iterator over the games in a PTN file, created by `parse_ptn_stream`
+pub struct PtnStream<B, R> {
+    lines: io::Lines<R>,
+    /// The lines read so far for the current game
+    game_text: String,
+    has_moves: bool,
+    in_comment: bool,
+    finished: bool,
+    phantom: PhantomData<B>,
+}
+
+impl<B: PgnPosition, R: BufRead> Iterator for PtnStream<B, R> {
+    type Item = Result<Game<B>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(err)) => {
+                    self.finished = true;
+                    self.game_text.clear();
+                    return Some(Err(Box::new(err)));
+                }
+                None => {
+                    self.finished = true;
+                    break;
+                }
+            };
+            let is_tag_line = !self.in_comment && line.trim_start().starts_with('[');
+
+            // A tag after the moves starts the next game
+            let finished_game_text = if is_tag_line && self.has_moves {
+                self.has_moves = false;
+                Some(mem::take(&mut self.game_text))
+            } else {
+                None
+            };
+
+            if !is_tag_line {
+                for ch in line.chars() {
+                    match ch {
+                        '{' => self.in_comment = true,
+                        '}' => self.in_comment = false,
+                        _ => (),
+                    }
+                }
+                if !line.trim().is_empty() {
+                    self.has_moves = true;
+                }
+            }
+            self.game_text.push_str(&line);
+            self.game_text.push('\n');
+
+            if let Some(game_text) = finished_game_text {
+                return Some(parse_game(&mut ParserData { input: &game_text }));
+            }
+        }
+
+        // Parse the last game
+        if self.game_text.chars().any(|ch| !ch.is_whitespace()) {
+            let game_text = mem::take(&mut self.game_text);
+            Some(parse_game(&mut ParserData { input: &game_text }))
+        } else {
+            None
+        }
+    }
+}
+
 fn parse_game<B: PgnPosition>(input: &mut ParserData) -> Result<Game<B>, ParseError> {
     let mut tags = vec![];
     input.skip_whitespaces();
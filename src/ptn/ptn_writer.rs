@@ -8,6 +8,32 @@ use std::io::Write;
 const LINE_WIDTH: usize = 80;
 
 impl<B: PgnPosition + Clone> Game<B> {
+    /// Write the game as PTN, annotating each move with an evaluation comment like `{W: 54.3%}`.
+    ///
+    /// `white_win_probabilities` must have one entry per move, each being white's winning probability in the position after that move, in the range `0.0..=1.0`.
+    /// The evaluation is appended to any existing comment on the move.
+    pub fn game_to_ptn_with_evals<W: Write>(
+        &self,
+        f: &mut W,
+        white_win_probabilities: &[f32],
+    ) -> Result<(), io::Error> {
+        assert_eq!(
+            self.moves.len(),
+            white_win_probabilities.len(),
+            "Expected one evaluation per move"
+        );
+        let mut game = self.clone();
+        for (ptn_move, eval) in game.moves.iter_mut().zip(white_win_probabilities) {
+            let eval_comment = format!("W: {:.1}%", eval * 100.0);
+            if ptn_move.comment.is_empty() {
+                ptn_move.comment = eval_comment;
+            } else {
+                ptn_move.comment = format!("{}, {}", ptn_move.comment, eval_comment);
+            }
+        }
+        game.game_to_ptn(f)
+    }
+
     pub fn game_to_ptn<W: Write>(&self, f: &mut W) -> Result<(), io::Error> {
         // Write the required tags first, in the correct order
         // Fill in default value if they are not available
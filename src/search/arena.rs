@@ -1,22 +1,35 @@
 use std::{
     alloc::{self, Layout, LayoutError},
     any,
+    cell::Cell,
     error::Error,
     fmt::{self, Display},
     marker::PhantomData,
     mem,
     num::NonZeroU32,
-    slice,
-    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+    ptr, slice,
+    sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering},
 };
 
+/// A bump allocator for the search tree, addressed by 32-bit indices.
+///
+/// The arena is split into one or more blocks of memory. A fixed arena allocates a single block up front,
+/// while a growable arena allocates fixed-size blocks on demand, up to its maximum size.
+/// Existing elements are never moved, so indices stay valid when the arena grows.
+///
+/// The arena hands out mutable references through `&self`, so it must not be shared between threads.
 pub struct Arena<const S: usize = 16> {
+    // The single block of a fixed arena, so that lookups can skip the block table. Null for a growable arena
     data: *mut u8,
-    orig_pointer: *mut u8,
+    blocks: Box<[AtomicPtr<u8>]>,
+    orig_pointers: Box<[AtomicPtr<u8>]>,
     layout: Layout,
+    block_bits: u32,
     next_index: AtomicU32,
     max_index: u32,
     pub stats: ArenaStats,
+    // The block table is atomic, but `get_mut` still allows aliasing through `&self`, so opt out of `Sync`
+    not_sync: PhantomData<Cell<()>>,
 }
 
 #[derive(Debug, Default)]
@@ -33,6 +46,7 @@ impl<const S: usize> fmt::Debug for Arena<S> {
             .field("next_index", &self.next_index)
             .field("slot_size", &S)
             .field("max_index", &self.max_index)
+            .field("num_blocks", &self.blocks.len())
             .finish()
     }
 }
@@ -94,6 +108,7 @@ pub enum ArenaError {
     AllocationFailed(usize),
     AllocationLayoutError(LayoutError, usize),
     InvalidNumberOfSlots(u32),
+    InvalidBlockSize(u32),
 }
 
 impl Display for ArenaError {
@@ -111,6 +126,13 @@ impl Display for ArenaError {
             ArenaError::InvalidNumberOfSlots(num_slots) => {
                 write!(f, "Arena cannot be allocated with {} slots", *num_slots)
             }
+            ArenaError::InvalidBlockSize(slots_per_block) => {
+                write!(
+                    f,
+                    "Arena block size must be a power of two, got {} slots",
+                    *slots_per_block
+                )
+            }
         }
     }
 }
@@ -118,44 +140,107 @@ impl Display for ArenaError {
 impl Error for ArenaError {}
 
 impl<const S: usize> Arena<S> {
+    /// Create an arena with room for `num_slots` slots, allocating all the memory up front.
     pub fn new(num_slots: u32) -> Result<Self, ArenaError> {
-        if S == 0 {
-            panic!("Arena slots cannot have size zero")
+        if num_slots == 0 || num_slots >= u32::MAX - 1 {
+            return Err(ArenaError::InvalidNumberOfSlots(num_slots));
         }
+        // A single block, large enough to contain every index
+        let mut arena = Self::with_blocks(num_slots, 32, num_slots as u64 + 1)?;
+        arena.allocate_block(0)?;
+        arena.data = *arena.blocks[0].get_mut();
+        Ok(arena)
+    }
+
+    /// Create an arena that grows in blocks of `slots_per_block` slots, up to a maximum of `num_slots` slots.
+    ///
+    /// Compared to a fixed arena, memory is only allocated as the tree grows, so a large maximum size is cheap.
+    /// The tradeoffs are an extra indirection on every lookup, some wasted space at the end of each block,
+    /// and that running out of system memory is only detected during search, not when the arena is created.
+    pub fn new_growable(num_slots: u32, slots_per_block: u32) -> Result<Self, ArenaError> {
         if num_slots == 0 || num_slots >= u32::MAX - 1 {
             return Err(ArenaError::InvalidNumberOfSlots(num_slots));
         }
+        if !slots_per_block.is_power_of_two() {
+            return Err(ArenaError::InvalidBlockSize(slots_per_block));
+        }
+        let arena = Self::with_blocks(
+            num_slots,
+            slots_per_block.trailing_zeros(),
+            slots_per_block as u64,
+        )?;
+        arena.allocate_block(0)?;
+        Ok(arena)
+    }
+
+    fn with_blocks(
+        num_slots: u32,
+        block_bits: u32,
+        slots_per_block: u64,
+    ) -> Result<Self, ArenaError> {
+        if S == 0 {
+            panic!("Arena slots cannot have size zero")
+        }
         let raw_alignment = raw_alignment(S);
-        let Some(size) = (num_slots as usize + 2).checked_mul(S) else {
-            return Err(ArenaError::TooLarge((num_slots as u64 + 2) * S as u64));
+        // Allocate one extra slot per block, to ensure correct alignment
+        let Some(size) = (slots_per_block as usize)
+            .checked_add(1)
+            .and_then(|slots| slots.checked_mul(S))
+        else {
+            return Err(ArenaError::TooLarge((slots_per_block + 1) * S as u64));
         };
 
         let layout = Layout::from_size_align(size, raw_alignment)
             .map_err(|err| ArenaError::AllocationLayoutError(err, size))?;
 
-        let (data, orig_pointer) = unsafe {
-            let ptr = alloc::alloc(layout);
+        let max_index = num_slots + 1;
+        let num_blocks = (max_index as u64 >> block_bits) as usize + 1;
+
+        Ok(Self {
+            data: ptr::null_mut(),
+            blocks: (0..num_blocks)
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect(),
+            orig_pointers: (0..num_blocks)
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect(),
+            layout,
+            block_bits,
+            next_index: AtomicU32::new(1),
+            max_index,
+            stats: ArenaStats::default(),
+            not_sync: PhantomData,
+        })
+    }
+
+    fn allocate_block(&self, block: usize) -> Result<(), ArenaError> {
+        if !self.blocks[block].load(Ordering::Acquire).is_null() {
+            return Ok(());
+        }
+        unsafe {
+            let ptr = alloc::alloc(self.layout);
 
             if ptr.is_null() {
-                return Err(ArenaError::AllocationFailed(size));
+                return Err(ArenaError::AllocationFailed(self.layout.size()));
             }
 
             // Make sure the pointer is correctly aligned
-            if (ptr as usize) % S == 0 {
-                (ptr, ptr)
+            let data = if (ptr as usize) % S == 0 {
+                ptr
             } else {
-                (ptr.add(S - (ptr as usize) % S), ptr)
+                ptr.add(S - (ptr as usize) % S)
+            };
+            // Only the first caller to allocate the block gets to keep it
+            if self.blocks[block]
+                .compare_exchange(ptr::null_mut(), data, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                alloc::dealloc(ptr, self.layout);
+                return Ok(());
             }
-        };
-
-        Ok(Self {
-            data,
-            orig_pointer,
-            layout,
-            next_index: AtomicU32::new(1),
-            max_index: num_slots + 1,
-            stats: ArenaStats::default(),
-        })
+            self.orig_pointers[block].store(ptr, Ordering::Relaxed);
+        }
+        Ok(())
     }
 
     /// Get a reference to an element in the arena
@@ -287,14 +372,44 @@ impl<const S: usize> Arena<S> {
     }
 
     /// Gets an appropriate index for the new element, if there is space available
+    /// Elements never cross block boundaries, so this may skip the remainder of the current block
     fn get_index_for_element(&self, slots: u32) -> Option<u32> {
-        self.next_index
-            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |index| {
-                index
-                    .checked_add(slots)
-                    .filter(|next_index| *next_index <= self.max_index)
-            })
-            .ok()
+        if slots as u64 > 1 << self.block_bits {
+            return None;
+        }
+        let mut index = self.next_index.load(Ordering::Relaxed);
+        loop {
+            let mut start = index;
+            if slots > 0 && self.block_of(start) != self.block_of(start.checked_add(slots - 1)?) {
+                start = ((self.block_of(start) as u64 + 1) << self.block_bits)
+                    .try_into()
+                    .ok()?;
+            }
+            let next_index = start
+                .checked_add(slots)
+                .filter(|next_index| *next_index <= self.max_index)?;
+            match self.next_index.compare_exchange_weak(
+                index,
+                next_index,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    if start != index {
+                        self.stats
+                            .padding_bytes
+                            .fetch_add((start - index) as usize * S, Ordering::Relaxed);
+                    }
+                    self.allocate_block(self.block_of(start)).ok()?;
+                    return Some(start);
+                }
+                Err(current_index) => index = current_index,
+            }
+        }
+    }
+
+    fn block_of(&self, raw_index: u32) -> usize {
+        (raw_index as u64 >> self.block_bits) as usize
     }
 
     pub const fn supports_type<T>(&self) -> bool {
@@ -306,7 +421,14 @@ impl<const S: usize> Arena<S> {
     }
 
     unsafe fn ptr_to_index(&self, raw_index: u32) -> *const u8 {
-        self.data.add(raw_index as usize * S)
+        if !self.data.is_null() {
+            return self.data.add(raw_index as usize * S);
+        }
+        let offset = raw_index as u64 & ((1 << self.block_bits) - 1);
+        self.blocks
+            .get_unchecked(self.block_of(raw_index))
+            .load(Ordering::Relaxed)
+            .add(offset as usize * S)
     }
 
     const fn num_slots_required<T>() -> u32 {
@@ -322,8 +444,13 @@ impl<const S: usize> Arena<S> {
 
 impl<const S: usize> Drop for Arena<S> {
     fn drop(&mut self) {
-        unsafe {
-            alloc::dealloc(self.orig_pointer, self.layout);
+        for orig_pointer in self.orig_pointers.iter_mut() {
+            let orig_pointer = *orig_pointer.get_mut();
+            if !orig_pointer.is_null() {
+                unsafe {
+                    alloc::dealloc(orig_pointer, self.layout);
+                }
+            }
         }
     }
 }
@@ -7,9 +7,9 @@ use rand::Rng;
 use rand_distr::Distribution;
 
 use crate::evaluation::parameters::IncrementalPolicy;
-use crate::position::Move;
 /// This module contains the core of the MCTS search algorithm
 use crate::position::Position;
+use crate::position::{GroupData, Move};
 use crate::search::{cp_to_win_percentage, MctsSetting};
 
 use super::{arena, Arena, Error};
@@ -141,6 +141,7 @@ impl<const S: usize> TreeBridge<S> {
         best_child_node_index as usize
     }
 
+    /// `group_data` is the group data of `position`, if it has already been computed
     pub fn select(
         &mut self,
         position: &mut Position<S>,
@@ -148,6 +149,7 @@ impl<const S: usize> TreeBridge<S> {
         temp_vectors: &mut TempVectors<S>,
         arena: &Arena,
         our_visits: u32,
+        group_data: Option<&GroupData<S>>,
     ) -> Result<f32, Error> {
         assert_ne!(
             arena.get_slice(&self.children).len(),
@@ -179,8 +181,15 @@ impl<const S: usize> TreeBridge<S> {
 
         position.do_move(child_move);
 
-        let result =
-            1.0 - child_edge.select(position, settings, temp_vectors, arena, child_visits)?;
+        let result = 1.0
+            - child_edge.select(
+                position,
+                settings,
+                temp_vectors,
+                arena,
+                child_visits,
+                group_data.map(|group_data| (group_data, child_move)),
+            )?;
 
         *arena
             .get_slice_mut(&mut self.visitss)
@@ -204,12 +213,17 @@ impl<const S: usize> TreeBridge<S> {
     /// The noise is given `epsilon` weight.
     /// `alpha` is used to generate the noise, lower values generate more varied noise.
     /// Values above 1 are less noisy, and tend towards uniform outputs
-    pub fn apply_dirichlet(&mut self, arena: &Arena, epsilon: f32, alpha: f32) {
-        let mut rng = rand::thread_rng();
+    pub fn apply_dirichlet<R: Rng>(
+        &mut self,
+        arena: &Arena,
+        epsilon: f32,
+        alpha: f32,
+        rng: &mut R,
+    ) {
         let dirichlet =
             rand_distr::Dirichlet::new_with_size(alpha, arena.get_slice(&self.children).len())
                 .unwrap();
-        let noise_vec = dirichlet.sample(&mut rng);
+        let noise_vec = dirichlet.sample(rng);
         for (child_prior, eta) in arena
             .get_slice_mut(&mut self.heuristic_scores)
             .iter_mut()
@@ -221,6 +235,8 @@ impl<const S: usize> TreeBridge<S> {
 }
 
 impl<const S: usize> TreeEdge<S> {
+    /// `parent_group_data` is the group data from before the last move, and that move, if it is known.
+    /// It is used to update the group data incrementally in the rollout.
     pub fn select(
         &mut self,
         position: &mut Position<S>,
@@ -228,6 +244,7 @@ impl<const S: usize> TreeEdge<S> {
         temp_vectors: &mut TempVectors<S>,
         arena: &Arena,
         parent_visits: u32,
+        parent_group_data: Option<(&GroupData<S>, Move<S>)>,
     ) -> Result<f32, Error> {
         if let Some(child) = self.child.as_mut() {
             return arena.get_mut(child).select(
@@ -239,8 +256,13 @@ impl<const S: usize> TreeEdge<S> {
             );
         }
 
-        let (result, game_result) =
-            rollout(position, settings, settings.rollout_depth, temp_vectors);
+        let (result, game_result) = rollout(
+            position,
+            settings,
+            settings.rollout_depth,
+            temp_vectors,
+            parent_group_data,
+        );
         self.child = Some(
             arena
                 .add(Tree {
@@ -287,6 +309,7 @@ impl<const S: usize> Tree<S> {
             temp_vectors,
             arena,
             parent_visits,
+            None,
         )?;
         self.total_action_value += result as f64;
         Ok(result)
@@ -310,7 +333,7 @@ impl<const S: usize> Tree<S> {
         position.generate_moves_with_params(
             match settings.policy_params.as_ref() {
                 Some(params) => params,
-                None => <Position<S>>::policy_params(position.komi()),
+                None => <Position<S>>::policy_params(settings.eval_komi.unwrap_or(position.komi())),
             },
             &group_data,
             &mut temp_vectors.simple_moves,
@@ -353,7 +376,14 @@ impl<const S: usize> Tree<S> {
         temp_vectors.moves.clear();
 
         // Select child edge before writing the child node into the tree, in case we OOM inside this call
-        let result = tree_edge.select(position, settings, temp_vectors, arena, 1)?;
+        let result = tree_edge.select(
+            position,
+            settings,
+            temp_vectors,
+            arena,
+            1,
+            Some(&group_data),
+        )?;
 
         self.children = Some(arena.add(tree_edge).ok_or(Error::OOM)?);
 
@@ -362,19 +392,40 @@ impl<const S: usize> Tree<S> {
 }
 
 pub struct Pv<'a, const S: usize> {
-    arena: &'a Arena,
-    edge: &'a TreeEdge<S>,
+    pv_detailed: PvDetailed<'a, S>,
 }
 
 impl<'a, const S: usize> Pv<'a, S> {
     pub fn new(edge: &'a TreeEdge<S>, arena: &'a Arena) -> Pv<'a, S> {
-        Pv { edge, arena }
+        Pv {
+            pv_detailed: PvDetailed::new(edge, arena),
+        }
     }
 }
 
 impl<'a, const S: usize> Iterator for Pv<'a, S> {
     type Item = Move<S>;
 
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pv_detailed.next().map(|(mv, _, _)| mv)
+    }
+}
+
+/// The principal variation, with the visit count and policy prior of each move
+pub struct PvDetailed<'a, const S: usize> {
+    arena: &'a Arena,
+    edge: &'a TreeEdge<S>,
+}
+
+impl<'a, const S: usize> PvDetailed<'a, S> {
+    pub fn new(edge: &'a TreeEdge<S>, arena: &'a Arena) -> PvDetailed<'a, S> {
+        PvDetailed { edge, arena }
+    }
+}
+
+impl<'a, const S: usize> Iterator for PvDetailed<'a, S> {
+    type Item = (Move<S>, u32, f32);
+
     fn next(&mut self) -> Option<Self::Item> {
         self.edge
             .child
@@ -385,20 +436,22 @@ impl<'a, const S: usize> Iterator for Pv<'a, S> {
             })
             .and_then(|index| {
                 let bridge = self.arena.get(index);
-                let (_, (mv, child)) = self
+                let (visits, (mv, (child, heuristic_score))) = self
                     .arena
                     .get_slice(&bridge.visitss)
                     .iter()
                     .zip(
-                        self.arena
-                            .get_slice(&bridge.moves)
-                            .iter()
-                            .zip(self.arena.get_slice(&bridge.children)),
+                        self.arena.get_slice(&bridge.moves).iter().zip(
+                            self.arena
+                                .get_slice(&bridge.children)
+                                .iter()
+                                .zip(self.arena.get_slice(&bridge.heuristic_scores)),
+                        ),
                     )
                     .filter(|(_, (mv, _))| mv.is_some())
                     .max_by_key(|(visits, _)| **visits)?;
                 self.edge = child;
-                *mv
+                Some(((*mv)?, *visits, heuristic_score.to_f32()))
             })
     }
 }
@@ -406,17 +459,32 @@ impl<'a, const S: usize> Iterator for Pv<'a, S> {
 /// Do a mcts rollout up to `depth` plies, before doing a static evaluation.
 /// Depth is 0 on default settings, in which case it immediately does a static evaluation
 /// Higher depths are mainly used for playing with reduced difficulty
+/// If `parent_group_data` is given, the group data is updated from it instead of being computed from scratch
 // Never inline, for profiling purposes
 #[inline(never)]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(depth = depth))
+)]
 pub fn rollout<const S: usize>(
     position: &mut Position<S>,
     settings: &MctsSetting<S>,
     depth: u16,
     temp_vectors: &mut TempVectors<S>,
+    parent_group_data: Option<(&GroupData<S>, Move<S>)>,
 ) -> (f32, Option<GameResultForUs>) {
-    let group_data = position.group_data();
+    let group_data = match parent_group_data {
+        Some((parent_group_data, mv)) => position.group_data_after_move(parent_group_data, mv),
+        None => position.group_data(),
+    };
+
+    let game_result = if settings.ignore_repetition_draws {
+        position.game_result_with_group_data_ignoring_repetitions(&group_data)
+    } else {
+        position.game_result_with_group_data(&group_data)
+    };
 
-    if let Some(game_result) = position.game_result_with_group_data(&group_data) {
+    if let Some(game_result) = game_result {
         let game_result_for_us = match (game_result, position.side_to_move()) {
             (GameResult::Draw, _) => GameResultForUs::Draw,
             (GameResult::WhiteWin, Color::Black) => GameResultForUs::Loss, // The side to move has lost
@@ -431,7 +499,7 @@ pub fn rollout<const S: usize>(
             &group_data,
             match settings.value_params.as_ref() {
                 Some(params) => params,
-                None => <Position<S>>::value_params(position.komi()),
+                None => <Position<S>>::value_params(settings.eval_komi.unwrap_or(position.komi())),
             },
         );
         let static_eval = if let Some(static_eval_variance) = settings.static_eval_variance {
@@ -454,7 +522,7 @@ pub fn rollout<const S: usize>(
             &mut temp_vectors.fcd_per_move,
             match settings.policy_params.as_ref() {
                 Some(params) => params,
-                None => <Position<S>>::policy_params(position.komi()),
+                None => <Position<S>>::policy_params(settings.eval_komi.unwrap_or(position.komi())),
             },
             &mut temp_vectors.policy_feature_sets,
         );
@@ -465,7 +533,13 @@ pub fn rollout<const S: usize>(
         position.do_move(best_move);
 
         temp_vectors.moves.clear();
-        let (score, _) = rollout(position, settings, depth - 1, temp_vectors);
+        let (score, _) = rollout(
+            position,
+            settings,
+            depth - 1,
+            temp_vectors,
+            Some((&group_data, best_move)),
+        );
         (1.0 - score, None)
     }
 }
@@ -2,21 +2,28 @@
 //!
 //! This implementation does not use full Monte Carlo rollouts, relying on a heuristic evaluation when expanding new nodes instead.
 
+use board_game_traits::{GameResult, Position as PositionTrait};
 use half::f16;
 #[cfg(feature = "serde")]
+use pgn_traits::PgnPosition;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 use std::fmt::Display;
-use std::{mem, time};
-use std::{process, sync};
+use std::{iter, mem, time};
+use std::{process, sync, thread};
 
+use crate::position::Komi;
 use crate::position::Move;
 use crate::position::Position;
+use crate::position::Role;
 pub use crate::search::mcts_core::best_move;
 use crate::search::mcts_core::{TempVectors, Tree, TreeEdge};
 
 use self::arena::ArenaError;
-use self::mcts_core::Pv;
+use self::mcts_core::{Pv, PvDetailed};
 
 mod arena;
 /// This module contains the public-facing convenience API for the search.
@@ -34,28 +41,42 @@ pub enum TimeControl {
 #[derive(Clone, PartialEq, Debug)]
 pub struct MctsSetting<const S: usize> {
     arena_size: u32,
+    growable_arena: bool,
     value_params: Option<&'static [f32]>,
     policy_params: Option<&'static [f32]>,
     search_params: Box<[f32]>,
     dirichlet: Option<f32>,
+    dirichlet_seed: Option<u64>,
     excluded_moves: Vec<Move<S>>,
     static_eval_variance: Option<f32>,
     rollout_depth: u16,
     rollout_temperature: Option<f64>,
+    ignore_repetition_draws: bool,
+    komi: Option<Komi>,
+    eval_komi: Option<Komi>,
+    initial_batch_size: u64,
+    batch_growth_factor: f32,
 }
 
 impl<const S: usize> Default for MctsSetting<S> {
     fn default() -> Self {
         MctsSetting {
             arena_size: 3 * 2_u32.pow(30), // Default to 48GB max
+            growable_arena: false,
             value_params: None,
             policy_params: None,
             search_params: vec![1.50, 2200.0, 0.61].into_boxed_slice(),
             dirichlet: None,
+            dirichlet_seed: None,
             excluded_moves: vec![],
             static_eval_variance: None,
             rollout_depth: 0,
             rollout_temperature: None,
+            ignore_repetition_draws: false,
+            komi: None,
+            eval_komi: None,
+            initial_batch_size: 50,
+            batch_growth_factor: 2.0_f32.powf(0.125),
         }
     }
 }
@@ -81,12 +102,38 @@ impl<const S: usize> MctsSetting<S> {
         self.arena_size((mem_usage / ARENA_ELEMENT_SIZE) as u32)
     }
 
+    /// Cap the memory used by the search tree at `bytes`. If the arena is exhausted, `select` returns `Error::OOM`.
+    ///
+    /// The number of nodes a given budget can hold depends on the board size and the position, since every expanded node
+    /// stores all its child moves. `arena_size_for_nodes` budgets a liberal `48 * S * S` bytes per node,
+    /// so for example 512MB is enough for at least 300k nodes on 6s, and usually several times more.
+    ///
+    /// Unlike `mem_usage`, this never panics. Budgets larger than the arena can address are clamped to the largest possible arena.
+    pub fn arena_size_bytes(self, bytes: usize) -> Self {
+        let max_bytes = usize::min(
+            isize::MAX as usize - 2 * ARENA_ELEMENT_SIZE,
+            (u32::MAX as usize - 2).saturating_mul(ARENA_ELEMENT_SIZE),
+        );
+        self.arena_size((bytes.min(max_bytes) / ARENA_ELEMENT_SIZE) as u32)
+    }
+
     pub fn arena_size(mut self, arena_size: u32) -> Self {
         assert!(arena_size < u32::MAX - 1);
         self.arena_size = arena_size;
         self
     }
 
+    /// Allocate the search tree's memory in blocks as the search progresses, instead of all at once.
+    /// The arena size then becomes an upper limit, rather than a fixed allocation.
+    ///
+    /// This is useful for long searches, where the required memory isn't known in advance.
+    /// The downside is slightly slower tree access, and that running out of system memory
+    /// is only detected during search, not when the search is started.
+    pub fn growable_arena(mut self) -> Self {
+        self.growable_arena = true;
+        self
+    }
+
     pub fn add_value_params(mut self, value_params: &'static [f32]) -> Self {
         self.value_params = Some(value_params);
         self
@@ -102,8 +149,19 @@ impl<const S: usize> MctsSetting<S> {
         self
     }
 
+    /// Add Dirichlet noise to the root's policy scores. The noise is drawn from entropy,
+    /// so the search is no longer deterministic.
     pub fn add_dirichlet(mut self, alpha: f32) -> Self {
         self.dirichlet = Some(alpha);
+        self.dirichlet_seed = None;
+        self
+    }
+
+    /// Add Dirichlet noise to the root's policy scores, drawn from an rng seeded with `seed`.
+    /// Searches with the same seed and settings apply identical noise.
+    pub fn add_dirichlet_seeded(mut self, alpha: f32, seed: u64) -> Self {
+        self.dirichlet = Some(alpha);
+        self.dirichlet_seed = Some(seed);
         self
     }
 
@@ -130,6 +188,102 @@ impl<const S: usize> MctsSetting<S> {
         self
     }
 
+    /// Do not treat repeated positions as draws during search.
+    /// Useful for analysing whether a position is theoretically won, regardless of the repetition rule.
+    ///
+    /// This only applies to terminal nodes inside the search tree. `Position::game_result` still reports repetition draws.
+    /// Note that the engine may then "see" wins in lines where the opponent could force a draw by repetition.
+    pub fn ignore_repetition_draws(mut self, ignore_repetition_draws: bool) -> Self {
+        self.ignore_repetition_draws = ignore_repetition_draws;
+        self
+    }
+
+    /// Search with this komi instead of the position's komi.
+    /// It is used both to decide the result of terminal positions, and to select the default evaluation parameters.
+    pub fn komi(mut self, komi: Komi) -> Self {
+        self.komi = Some(komi);
+        self
+    }
+
+    /// Select the default evaluation parameters for this komi, instead of for the komi the game is played with.
+    /// Useful for komis that do not have parameters of their own. Has no effect if the parameters are set explicitly.
+    pub fn eval_komi(mut self, eval_komi: Komi) -> Self {
+        self.eval_komi = Some(eval_komi);
+        self
+    }
+
+    /// The number of nodes `search_for_time` searches between each time check and callback.
+    /// The first batch has `initial_batch_size` nodes, and each batch is `growth_factor` times larger than the previous one.
+    /// The defaults are 50 nodes and a factor of 2^(1/8), which doubles the batch size every 8 batches.
+    ///
+    /// Time is only checked between batches, so large batches may overshoot the 10ms safety buffer at the end of the search.
+    /// Time management compares the elapsed time ratio to the visit ratio of the two best moves, which is not affected by the batch size,
+    /// but small batches let it stop the search closer to the point where the best move is decided.
+    /// Small batches also give more frequent callbacks, at the cost of more overhead.
+    pub fn search_batch_size(mut self, initial_batch_size: u64, growth_factor: f32) -> Self {
+        assert!(initial_batch_size > 0);
+        assert!(growth_factor >= 1.0);
+        self.initial_batch_size = initial_batch_size;
+        self.batch_growth_factor = growth_factor;
+        self
+    }
+
+    fn batch_size(&self, batch: i32) -> u64 {
+        (self.initial_batch_size as f32 * self.batch_growth_factor.powi(batch)) as u64
+    }
+
+    /// A stable hash of the evaluation parameters and every search setting, along with the crate version.
+    /// Two searches with the same fingerprint use identical parameters and settings.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = FingerprintHasher::default();
+        // Only some sizes have default parameters
+        let has_default_params = matches!(S, 4..=6);
+        match self.value_params {
+            Some(value_params) => hasher.write_f32s(value_params),
+            None if has_default_params => {
+                hasher.write_f32s(<Position<S>>::value_params_0komi());
+                hasher.write_f32s(<Position<S>>::value_params_2komi());
+            }
+            None => (),
+        }
+        match self.policy_params {
+            Some(policy_params) => hasher.write_f32s(policy_params),
+            None if has_default_params => {
+                hasher.write_f32s(<Position<S>>::policy_params_0komi());
+                hasher.write_f32s(<Position<S>>::policy_params_2komi());
+            }
+            None => (),
+        }
+        hasher.write_f32s(&self.search_params);
+        hasher.write_f32s(&[
+            self.dirichlet.unwrap_or(f32::NAN),
+            self.static_eval_variance.unwrap_or(f32::NAN),
+            self.rollout_temperature.unwrap_or(f64::NAN) as f32,
+            self.rollout_depth as f32,
+            self.ignore_repetition_draws as u8 as f32,
+            self.komi.map_or(f32::NAN, |komi| komi.half_komi() as f32),
+            self.eval_komi
+                .map_or(f32::NAN, |komi| komi.half_komi() as f32),
+            self.batch_growth_factor,
+        ]);
+        hasher.write_u64(self.arena_size as u64);
+        hasher.write_u64(self.growable_arena as u64);
+        hasher.write_u64(self.initial_batch_size);
+        match self.dirichlet_seed {
+            Some(seed) => {
+                hasher.write_u64(1);
+                hasher.write_u64(seed);
+            }
+            None => hasher.write_u64(0),
+        }
+        hasher.write_u64(self.excluded_moves.len() as u64);
+        for mv in self.excluded_moves.iter() {
+            hasher.write_bytes(mv.to_string().as_bytes());
+            hasher.write_bytes(&[0]);
+        }
+        hasher.fingerprint()
+    }
+
     pub fn c_puct_init(&self) -> f32 {
         self.search_params[0]
     }
@@ -143,9 +297,65 @@ impl<const S: usize> MctsSetting<S> {
     }
 }
 
+/// A stable hash of the default evaluation parameters for all supported sizes,
+/// the default search settings, and the crate version.
+pub fn engine_fingerprint() -> String {
+    let mut hasher = FingerprintHasher::default();
+    for fingerprint in [
+        MctsSetting::<4>::default().fingerprint(),
+        MctsSetting::<5>::default().fingerprint(),
+        MctsSetting::<6>::default().fingerprint(),
+    ] {
+        hasher.write_bytes(fingerprint.as_bytes());
+    }
+    hasher.fingerprint()
+}
+
+/// 64-bit FNV-1a, which unlike `std`'s hashers is guaranteed to be stable between releases
+struct FingerprintHasher {
+    hash: u64,
+}
+
+impl Default for FingerprintHasher {
+    fn default() -> Self {
+        FingerprintHasher {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl FingerprintHasher {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_f32s(&mut self, values: &[f32]) {
+        for value in values {
+            self.write_bytes(&value.to_bits().to_le_bytes());
+        }
+    }
+
+    fn fingerprint(&self) -> String {
+        format!("tiltak-{}-{:016x}", env!("CARGO_PKG_VERSION"), self.hash)
+    }
+}
+
 /// Type alias for winning probability, used for scoring positions.
 pub const ARENA_ELEMENT_SIZE: usize = 16;
 
+/// Number of slots allocated at a time by a growable arena, i.e. 64MiB blocks
+const GROWABLE_ARENA_BLOCK_SLOTS: u32 = 1 << 22;
+
+/// Dirichlet noise used to diversify the trees in `mcts_parallel`
+const ROOT_PARALLEL_DIRICHLET_ALPHA: f32 = 0.2;
+
 #[derive(Debug)]
 pub enum Error {
     OOM,
@@ -178,8 +388,16 @@ pub struct MonteCarloTree<const S: usize> {
 }
 
 impl<const S: usize> MonteCarloTree<S> {
-    pub fn new(position: Position<S>, settings: MctsSetting<S>) -> MonteCarloTree<S> {
-        let arena = match Arena::new(settings.arena_size) {
+    pub fn new(mut position: Position<S>, settings: MctsSetting<S>) -> MonteCarloTree<S> {
+        if let Some(komi) = settings.komi {
+            position.set_komi(komi);
+        }
+        let arena = if settings.growable_arena {
+            Arena::new_growable(settings.arena_size, GROWABLE_ARENA_BLOCK_SLOTS)
+        } else {
+            Arena::new(settings.arena_size)
+        };
+        let arena = match arena {
             Ok(arena) => arena,
             Err(ArenaError::AllocationFailed(num_bytes)) if !sysinfo::IS_SUPPORTED_SYSTEM => {
                 panic!(
@@ -233,6 +451,7 @@ impl<const S: usize> MonteCarloTree<S> {
             &mut temp_vectors,
             &arena,
             0,
+            None,
         )
         .unwrap();
         tree.select(
@@ -241,18 +460,23 @@ impl<const S: usize> MonteCarloTree<S> {
             &mut temp_vectors,
             &arena,
             1,
+            None,
         )
         .unwrap();
 
         if let Some(alpha) = settings.dirichlet {
-            arena
-                .get_mut(
-                    (arena.get_mut(tree.child.as_mut().unwrap()))
-                        .children
-                        .as_mut()
-                        .unwrap(),
-                )
-                .apply_dirichlet(&arena, 0.25, alpha);
+            let bridge = arena.get_mut(
+                (arena.get_mut(tree.child.as_mut().unwrap()))
+                    .children
+                    .as_mut()
+                    .unwrap(),
+            );
+            match settings.dirichlet_seed {
+                Some(seed) => {
+                    bridge.apply_dirichlet(&arena, 0.25, alpha, &mut StdRng::seed_from_u64(seed))
+                }
+                None => bridge.apply_dirichlet(&arena, 0.25, alpha, &mut rand::thread_rng()),
+            }
         }
 
         if !settings.excluded_moves.is_empty() {
@@ -289,19 +513,70 @@ impl<const S: usize> MonteCarloTree<S> {
         }
     }
 
-    pub fn search_for_time<F>(&mut self, max_time: time::Duration, callback: F)
+    /// Advance the root by a single move, keeping the subtree that was already searched below it.
+    /// The visit count of the reused subtree is available from `visits()` on the returned tree,
+    /// so that the caller can decide whether it's worth keeping, or if it's better to start fresh.
+    ///
+    /// Returns `None` if the move has not been expanded as a child of the root, including if it is illegal.
+    /// Dirichlet noise and excluded moves are not re-applied to the new root.
+    /// Memory used by the rest of the tree is not reclaimed.
+    pub fn reroot_one(mut self, mv: Move<S>) -> Option<Self> {
+        let root = self.arena.get_mut(self.tree.child.as_mut()?);
+        let bridge = self.arena.get_mut(root.children.as_mut()?);
+        let index = self
+            .arena
+            .get_slice(&bridge.moves)
+            .iter()
+            .position(|child_move| *child_move == Some(mv))?;
+        let visits = self.arena.get_slice(&bridge.visitss)[index];
+        let child = self.arena.get_slice_mut(&mut bridge.children)[index]
+            .child
+            .take()?;
+
+        self.position.do_move(mv);
+        self.temp_position.clone_from(&self.position);
+
+        Some(MonteCarloTree {
+            tree: TreeEdge { child: Some(child) },
+            visits,
+            ..self
+        })
+    }
+
+    /// Search for up to `max_time`, calling `callback` regularly. Returns how the search was stopped.
+    ///
+    /// The search is stopped early if one move dominates the others, based on
+    /// the ratio of visits between the two best moves compared to the ratio of time spent.
+    pub fn search_for_time<F>(
+        &mut self,
+        max_time: time::Duration,
+        callback: F,
+    ) -> TimeManagementReport
     where
         F: Fn(&Self),
     {
         let start_time = time::Instant::now();
+        let mut node_ratio = None;
+        let report = |tree: &Self, stop_reason: StopReason, node_ratio: Option<f32>| {
+            let elapsed = start_time.elapsed();
+            TimeManagementReport {
+                stop_reason,
+                elapsed,
+                visits: tree.visits,
+                node_ratio,
+                time_ratio: elapsed.as_secs_f32() / max_time.as_secs_f32(),
+            }
+        };
 
         for i in 0.. {
-            let nodes = (50.0 * 2.0_f32.powf(0.125).powi(i)) as u64;
+            let nodes = self.settings.batch_size(i);
             for _ in 0..nodes {
                 if let Err(err) = self.select() {
-                    eprintln!("Warning: {err}");
+                    self.warn_search_stopped(&err);
+                    #[cfg(feature = "tracing")]
+                    self.trace_search_finished(StopReason::Error);
                     callback(self);
-                    return;
+                    return report(self, StopReason::Error, node_ratio);
                 };
             }
 
@@ -312,17 +587,33 @@ impl<const S: usize> MonteCarloTree<S> {
                 || start_time.elapsed() > max_time - (time::Duration::from_millis(10))
                 || shallow_edges.len() == 1
             {
+                let stop_reason = if shallow_edges.len() == 1 {
+                    StopReason::ForcedMove
+                } else {
+                    StopReason::OutOfTime
+                };
+                #[cfg(feature = "tracing")]
+                self.trace_search_finished(stop_reason);
                 callback(self);
-                return;
+                return report(self, stop_reason, node_ratio);
             }
 
             shallow_edges.sort_by_key(|edge| edge.visits);
             shallow_edges.reverse();
 
-            let node_ratio =
+            let current_node_ratio =
                 (1 + shallow_edges[1].visits) as f32 / (1 + shallow_edges[0].visits) as f32;
+            node_ratio = Some(current_node_ratio);
             let time_ratio = start_time.elapsed().as_secs_f32() / max_time.as_secs_f32();
 
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                visits = self.visits,
+                node_ratio = current_node_ratio,
+                time_ratio,
+                "Time management check"
+            );
+
             let visits_sqrt = (self.visits() as f32).sqrt();
             let dynamic_cpuct = self.settings.c_puct_init()
                 + f32::ln(
@@ -334,7 +625,7 @@ impl<const S: usize> MonteCarloTree<S> {
 
             let best_exploration_value = best_edge.exploration_value(visits_sqrt, dynamic_cpuct);
 
-            if time_ratio.powf(2.0) > node_ratio / 2.0 {
+            if time_ratio.powf(2.0) > current_node_ratio / 2.0 {
                 callback(self);
                 // Do not stop if any other child nodes have better exploration value
                 if shallow_edges.iter().any(|edge| {
@@ -344,11 +635,85 @@ impl<const S: usize> MonteCarloTree<S> {
                 }) {
                     continue;
                 }
-                return;
+                #[cfg(feature = "tracing")]
+                self.trace_search_finished(StopReason::DominantMove);
+                return report(self, StopReason::DominantMove, node_ratio);
             } else if i % 2 == 0 {
                 callback(self);
             }
         }
+        unreachable!()
+    }
+
+    /// Search for up to `max_time`, yielding the best move, its score and the total number of visits after each batch of nodes.
+    /// The batches grow geometrically, like in `search_for_time`, but the search never stops early from time management.
+    /// It stops when the time is up, when there is only one legal move, or when the search fails.
+    /// The last item is the final result of the search.
+    pub fn analysis_stream(&mut self, max_time: time::Duration) -> AnalysisStream<'_, S> {
+        AnalysisStream {
+            tree: self,
+            start_time: time::Instant::now(),
+            max_time,
+            iteration: 0,
+            finished: false,
+        }
+    }
+
+    /// Search until the best move is decided with the given confidence, or until the tree has `max_nodes` visits.
+    /// Returns whether the confidence was reached.
+    ///
+    /// The confidence is measured like in `search_for_time`'s time management:
+    /// as one minus the ratio of the second most visited move's visits to the most visited move's visits.
+    /// For example, a confidence of 0.9 stops the search when no other move has more than 10% of the best move's visits.
+    /// The check is done between batches of nodes, as set by `MctsSetting::search_batch_size`,
+    /// so the search may run up to one batch past `max_nodes`. A position with only one legal move is always decided.
+    pub fn search_until_confident(&mut self, confidence: f32, max_nodes: u32) -> bool {
+        for i in 0.. {
+            if self.visits >= max_nodes {
+                return false;
+            }
+            let nodes = self.settings.batch_size(i);
+            match self.select_n(nodes) {
+                Ok(completed) if completed == nodes => (),
+                _ => return false,
+            }
+
+            let mut shallow_edges = self.shallow_edges().unwrap();
+            if shallow_edges.len() == 1 {
+                return true;
+            }
+            shallow_edges.sort_by_key(|edge| edge.visits);
+            shallow_edges.reverse();
+
+            let node_ratio =
+                (1 + shallow_edges[1].visits) as f32 / (1 + shallow_edges[0].visits) as f32;
+            if 1.0 - node_ratio >= confidence {
+                return true;
+            }
+        }
+        unreachable!()
+    }
+
+    /// Emit the final move choice as a structured event, along with the reason the search stopped
+    #[cfg(feature = "tracing")]
+    fn trace_search_finished(&self, reason: StopReason) {
+        if let Some((mv, score)) = self.best_move() {
+            tracing::info!(
+                %mv,
+                score,
+                visits = self.visits,
+                ?reason,
+                "Search finished"
+            );
+        }
+    }
+
+    /// Report an error that stopped the search early, as a structured event if tracing is enabled
+    fn warn_search_stopped(&self, err: &Error) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = %err, visits = self.visits, "Search stopped early");
+        #[cfg(not(feature = "tracing"))]
+        eprintln!("Warning: {err}");
     }
 
     // TODO: Count up to u64 on root?
@@ -380,12 +745,109 @@ impl<const S: usize> MonteCarloTree<S> {
         Pv::new(&self.tree, &self.arena)
     }
 
+    /// The same principal variation as `pv`, with the visit count and policy prior of each move.
+    /// Useful for seeing where the search deviates from the policy's preferred move.
+    pub fn pv_detailed(&self) -> impl Iterator<Item = (Move<S>, u32, f32)> + '_ {
+        PvDetailed::new(&self.tree, &self.arena)
+    }
+
+    /// Statistics for every legal move at the root, sorted with the most visited moves first.
+    /// If several moves are tied for the most visits, the first one is the same as `best_move`.
+    pub fn root_move_stats(&self) -> Vec<MoveStat<S>> {
+        let Some(shallow_edges) = self.shallow_edges() else {
+            return vec![];
+        };
+        // `best_move` picks the last of the tied moves, so reverse them before the stable sort
+        let mut move_stats: Vec<MoveStat<S>> = shallow_edges
+            .iter()
+            .rev()
+            .map(|edge| MoveStat {
+                mv: edge.mv,
+                visits: edge.visits,
+                mean_action_value: 1.0 - edge.mean_action_value,
+                policy: edge.policy.to_f32(),
+                pv: iter::once(edge.mv)
+                    .chain(Pv::new(edge.child, &self.arena))
+                    .collect(),
+            })
+            .collect();
+        move_stats.sort_by_key(|move_stat| u32::MAX - move_stat.visits);
+        move_stats
+    }
+
+    /// The path the next call to `select` would take through the tree, as
+    /// `(move, mean action value, policy, exploration value)` for each node on the path.
+    /// The mean action value is from the perspective of the side making the move.
+    ///
+    /// This is a dry run: It does not expand any nodes or update any statistics.
+    pub fn explain_selection(&self) -> Vec<(Move<S>, f32, f32, f32)> {
+        let mut path = vec![];
+        let mut edge = &self.tree;
+        let mut our_visits = self.visits;
+
+        while let Some(node) = edge.child.as_ref().map(|index| self.arena.get(index)) {
+            if node.game_result.is_some() {
+                break;
+            }
+            let Some(bridge) = node.children.as_ref().map(|index| self.arena.get(index)) else {
+                break;
+            };
+            let visits_sqrt = (our_visits as f32).sqrt();
+            let dynamic_cpuct = self.settings.c_puct_init()
+                + f32::ln(
+                    (1.0 + our_visits as f32 + self.settings.c_puct_base())
+                        / self.settings.c_puct_base(),
+                );
+
+            let Some((mv, mean_action_value, policy, child_visits, child_edge, exploration_value)) =
+                self.arena
+                    .get_slice(&bridge.moves)
+                    .iter()
+                    .zip(self.arena.get_slice(&bridge.mean_action_values))
+                    .zip(self.arena.get_slice(&bridge.heuristic_scores))
+                    .zip(self.arena.get_slice(&bridge.visitss))
+                    .zip(self.arena.get_slice(&bridge.children))
+                    .filter_map(|((((mv, mean_action_value), policy), visits), child)| {
+                        let exploration_value = mcts_core::exploration_value(
+                            *mean_action_value,
+                            policy.to_f32(),
+                            *visits,
+                            visits_sqrt,
+                            dynamic_cpuct,
+                        );
+                        Some((
+                            (*mv)?,
+                            *mean_action_value,
+                            policy.to_f32(),
+                            *visits,
+                            child,
+                            exploration_value,
+                        ))
+                    })
+                    .max_by(|a, b| a.5.partial_cmp(&b.5).unwrap())
+            else {
+                break;
+            };
+
+            path.push((mv, 1.0 - mean_action_value, policy, exploration_value));
+            edge = child_edge;
+            our_visits = child_visits;
+        }
+        path
+    }
+
+    /// A snapshot of the search's progress, for displaying elsewhere than stdout
+    pub fn info(&self) -> SearchInfo<S> {
+        SearchInfo {
+            visits: self.visits,
+            mean_action_value: self.mean_action_value(),
+            moves: self.root_move_stats(),
+        }
+    }
+
     /// Print human-readable information of the search's progress.
     pub fn print_info(&self) {
-        let mut best_children: Vec<ShallowEdge<S>> = self.shallow_edges().unwrap_or_default();
-
-        best_children.sort_by_key(|edge| edge.visits);
-        best_children.reverse();
+        let info = self.info();
 
         use sync::atomic::Ordering::*;
         println!(
@@ -402,15 +864,24 @@ impl<const S: usize> MonteCarloTree<S> {
                     / self.settings.c_puct_base(),
             );
 
-        best_children.iter().take(8).for_each(|edge| {
+        info.moves.iter().take(8).for_each(|move_stat| {
+            let mean_action_value = 1.0 - move_stat.mean_action_value;
+            let exploration_value = mcts_core::exploration_value(
+                mean_action_value,
+                move_stat.policy,
+                move_stat.visits,
+                (info.visits as f32).sqrt(),
+                dynamic_cpuct,
+            );
             println!(
                 "Move {}: {} visits, {:.2}% mean action value, {:.3}% static score, {:.3} exploration value, pv {}",
-                edge.mv,
-                edge.visits,
-                edge.mean_action_value * 100.0,
-                edge.policy.to_f32() * 100.0,
-                1.0 + edge.exploration_value((self.visits() as f32).sqrt(), dynamic_cpuct), // The +1.0 doesn't matter, but positive numbers are easier to read
-                Pv::new(edge.child, &self.arena)
+                move_stat.mv,
+                move_stat.visits,
+                mean_action_value * 100.0,
+                move_stat.policy * 100.0,
+                1.0 + exploration_value, // The +1.0 doesn't matter, but positive numbers are easier to read
+                move_stat.pv[1..]
+                    .iter()
                     .map(|mv| mv.to_string())
                     .collect::<Vec<_>>()
                     .join(" ")
@@ -418,6 +889,29 @@ impl<const S: usize> MonteCarloTree<S> {
         });
     }
 
+    /// Run up to `n` iterations of `select`, returning the number of iterations that completed.
+    /// Running out of memory or reaching the maximum visit count stops the search early without an error,
+    /// which callers can detect from the count being smaller than `n`. A warning is logged in that case.
+    ///
+    /// Because both search errors stop the search early, this currently never returns `Err`.
+    /// The `Result` leaves room for future errors that should not be turned into a short search.
+    pub fn select_n(&mut self, n: u64) -> Result<u64, Error> {
+        for i in 0..n {
+            match self.select() {
+                Ok(_) => (),
+                Err(err @ (Error::OOM | Error::MaxVisits)) => {
+                    self.warn_search_stopped(&err);
+                    return Ok(i);
+                }
+            }
+        }
+        Ok(n)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(visits = self.visits))
+    )]
     pub fn select(&mut self) -> Result<f32, Error> {
         if self.visits == u32::MAX {
             return Err(Error::MaxVisits);
@@ -429,6 +923,7 @@ impl<const S: usize> MonteCarloTree<S> {
             &mut self.temp_vectors,
             &self.arena,
             self.visits,
+            None,
         )?;
         self.visits += 1;
         Ok(result)
@@ -469,6 +964,86 @@ impl<const S: usize> MonteCarloTree<S> {
         )
     }
 }
+
+/// A snapshot of the search's progress, see `MonteCarloTree::info`
+#[derive(Clone, PartialEq, Debug)]
+pub struct SearchInfo<const S: usize> {
+    pub visits: u32,
+    /// The root's mean action value, from the perspective of the side to move
+    pub mean_action_value: f32,
+    /// Statistics for every legal move at the root, sorted with the most visited moves first
+    pub moves: Vec<MoveStat<S>>,
+}
+
+/// Search statistics for a single move at the root, see `MonteCarloTree::root_move_stats`
+///
+/// With the `serde` feature, moves are serialized in PTN, see `analysis_to_json`.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct MoveStat<const S: usize> {
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "move", serialize_with = "serialize_ptn_move")
+    )]
+    pub mv: Move<S>,
+    pub visits: u32,
+    /// The mean action value of the move, from the perspective of the side making it.
+    /// This is the winning probability for the side to move at the root, like `SearchInfo::mean_action_value`
+    #[cfg_attr(feature = "serde", serde(rename = "eval"))]
+    pub mean_action_value: f32,
+    pub policy: f32,
+    /// The principal variation, starting with the move itself
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_ptn_moves"))]
+    pub pv: Vec<Move<S>>,
+}
+
+#[cfg(feature = "serde")]
+fn serialize_ptn_move<const S: usize, Ser: serde::Serializer>(
+    mv: &Move<S>,
+    serializer: Ser,
+) -> Result<Ser::Ok, Ser::Error> {
+    serializer.collect_str(mv)
+}
+
+#[cfg(feature = "serde")]
+fn serialize_ptn_moves<const S: usize, Ser: serde::Serializer>(
+    moves: &[Move<S>],
+    serializer: Ser,
+) -> Result<Ser::Ok, Ser::Error> {
+    serializer.collect_seq(moves.iter().map(|mv| mv.to_string()))
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+struct Analysis<'a, const S: usize> {
+    tps: String,
+    moves: &'a [MoveStat<S>],
+}
+
+/// Serialize search results as JSON, for use in analysis tools.
+///
+/// The output is an object with the position's TPS, and an array of moves in the order given:
+/// ```json
+/// {
+///   "tps": "x5/x5/x5/x5/x5 1 1",
+///   "moves": [
+///     { "move": "c3", "visits": 1000, "eval": 0.52, "policy": 0.05, "pv": ["c3", "a5"] }
+///   ]
+/// }
+/// ```
+/// Moves are written in PTN, and `eval` is the winning probability for the side to move in `position`.
+#[cfg(feature = "serde")]
+pub fn analysis_to_json<const S: usize>(
+    position: &Position<S>,
+    move_stats: &[MoveStat<S>],
+) -> String {
+    let analysis = Analysis {
+        tps: position.to_fen(),
+        moves: move_stats,
+    };
+    serde_json::to_string(&analysis).expect("Analysis can always be serialized")
+}
+
 // More convenient edge representation, allowing them to be stored as array-of-structs rather than struct-of-arrays
 pub struct ShallowEdge<'a, const S: usize> {
     visits: u32,
@@ -490,18 +1065,181 @@ impl<'a, const S: usize> ShallowEdge<'a, S> {
     }
 }
 
+/// Pull-based search iterator, created by `MonteCarloTree::analysis_stream`
+pub struct AnalysisStream<'a, const S: usize> {
+    tree: &'a mut MonteCarloTree<S>,
+    start_time: time::Instant,
+    max_time: time::Duration,
+    iteration: i32,
+    finished: bool,
+}
+
+impl<const S: usize> Iterator for AnalysisStream<'_, S> {
+    type Item = (Move<S>, f32, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let nodes = self.tree.settings.batch_size(self.iteration);
+        self.iteration += 1;
+        for _ in 0..nodes {
+            if let Err(err) = self.tree.select() {
+                self.tree.warn_search_stopped(&err);
+                self.finished = true;
+                break;
+            }
+        }
+
+        // Always stop when we have less than 10ms left
+        if self.start_time.elapsed() + time::Duration::from_millis(10) > self.max_time
+            || self.tree.shallow_edges()?.len() == 1
+        {
+            self.finished = true;
+        }
+        let (mv, score) = self.tree.best_move()?;
+        Some((mv, score, self.tree.visits()))
+    }
+}
+
 /// The simplest way to use the mcts module. Run Monte Carlo Tree Search for `nodes` nodes, returning the best move, and its estimated winning probability for the side to move.
 pub fn mcts<const S: usize>(position: Position<S>, nodes: u64) -> (Move<S>, f32) {
+    mcts_with_progress(position, nodes, u64::MAX, |_, _| ())
+}
+
+/// Like `mcts`, but calls `callback` with the visit count and the tree after every `interval` nodes,
+/// for example to print search info during a long search.
+pub fn mcts_with_progress<const S: usize, F>(
+    position: Position<S>,
+    nodes: u64,
+    interval: u64,
+    mut callback: F,
+) -> (Move<S>, f32)
+where
+    F: FnMut(u32, &MonteCarloTree<S>),
+{
+    assert!(interval > 0, "Progress interval must be at least 1 node");
     let settings = MctsSetting::default().arena_size_for_nodes(nodes as u32);
     let mut tree = MonteCarloTree::new(position, settings);
 
-    for _ in 0..nodes.max(2) {
+    for i in 1..=nodes.max(2) {
         tree.select().unwrap();
+        if i % interval == 0 {
+            callback(tree.visits(), &tree);
+        }
     }
     let (mv, score) = tree.best_move().unwrap();
     (mv, score)
 }
 
+/// Search the position for `nodes` nodes, and return the statistics for every legal move at the root,
+/// sorted with the most visited moves first. See `MonteCarloTree::root_move_stats`.
+///
+/// Like `MonteCarloTree::select_n`, the search stops early if it runs out of memory.
+/// Returns `Error::MaxVisits` if `nodes` does not fit in a `u32`.
+pub fn analyze<const S: usize>(
+    position: Position<S>,
+    nodes: u64,
+) -> Result<Vec<MoveStat<S>>, Error> {
+    let max_nodes = u32::try_from(nodes).map_err(|_| Error::MaxVisits)?;
+    let settings = MctsSetting::default().arena_size_for_nodes(max_nodes);
+    let mut tree = MonteCarloTree::new(position, settings);
+
+    tree.select_n(nodes.max(2))?;
+    Ok(tree.root_move_stats())
+}
+
+/// Root-parallel search, running an independent search of `nodes` nodes on each of `threads` threads.
+/// The root visits of each move are summed across the trees, and the move with the most visits is returned,
+/// along with its visit-weighted score.
+///
+/// Each search is deterministic on its own, so every tree except the first has Dirichlet noise applied at the root,
+/// to make the threads explore different moves. The noise is seeded by the thread's index,
+/// so the result is also deterministic.
+///
+/// # Panics
+///
+/// Panics if `threads` is 0, or if `nodes` does not fit in a `u32`.
+pub fn mcts_parallel<const S: usize>(
+    position: Position<S>,
+    nodes: u64,
+    threads: usize,
+) -> (Move<S>, f32) {
+    assert!(threads > 0, "Cannot search with 0 threads");
+    let max_nodes = u32::try_from(nodes).expect("Cannot search more than u32::MAX nodes");
+    let root_move_stats: Vec<Vec<MoveStat<S>>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let position = position.clone();
+                scope.spawn(move || {
+                    let mut settings = MctsSetting::default().arena_size_for_nodes(max_nodes);
+                    if i > 0 {
+                        settings =
+                            settings.add_dirichlet_seeded(ROOT_PARALLEL_DIRICHLET_ALPHA, i as u64);
+                    }
+                    let mut tree = MonteCarloTree::new(position, settings);
+                    tree.select_n(nodes.max(2)).unwrap();
+                    tree.root_move_stats()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    // Total visits and visit-weighted score for each move
+    let mut totals: Vec<(Move<S>, u64, f64)> = vec![];
+    for move_stat in root_move_stats.iter().flatten() {
+        let visits = move_stat.visits as u64;
+        let weighted_score = move_stat.mean_action_value as f64 * visits as f64;
+        match totals.iter_mut().find(|(mv, _, _)| *mv == move_stat.mv) {
+            Some((_, total_visits, total_score)) => {
+                *total_visits += visits;
+                *total_score += weighted_score;
+            }
+            None => totals.push((move_stat.mv, visits, weighted_score)),
+        }
+    }
+    let (mv, visits, total_score) = totals
+        .into_iter()
+        .max_by_key(|(_, visits, _)| *visits)
+        .unwrap();
+    (mv, (total_score / visits.max(1) as f64) as f32)
+}
+
+/// Like `mcts`, but searches the canonical form of the position, so that symmetric positions get the same evaluation.
+/// The returned move is mapped back to the orientation of the input position.
+pub fn mcts_symmetry_averaged<const S: usize>(position: Position<S>, nodes: u64) -> (Move<S>, f32) {
+    let (canonical_position, symmetry) = position.canonical_form();
+    let (mv, score) = mcts(canonical_position, nodes);
+    (symmetry.inverse_transform_move(mv), score)
+}
+
+/// Search the position with each of the given komis, returning the best move and its score for each.
+/// Komis without their own evaluation parameters use parameters interpolated from the supported komis.
+pub fn analyze_komi_sweep<const S: usize>(
+    position: Position<S>,
+    komis: &[Komi],
+    nodes: u64,
+) -> Vec<(Komi, Move<S>, f32)> {
+    komis
+        .iter()
+        .map(|&komi| {
+            let mut position = position.clone();
+            position.set_komi(komi);
+            let settings = MctsSetting::default().arena_size_for_nodes(nodes as u32);
+            let mut tree = MonteCarloTree::new(position, settings);
+            for _ in 0..nodes.max(2) {
+                tree.select().unwrap();
+            }
+            let (mv, score) = tree.best_move().unwrap();
+            (komi, mv, score)
+        })
+        .collect()
+}
+
 /// Play a move, calculating for a maximum duration.
 /// It will usually spend much less time, especially if the move is obvious.
 /// On average, it will spend around 20% of `max_time`, and rarely more than 50%.
@@ -510,9 +1248,47 @@ pub fn play_move_time<const S: usize>(
     max_time: time::Duration,
     settings: MctsSetting<S>,
 ) -> (Move<S>, f32) {
-    let mut tree = MonteCarloTree::new(board.clone(), settings);
-    tree.search_for_time(max_time, |_| {});
-    tree.best_move().unwrap()
+    let (mv, score, _) = play_move_time_verbose(board, max_time, settings);
+    (mv, score)
+}
+
+/// Like `play_move_time`, but also reports why the search stopped, for tuning time management.
+pub fn play_move_time_verbose<const S: usize>(
+    board: Position<S>,
+    max_time: time::Duration,
+    settings: MctsSetting<S>,
+) -> (Move<S>, f32, TimeManagementReport) {
+    let mut tree = MonteCarloTree::new(board, settings);
+    let report = tree.search_for_time(max_time, |_| {});
+    let (mv, score) = tree.best_move().unwrap();
+    (mv, score, report)
+}
+
+/// Why `MonteCarloTree::search_for_time` stopped
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StopReason {
+    /// The search ran into an error, such as running out of memory
+    Error,
+    /// Less than 10ms of the allotted time was left
+    OutOfTime,
+    /// There is only one legal move
+    ForcedMove,
+    /// The best move had enough visits compared to the second best, relative to the time spent
+    DominantMove,
+}
+
+/// Time management decisions from `MonteCarloTree::search_for_time`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeManagementReport {
+    pub stop_reason: StopReason,
+    pub elapsed: time::Duration,
+    /// Total visits in the tree when the search stopped
+    pub visits: u32,
+    /// The second best root move's visits divided by the best's, from the last time management check.
+    /// `None` if the search stopped before the first check
+    pub node_ratio: Option<f32>,
+    /// The fraction of the allotted time that was used
+    pub time_ratio: f32,
 }
 
 /// Run mcts with specific static evaluation parameters, for optimization the parameter set.
@@ -522,22 +1298,41 @@ pub fn mcts_training<const S: usize>(
     time_control: &TimeControl,
     settings: MctsSetting<S>,
 ) -> Vec<(Move<S>, f16)> {
+    let tree = training_search(position, time_control, settings);
+    root_visit_distribution(&tree)
+}
+
+/// Like `mcts_training`, but also returns the root's mean action value as a value target.
+/// The value is between 0.0 and 1.0, from the perspective of the side to move.
+pub fn mcts_training_with_value<const S: usize>(
+    position: Position<S>,
+    time_control: &TimeControl,
+    settings: MctsSetting<S>,
+) -> (Vec<(Move<S>, f16)>, f32) {
+    let tree = training_search(position, time_control, settings);
+    (root_visit_distribution(&tree), tree.mean_action_value())
+}
+
+fn training_search<const S: usize>(
+    position: Position<S>,
+    time_control: &TimeControl,
+    settings: MctsSetting<S>,
+) -> MonteCarloTree<S> {
     let mut tree = MonteCarloTree::new(position, settings);
 
     match time_control {
         TimeControl::FixedNodes(nodes) => {
-            for _ in 0..*nodes {
-                if let Err(err) = tree.select() {
-                    eprintln!("Warning: {err}");
-                    break;
-                }
-            }
+            tree.select_n(*nodes).unwrap();
         }
         TimeControl::Time(time, increment) => {
             let max_time = *time / 5 + *increment / 2;
             tree.search_for_time(max_time, |_| {});
         }
     }
+    tree
+}
+
+fn root_visit_distribution<const S: usize>(tree: &MonteCarloTree<S>) -> Vec<(Move<S>, f16)> {
     let shallow_edges = tree.shallow_edges().unwrap();
     let child_visits: u32 = shallow_edges.iter().map(|edge| edge.visits).sum();
     shallow_edges
@@ -551,11 +1346,222 @@ pub fn mcts_training<const S: usize>(
         .collect()
 }
 
+/// Winning probability below which a move is considered losing by `Position::is_forced_move`
+const FORCED_MOVE_LOSS_THRESHOLD: f32 = 0.1;
+
+/// Winning probability at which `Position::verify_puzzle` considers a move winning,
+/// for puzzles that are not checked exactly
+pub const PUZZLE_WIN_THRESHOLD: f32 = 0.9;
+
+impl<const S: usize> Position<S> {
+    /// Run a short search, and return the only move that does not lose, if there is exactly one.
+    /// Moves are considered losing if their score is below 10%, or if the search never visited them.
+    /// Intended for annotating forced moves when reviewing a game, so `nodes` can be kept small.
+    pub fn is_forced_move(&self, nodes: u32) -> Option<Move<S>> {
+        let settings = MctsSetting::default().arena_size_for_nodes(nodes);
+        let mut tree = MonteCarloTree::new(self.clone(), settings);
+        for _ in 0..nodes {
+            if tree.select().is_err() {
+                break;
+            }
+        }
+        let shallow_edges = tree.shallow_edges()?;
+        let mut non_losing_moves = shallow_edges.iter().filter(|edge| {
+            edge.visits > 0 && 1.0 - edge.mean_action_value >= FORCED_MOVE_LOSS_THRESHOLD
+        });
+        let forced_move = non_losing_moves.next()?.mv;
+        if non_losing_moves.next().is_none() {
+            Some(forced_move)
+        } else {
+            None
+        }
+    }
+
+    /// Search the position twice, once as it is, and once after a null move hands the turn to the opponent.
+    ///
+    /// Both scores are winning probabilities for the current side to move.
+    /// The first is the score with us to move, the second is the score if the opponent were to move instead.
+    /// Their difference estimates the value of the tempo.
+    pub fn evaluate_both_to_move(&self, nodes: u64) -> (f32, f32) {
+        let (_, score) = mcts(self.clone(), nodes);
+        let mut passed_position = self.clone();
+        passed_position.null_move();
+        let (_, opponent_score) = mcts(passed_position, nodes);
+        (score, 1.0 - opponent_score)
+    }
+
+    /// Check that `solution` is a valid puzzle line for the side to move.
+    ///
+    /// The solving side's moves are the even plies of `solution`, and the defending side's moves are the odd plies.
+    /// Every solving move must be the unique winning move in its position:
+    /// * If the side to move has any immediate wins, those are the only moves considered winning.
+    ///   This way, a road win in one is never reported as ambiguous because a slower win also exists.
+    /// * If the line ends with the solving side winning, it is checked exactly: A move is winning
+    ///   if it forces a win against every defense within the plies left in the line.
+    ///   This searches every move, so the cost grows quickly with the length of the line.
+    /// * Otherwise, a move is winning if a search of `nodes` nodes scores it at `PUZZLE_WIN_THRESHOLD` or more.
+    ///   Moves the search never visits are not winning.
+    ///
+    /// The defending moves are only checked for legality, since every defense is expected to lose.
+    pub fn verify_puzzle(&self, solution: &[Move<S>], nodes: u32) -> PuzzleVerdict {
+        let mut position = self.clone();
+        let mut legal_moves = vec![];
+        for (ply, mv) in solution.iter().enumerate() {
+            legal_moves.clear();
+            position.generate_moves(&mut legal_moves);
+            if position.game_result().is_some() || !legal_moves.contains(mv) {
+                return PuzzleVerdict::IllegalMove { ply };
+            }
+            position.do_move(*mv);
+        }
+        let ends_in_win = position.game_result() == Some(GameResult::win_by(self.side_to_move()));
+
+        let mut position = self.clone();
+        for (ply, mv) in solution.iter().enumerate() {
+            if ply % 2 == 0 {
+                legal_moves.clear();
+                position.generate_moves(&mut legal_moves);
+                let winning_moves = if ends_in_win {
+                    position.forced_winning_moves(&legal_moves, solution.len() - ply)
+                } else {
+                    position.puzzle_winning_moves(&legal_moves, nodes)
+                };
+                if !winning_moves.contains(mv) {
+                    return PuzzleVerdict::SolutionNotBest { ply };
+                }
+                if winning_moves.len() > 1 {
+                    return PuzzleVerdict::MultipleSolutions { ply };
+                }
+            }
+            position.do_move(*mv);
+        }
+        PuzzleVerdict::UniqueSolution
+    }
+
+    fn immediate_wins(&self, legal_moves: &[Move<S>]) -> Vec<Move<S>> {
+        legal_moves
+            .iter()
+            .copied()
+            .filter(|mv| self.move_is_immediate_win(*mv))
+            .collect()
+    }
+
+    /// The moves that force a win within `plies` plies, or only the immediate wins if there are any
+    fn forced_winning_moves(&self, legal_moves: &[Move<S>], plies: usize) -> Vec<Move<S>> {
+        let immediate_wins = self.immediate_wins(legal_moves);
+        if !immediate_wins.is_empty() {
+            return immediate_wins;
+        }
+        let mut position = self.clone();
+        legal_moves
+            .iter()
+            .copied()
+            .filter(|mv| position.move_forces_win_within(*mv, plies))
+            .collect()
+    }
+
+    /// Whether the side to move wins within `plies` plies against every defense, by an exhaustive search.
+    fn forces_win_within(&mut self, plies: usize) -> bool {
+        if self.has_immediate_win() {
+            return true;
+        }
+        if plies < 3 {
+            return false;
+        }
+        let moves: Vec<Move<S>> = self.moves_iter().collect();
+        moves
+            .into_iter()
+            .any(|mv| self.move_forces_win_within(mv, plies))
+    }
+
+    /// Whether `mv` wins immediately, or wins within `plies` plies, counting `mv`, against every reply
+    fn move_forces_win_within(&mut self, mv: Move<S>, plies: usize) -> bool {
+        let win = GameResult::win_by(self.side_to_move());
+        let reverse_move = self.do_move(mv);
+        let forces_win = match self.game_result() {
+            Some(result) => result == win,
+            None if plies < 3 => false,
+            None => {
+                let replies: Vec<Move<S>> = self.moves_iter().collect();
+                replies.into_iter().all(|reply| {
+                    let reverse_reply = self.do_move(reply);
+                    let wins = match self.game_result() {
+                        Some(result) => result == win,
+                        None => self.forces_win_within(plies - 2),
+                    };
+                    self.reverse_move(reverse_reply);
+                    wins
+                })
+            }
+        };
+        self.reverse_move(reverse_move);
+        forces_win
+    }
+
+    /// Whether the side to move can win this turn, by road or on flats
+    fn has_immediate_win(&self) -> bool {
+        if self.has_winning_move() {
+            return true;
+        }
+        // A flat win needs the board to be filled, which a spread can do for at most `S` empty squares,
+        // or a player to run out of pieces, which only a placement can do
+        let us = self.side_to_move();
+        let pieces_left = self.pieces_in_hand(us, Role::Flat) + self.pieces_in_hand(us, Role::Cap);
+        if self.empty_square_count() as usize > S && pieces_left > 1 {
+            return false;
+        }
+        self.moves_iter().any(|mv| self.move_is_immediate_win(mv))
+    }
+
+    fn puzzle_winning_moves(&self, legal_moves: &[Move<S>], nodes: u32) -> Vec<Move<S>> {
+        let immediate_wins = self.immediate_wins(legal_moves);
+        if !immediate_wins.is_empty() {
+            return immediate_wins;
+        }
+
+        let settings = MctsSetting::default().arena_size_for_nodes(nodes);
+        let mut tree = MonteCarloTree::new(self.clone(), settings);
+        for _ in 0..nodes {
+            if tree.select().is_err() {
+                break;
+            }
+        }
+        tree.shallow_edges()
+            .unwrap_or_default()
+            .iter()
+            .filter(|edge| edge.visits > 0 && 1.0 - edge.mean_action_value >= PUZZLE_WIN_THRESHOLD)
+            .map(|edge| edge.mv)
+            .collect()
+    }
+}
+
+/// Outcome of `Position::verify_puzzle`. Plies are indexed into the solution line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PuzzleVerdict {
+    /// Every move by the solving side is the only winning move in its position
+    UniqueSolution,
+    /// The solution move at `ply` wins, but at least one other move also wins
+    MultipleSolutions { ply: usize },
+    /// The solution move at `ply` was not found to win
+    SolutionNotBest { ply: usize },
+    /// The move at `ply` is illegal, or the game was already over
+    IllegalMove { ply: usize },
+}
+
 /// Convert a static evaluation in centipawns to a winning probability between 0.0 and 1.0.
 pub fn cp_to_win_percentage(cp: f32) -> f32 {
     0.5 + f32::atan(cp) / PI
 }
 
+/// Convert a winning probability to the centipawn scale of the static evaluation.
+/// This is the inverse of `cp_to_win_percentage`.
+///
+/// Probabilities approaching 0.0 or 1.0 give arbitrarily large values, so `p` is clamped to
+/// `[0.000001, 0.999999]`, which keeps the output finite, within roughly ±300000.
+pub fn win_percentage_to_cp(p: f32) -> f32 {
+    f32::tan((p.clamp(0.000_001, 0.999_999) - 0.5) * PI)
+}
+
 // Utility for testing
 pub fn edge_mem_usage<const S: usize>() -> usize {
     mem::size_of::<TreeEdge<S>>()
@@ -36,3 +36,25 @@ fn slot_57_size() {
     assert_eq!(arena.get(&index), &[3; 57]);
     assert_eq!(arena.get_slice(&slice_index), &[[4; 57], [5; 57]]);
 }
+
+#[test]
+fn growable_arena_test() {
+    let arena: Arena<4> = Arena::new_growable(100, 8).unwrap();
+    let indexes: Vec<_> = (0..20).map(|i| arena.add([i as u32; 3]).unwrap()).collect();
+    for (i, index) in indexes.iter().enumerate() {
+        assert_eq!(*arena.get(index), [i as u32; 3]);
+    }
+}
+
+#[test]
+fn growable_arena_full_test() {
+    let arena: Arena<4> = Arena::new_growable(16, 8).unwrap();
+    // Elements never cross block boundaries, so only two fit in the first block
+    assert!(arena.add([1u32; 3]).is_some());
+    assert!(arena.add([2u32; 3]).is_some());
+    assert!(arena.add([3u32; 3]).is_some());
+    assert!(arena.add([4u32; 3]).is_some());
+    assert_eq!(arena.add([5u32; 3]), None);
+    // An element larger than a block can never be stored
+    assert_eq!(arena.add([6u32; 9]), None);
+}
@@ -1,6 +1,7 @@
 use crate::evaluation::parameters::IncrementalPolicy;
 use crate::evaluation::parameters::PolicyApplier;
 use crate::position::starting_capstones;
+use crate::position::starting_stones;
 use crate::position::Komi;
 use board_game_traits::{Color, EvalPosition, GameResult::*, Position as PositionTrait};
 use pgn_traits::PgnPosition;
@@ -8,8 +9,8 @@ use rand::seq::SliceRandom;
 use rand::Rng;
 
 use crate::position::{squares_iterator, Role, Square};
-use crate::position::{GroupData, Move};
-use crate::position::{GroupEdgeConnection, Position};
+use crate::position::{ExpMove, GroupData, Move};
+use crate::position::{GroupEdgeConnection, Position, RoadOutcome};
 use crate::tests::do_moves_and_check_validity;
 
 #[test]
@@ -75,6 +76,122 @@ fn play_random_6s_games_test_long() {
     play_random_games_prop::<6>(10_000)
 }
 
+#[test]
+fn eval_breakdown_sums_to_static_eval_4s_test() {
+    eval_breakdown_sums_to_static_eval_prop::<4>()
+}
+
+#[test]
+fn eval_breakdown_sums_to_static_eval_5s_test() {
+    eval_breakdown_sums_to_static_eval_prop::<5>()
+}
+
+#[test]
+fn eval_breakdown_sums_to_static_eval_6s_test() {
+    eval_breakdown_sums_to_static_eval_prop::<6>()
+}
+
+fn eval_breakdown_sums_to_static_eval_prop<const S: usize>() {
+    let mut rng = rand::thread_rng();
+    let mut moves = vec![];
+    for _ in 0..20 {
+        let mut position = <Position<S>>::start_position();
+        while position.game_result().is_none() {
+            let breakdown = position.eval_breakdown(position.komi());
+            let sum: f32 = breakdown.iter().map(|(_, contribution)| contribution).sum();
+            let static_eval = position.static_eval();
+            assert!(
+                (sum - static_eval).abs() < 0.001,
+                "Breakdown sums to {}, static eval is {}\nTPS: {}",
+                sum,
+                static_eval,
+                position.to_fen()
+            );
+
+            moves.clear();
+            position.generate_moves(&mut moves);
+            position.do_move(*moves.choose(&mut rng).unwrap());
+        }
+    }
+}
+
+#[test]
+fn static_eval_breakdown_uses_komi_test() {
+    static_eval_breakdown_uses_komi_prop::<5>();
+    static_eval_breakdown_uses_komi_prop::<6>();
+}
+
+fn static_eval_breakdown_uses_komi_prop<const S: usize>() {
+    let komi = Komi::from_half_komi(4).unwrap();
+    let mut rng = rand::thread_rng();
+    let mut moves = vec![];
+    for _ in 0..5 {
+        let mut position = <Position<S>>::start_position_with_komi(komi);
+        while position.game_result().is_none() {
+            let breakdown = position.static_eval_breakdown();
+            assert_eq!(breakdown, position.eval_breakdown(komi));
+            let sum: f32 = breakdown.iter().map(|(_, contribution)| contribution).sum();
+            assert!(
+                (sum - position.static_eval()).abs() < 0.001,
+                "Breakdown sums to {}, static eval is {}\nTPS: {}",
+                sum,
+                position.static_eval(),
+                position.to_fen()
+            );
+
+            moves.clear();
+            position.generate_moves(&mut moves);
+            position.do_move(*moves.choose(&mut rng).unwrap());
+        }
+    }
+}
+
+#[test]
+fn move_symmetries_5s_test() {
+    move_symmetries_prop::<5>()
+}
+
+#[test]
+fn move_symmetries_6s_test() {
+    move_symmetries_prop::<6>()
+}
+
+fn move_symmetries_prop<const S: usize>() {
+    let mut rng = rand::thread_rng();
+    let mut moves = vec![];
+    for _ in 0..2 {
+        let mut position = <Position<S>>::start_position();
+        while position.game_result().is_none() {
+            moves.clear();
+            position.generate_moves(&mut moves);
+
+            let canonical_fen = position.canonical_form().0.to_fen();
+            for (symmetry, rotation) in position.symmetries().into_iter().enumerate() {
+                assert_eq!(rotation.canonical_form().0.to_fen(), canonical_fen);
+
+                let mut rotated_moves = vec![];
+                rotation.generate_moves(&mut rotated_moves);
+                for mv in moves.iter() {
+                    let rotated_move = mv.apply_symmetry(symmetry);
+                    assert!(rotated_moves.contains(&rotated_move));
+                    assert_eq!(rotated_move.apply_inverse_symmetry(symmetry), *mv);
+
+                    let mut position_after = position.clone();
+                    position_after.do_move(*mv);
+                    let mut rotation_after = rotation.clone();
+                    rotation_after.do_move(rotated_move);
+                    assert_eq!(
+                        position_after.symmetries()[symmetry].to_fen(),
+                        rotation_after.to_fen()
+                    );
+                }
+            }
+
+            position.do_move(*moves.choose(&mut rng).unwrap());
+        }
+    }
+}
+
 fn play_random_games_prop<const S: usize>(num_games: usize) {
     let mut white_wins = 0;
     let mut black_wins = 0;
@@ -91,7 +208,7 @@ fn play_random_games_prop<const S: usize>(num_games: usize) {
             let hash_from_scratch = position.zobrist_hash_from_scratch();
             assert_eq!(
                 hash_from_scratch,
-                position.zobrist_hash(),
+                position.hash(),
                 "Hash mismatch for board:\n{:?}\nMoves: {:?}",
                 position,
                 position.moves()
@@ -125,6 +242,13 @@ fn play_random_games_prop<const S: usize>(num_games: usize) {
 
             for mv in moves.iter() {
                 assert_eq!(*mv, Move::compress(mv.expand()));
+                if position.is_crush_move(*mv) {
+                    let ExpMove::Move(origin, _, stack_movement) = mv.expand() else {
+                        panic!("Placement {} cannot be a crush", mv)
+                    };
+                    assert_eq!(position.top_stones()[origin].unwrap().role(), Role::Cap);
+                    assert_eq!(stack_movement.into_iter().last().unwrap().pieces_to_take, 1);
+                }
             }
 
             let parameters = <Position<S>>::policy_params(position.komi());
@@ -250,7 +374,7 @@ fn play_random_games_no_eval_prop<const S: usize>(num_games: usize) {
             let hash_from_scratch = position.zobrist_hash_from_scratch();
             assert_eq!(
                 hash_from_scratch,
-                position.zobrist_hash(),
+                position.hash(),
                 "Hash mismatch for board:\n{:?}\nMoves: {:?}",
                 position,
                 position.moves()
@@ -269,6 +393,10 @@ fn play_random_games_no_eval_prop<const S: usize>(num_games: usize) {
                 (group_data.white_road_pieces() & group_data.white_blocking_pieces()).count()
                     <= starting_capstones(S)
             );
+            assert_eq!(
+                position.empty_square_count() as usize + group_data.all_pieces().count() as usize,
+                S * S
+            );
 
             moves.clear();
 
@@ -277,6 +405,7 @@ fn play_random_games_no_eval_prop<const S: usize>(num_games: usize) {
             for mv in moves.iter() {
                 assert_eq!(*mv, Move::compress(mv.expand()));
             }
+            assert!(position.moves_iter().eq(moves.iter().copied()));
 
             let mv = moves
                 .choose(&mut rng)
@@ -336,6 +465,228 @@ fn play_random_games_no_eval_prop<const S: usize>(num_games: usize) {
     )
 }
 
+/// Play `num_games` random games, and call `f` on every position before a move is played in it.
+/// `f` may play moves, but must reverse them again.
+fn for_each_random_game_position<const S: usize>(
+    num_games: usize,
+    mut f: impl FnMut(&mut Position<S>),
+) {
+    let mut rng = rand::thread_rng();
+    let mut moves = vec![];
+    for _ in 0..num_games {
+        let mut position = <Position<S>>::start_position();
+        while position.game_result().is_none() {
+            f(&mut position);
+            moves.clear();
+            position.generate_moves(&mut moves);
+            position.do_move(*moves.choose(&mut rng).unwrap());
+        }
+    }
+}
+
+#[test]
+fn group_data_after_move_random_games_test() {
+    group_data_after_move_prop::<4>(50);
+    group_data_after_move_prop::<5>(20);
+    group_data_after_move_prop::<6>(10);
+}
+
+fn group_data_after_move_prop<const S: usize>(num_games: usize) {
+    let mut moves = vec![];
+    for_each_random_game_position::<S>(num_games, |position| {
+        let group_data = position.group_data();
+        moves.clear();
+        position.generate_moves(&mut moves);
+        for mv in moves.iter() {
+            let reverse_move = position.do_move(*mv);
+            assert_eq!(
+                position.group_data_after_move(&group_data, *mv),
+                position.group_data(),
+                "Wrong group data after {} on\n{:?}",
+                mv,
+                position
+            );
+            position.reverse_move(reverse_move);
+        }
+    });
+}
+
+#[test]
+fn flat_count_random_games_test() {
+    flat_count_prop::<4>(100);
+    flat_count_prop::<5>(100);
+    flat_count_prop::<6>(100);
+}
+
+fn flat_count_prop<const S: usize>(num_games: usize) {
+    for_each_random_game_position::<S>(num_games, |position| {
+        let group_data = position.group_data();
+        assert_eq!(
+            position.flat_count(Color::White),
+            group_data.white_flat_stones.count()
+        );
+        assert_eq!(
+            position.flat_count(Color::Black),
+            group_data.black_flat_stones.count()
+        );
+    });
+}
+
+#[test]
+fn piece_counts_random_games_test() {
+    piece_counts_prop::<4>(100);
+    piece_counts_prop::<5>(100);
+    piece_counts_prop::<6>(100);
+}
+
+fn piece_counts_prop<const S: usize>(num_games: usize) {
+    for_each_random_game_position::<S>(num_games, |position| {
+        let group_data = position.group_data();
+        let piece_counts = position.piece_counts();
+        assert_eq!(
+            piece_counts.white_reserves + piece_counts.white_flats + piece_counts.white_walls,
+            starting_stones(S)
+        );
+        assert_eq!(
+            piece_counts.black_reserves + piece_counts.black_flats + piece_counts.black_walls,
+            starting_stones(S)
+        );
+        assert_eq!(
+            piece_counts.white_caps_in_hand + piece_counts.white_caps,
+            starting_capstones(S)
+        );
+        assert_eq!(
+            piece_counts.black_caps_in_hand + piece_counts.black_caps,
+            starting_capstones(S)
+        );
+        assert_eq!(piece_counts.white_walls, group_data.white_walls.count());
+        assert_eq!(piece_counts.black_caps, group_data.black_caps.count());
+    });
+}
+
+#[test]
+fn pieces_in_hand_random_games_test() {
+    pieces_in_hand_prop::<4>(100);
+    pieces_in_hand_prop::<6>(100);
+}
+
+fn pieces_in_hand_prop<const S: usize>(num_games: usize) {
+    for_each_random_game_position::<S>(num_games, |position| {
+        for color in [Color::White, Color::Black] {
+            assert_eq!(
+                position.pieces_in_hand(color, Role::Flat),
+                position.pieces_in_hand(color, Role::Wall)
+            );
+        }
+        assert_eq!(
+            position.pieces_in_hand(Color::White, Role::Flat),
+            position.white_reserves_left()
+        );
+        assert_eq!(
+            position.pieces_in_hand(Color::Black, Role::Flat),
+            position.black_reserves_left()
+        );
+        assert_eq!(
+            position.pieces_in_hand(Color::White, Role::Cap),
+            position.white_caps_left()
+        );
+        assert_eq!(
+            position.pieces_in_hand(Color::Black, Role::Cap),
+            position.black_caps_left()
+        );
+    });
+}
+
+#[test]
+fn count_legal_moves_random_games_test() {
+    count_legal_moves_prop::<3>(100);
+    count_legal_moves_prop::<4>(100);
+    count_legal_moves_prop::<5>(100);
+    count_legal_moves_prop::<6>(100);
+    count_legal_moves_prop::<7>(20);
+    count_legal_moves_prop::<8>(20);
+}
+
+fn count_legal_moves_prop<const S: usize>(num_games: usize) {
+    let mut moves = vec![];
+    for_each_random_game_position::<S>(num_games, |position| {
+        moves.clear();
+        position.generate_moves(&mut moves);
+        assert_eq!(
+            position.count_legal_moves(),
+            moves.len(),
+            "Wrong move count for {}",
+            position.to_fen()
+        );
+    });
+}
+
+#[test]
+fn has_winning_move_random_games_test() {
+    has_winning_move_prop::<4>(100);
+    has_winning_move_prop::<5>(100);
+    has_winning_move_prop::<6>(50);
+}
+
+fn has_winning_move_prop<const S: usize>(num_games: usize) {
+    let mut moves = vec![];
+    for_each_random_game_position::<S>(num_games, |position| {
+        let us = position.side_to_move();
+        moves.clear();
+        position.generate_moves(&mut moves);
+        let completes_road = moves.iter().any(|mv| {
+            let reverse_move = position.do_move(*mv);
+            let road_outcome = position.road_winner_detailed();
+            position.reverse_move(reverse_move);
+            matches!(
+                road_outcome,
+                Some(RoadOutcome::SingleRoad(color) | RoadOutcome::DoubleRoad(color))
+                    if color == us
+            )
+        });
+        assert_eq!(
+            position.has_winning_move(),
+            completes_road,
+            "TPS {}",
+            position.to_fen()
+        );
+    });
+}
+
+#[test]
+fn canonical_form_symmetry_random_games_test() {
+    canonical_form_symmetry_prop::<4>(20);
+    canonical_form_symmetry_prop::<5>(10);
+    canonical_form_symmetry_prop::<6>(10);
+}
+
+fn canonical_form_symmetry_prop<const S: usize>(num_games: usize) {
+    let mut moves = vec![];
+    let mut canonical_moves = vec![];
+    for_each_random_game_position::<S>(num_games, |position| {
+        let (canonical_position, canonical_symmetry) = position.canonical_form();
+        assert!(!canonical_symmetry.swap_colors);
+        assert_eq!(
+            canonical_position.to_fen(),
+            position.symmetries()[canonical_symmetry.board_symmetry.index()].to_fen()
+        );
+
+        moves.clear();
+        position.generate_moves(&mut moves);
+        canonical_moves.clear();
+        canonical_position.generate_moves(&mut canonical_moves);
+        assert_eq!(moves.len(), canonical_moves.len());
+        for mv in moves.iter() {
+            let canonical_move = canonical_symmetry.transform_move(*mv);
+            assert!(canonical_moves.contains(&canonical_move));
+            assert_eq!(
+                canonical_symmetry.inverse_transform_move(canonical_move),
+                *mv
+            );
+        }
+    });
+}
+
 #[test]
 fn go_in_directions_3s_test() {
     go_in_directions_prop::<3>()
@@ -1,11 +1,15 @@
+use std::error;
+
 use board_game_traits::{Color, Position as PositionTrait};
 use board_game_traits::{GameResult, GameResult::*};
 use pgn_traits::PgnPosition;
 
-use crate::position::Direction::*;
-use crate::position::Piece::{BlackCap, BlackFlat, WhiteFlat, WhiteWall};
-use crate::position::Position;
-use crate::position::{squares_iterator, Piece, Role, Square, Stack};
+use crate::position::Direction;
+use crate::position::Piece::{BlackCap, BlackFlat, BlackWall, WhiteCap, WhiteFlat, WhiteWall};
+use crate::position::{squares_iterator, BitBoard, Piece, Role, Square, Stack, StackComposition};
+use crate::position::{
+    BoardSymmetry, Komi, Position, ReservePressure, RoadOutcome, Symmetry, ThreatSummary, TpsError,
+};
 use crate::position::{ExpMove, Move};
 use crate::tests::do_moves_and_check_validity;
 use crate::{position as board_mod, search};
@@ -102,9 +106,9 @@ fn correct_number_of_neighbours_test() {
 fn correct_number_of_legal_directions_test() {
     assert_eq!(
         squares_iterator::<5>()
-            .flat_map(|square| [North, South, East, West]
-                .iter()
-                .filter_map(move |&direction| square.go_direction(direction)))
+            .flat_map(|square| Direction::all()
+                .into_iter()
+                .filter_map(move |direction| square.go_direction(direction)))
             .count(),
         4 * 2 + 12 * 3 + 9 * 4
     );
@@ -429,6 +433,69 @@ fn repetitions_are_draws_test() {
     assert_eq!(position.game_result(), None);
 }
 
+#[test]
+fn repetition_draw_count_test() {
+    let cycle_move_strings = ["e5-", "a1+", "e4+", "a2-"];
+
+    let settings = board_mod::Settings {
+        repetition_draw_count: Some(2),
+        ..Default::default()
+    };
+    let mut position = <Position<5>>::start_position_with_settings(&settings);
+    do_moves_and_check_validity(&mut position, &["a1", "e5"]);
+    do_moves_and_check_validity(&mut position, &cycle_move_strings);
+    assert_eq!(position.game_result(), Some(GameResult::Draw));
+
+    let settings = board_mod::Settings {
+        repetition_draw_count: None,
+        ..Default::default()
+    };
+    let mut position =
+        <Position<5>>::from_fen_with_settings("x5/x5/x5/x5/x5 1 1", &settings).unwrap();
+    assert_eq!(position.repetition_draw_count(), None);
+    do_moves_and_check_validity(&mut position, &["a1", "e5"]);
+    for _ in 0..4 {
+        do_moves_and_check_validity(&mut position, &cycle_move_strings);
+        assert_eq!(position.game_result(), None);
+    }
+    assert_eq!(position.repetition_count(), 4);
+
+    position.set_repetition_draw_count(Some(3)).unwrap();
+    assert_eq!(position.game_result(), Some(GameResult::Draw));
+}
+
+#[test]
+fn invalid_repetition_draw_count_test() {
+    let mut position = <Position<5>>::start_position();
+    assert!(position.set_repetition_draw_count(Some(0)).is_err());
+    assert!(position.set_repetition_draw_count(Some(1)).is_err());
+    assert_eq!(position.repetition_draw_count(), Some(3));
+
+    let settings = board_mod::Settings {
+        repetition_draw_count: Some(1),
+        ..Default::default()
+    };
+    assert!(<Position<5>>::from_fen_with_settings("x5/x5/x5/x5/x5 1 1", &settings).is_err());
+}
+
+#[test]
+fn reversible_moves_count_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5"]);
+    assert_eq!(position.reversible_moves_count(), 0);
+
+    do_moves_and_check_validity(&mut position, &["e5-", "a1+", "e4+", "a2-"]);
+    assert_eq!(position.reversible_moves_count(), 4);
+    assert_eq!(position.repetition_count(), 1);
+
+    do_moves_and_check_validity(&mut position, &["e5-"]);
+    assert_eq!(position.reversible_moves_count(), 5);
+    assert_eq!(position.repetition_count(), 1);
+
+    do_moves_and_check_validity(&mut position, &["b1"]);
+    assert_eq!(position.reversible_moves_count(), 0);
+}
+
 #[test]
 fn fake_repetitions_are_not_draws_test() {
     let mut position = <Position<6>>::start_position();
@@ -443,6 +510,465 @@ fn fake_repetitions_are_not_draws_test() {
     assert_eq!(position.game_result(), None);
 }
 
+#[test]
+fn same_board_different_move_order_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "b1", "d5", "c1", "c5"]);
+
+    let mut transposed_position = <Position<5>>::start_position();
+    do_moves_and_check_validity(
+        &mut transposed_position,
+        &["a1", "e5", "c1", "c5", "b1", "d5"],
+    );
+
+    assert!(position.same_board(&transposed_position));
+    assert_eq!(position, transposed_position);
+}
+
+#[test]
+fn same_board_ignores_move_count_and_komi_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "b1", "d5"]);
+
+    let mut shuffled_position = position.clone();
+    do_moves_and_check_validity(&mut shuffled_position, &["b1+", "d5-", "b2-", "d4+"]);
+
+    assert!(position.same_board(&shuffled_position));
+    assert_ne!(position, shuffled_position);
+
+    shuffled_position.set_komi(Komi::from_half_komi(4).unwrap());
+    assert!(position.same_board(&shuffled_position));
+
+    do_moves_and_check_validity(&mut shuffled_position, &["b1+"]);
+    assert!(!position.same_board(&shuffled_position));
+}
+
+#[test]
+fn spread_reach_test() {
+    let position = <Position<5>>::from_fen("x5/x5/x,2C,121C,2S,x/x5/x5 1 10").unwrap();
+    let reach: Vec<(String, u8)> = position
+        .spread_reach(Square::parse_square("c3").unwrap())
+        .into_iter()
+        .map(|(square, pieces)| (square.to_string(), pieces))
+        .collect();
+    assert_eq!(
+        reach,
+        vec![
+            ("c5".to_string(), 2),
+            ("c4".to_string(), 3),
+            ("c2".to_string(), 3),
+            ("c1".to_string(), 2),
+            ("d3".to_string(), 1)
+        ]
+    );
+
+    // The stack belongs to white, so black cannot spread it
+    let position = <Position<5>>::from_fen("x5/x5/x,2C,121C,2S,x/x5/x5 2 10").unwrap();
+    assert!(position
+        .spread_reach(Square::parse_square("c3").unwrap())
+        .is_empty());
+}
+
+#[test]
+fn stack_height_histogram_test() {
+    let position = <Position<5>>::from_fen("x4,21/x5/x2,1212121212121C,x2/x5/2S,x4 1 10").unwrap();
+    let histogram = position.stack_height_histogram();
+    assert_eq!(histogram[0], 22);
+    assert_eq!(histogram[1], 1);
+    assert_eq!(histogram[2], 1);
+    assert_eq!(histogram[13], 1);
+    assert_eq!(histogram.iter().sum::<u16>(), 25);
+}
+
+#[test]
+fn result_string_if_adjudicated_test() {
+    // White leads 4-3 on flats, and the game is not over
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    assert_eq!(position.pgn_game_result(), None);
+    let result_str =
+        |half_komi| position.result_string_if_adjudicated(Komi::from_half_komi(half_komi).unwrap());
+    assert_eq!(result_str(0), "F-0");
+    assert_eq!(result_str(2), "1/2-1/2");
+    assert_eq!(result_str(4), "0-F");
+}
+
+#[test]
+fn board_fill_fraction_test() {
+    let position = <Position<5>>::start_position();
+    assert_eq!(position.empty_square_count(), 25);
+    assert_eq!(position.board_fill_fraction(), 0.0);
+
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    assert_eq!(position.empty_square_count(), 18);
+    assert_eq!(position.board_fill_fraction(), 7.0 / 25.0);
+}
+
+#[test]
+fn null_move_hash_test() {
+    let mut position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let original_hash = position.hash();
+    position.null_move();
+    assert_eq!(position.side_to_move(), Color::Black);
+    assert_eq!(position.hash(), position.zobrist_hash_from_scratch());
+    assert_ne!(position.hash(), original_hash);
+    position.null_move();
+    assert_eq!(position.hash(), original_hash);
+}
+
+#[test]
+fn threat_summary_test() {
+    // White threatens a1, Black threatens b5
+    let position = <Position<5>>::from_fen("1,x4/1,2,x3/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let mutual_threats = ThreatSummary {
+        white_open_threats: 1,
+        black_open_threats: 1,
+        white_closed_threats: 0,
+        black_closed_threats: 0,
+        in_tak: true,
+        has_immediate_win: true,
+    };
+    assert_eq!(position.threat_summary(), mutual_threats);
+    let position = <Position<5>>::from_fen("1,x4/1,2,x3/1,2,x3/1,2,x3/x,2,x3 2 5").unwrap();
+    assert_eq!(position.threat_summary(), mutual_threats);
+
+    // A black wall on a1 closes White's threat
+    let position = <Position<5>>::from_fen("1,x4/1,2,x3/1,2,x3/1,2,x3/2S,2,x3 1 6").unwrap();
+    assert_eq!(
+        position.threat_summary(),
+        ThreatSummary {
+            white_open_threats: 0,
+            black_open_threats: 1,
+            white_closed_threats: 1,
+            black_closed_threats: 0,
+            in_tak: true,
+            has_immediate_win: false,
+        }
+    );
+}
+
+#[test]
+fn ranked_threat_squares_test() {
+    let a1 = <Square<5>>::parse_square("a1").unwrap();
+
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    assert_eq!(position.ranked_threat_squares(Color::White), vec![(a1, 1)]);
+    assert_eq!(position.ranked_threat_squares(Color::Black), vec![]);
+
+    // a1 completes both the a-file and the first rank.
+    // The occupied b2 is also critical, since a road piece spreading there would connect a2 and b1
+    let b2 = <Square<5>>::parse_square("b2").unwrap();
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,2,2,x/x,1,1,1,1 2 9").unwrap();
+    assert_eq!(
+        position.ranked_threat_squares(Color::White),
+        vec![(a1, 2), (b2, 1)]
+    );
+}
+
+#[test]
+fn is_symmetric_to_test() {
+    let position = <Position<6>>::from_fen("2,x5/x6/x,1,2,x3/x6/x6/x5,1 1 3").unwrap();
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+
+    for (i, transformed) in position.symmetries_with_swapped_colors().iter().enumerate() {
+        let symmetry = position.is_symmetric_to(transformed).unwrap();
+        assert_eq!(
+            symmetry,
+            Symmetry {
+                board_symmetry: BoardSymmetry::ALL[i / 2],
+                swap_colors: i % 2 == 1
+            }
+        );
+        let mut transformed_moves = vec![];
+        transformed.generate_moves(&mut transformed_moves);
+        for mv in legal_moves.iter() {
+            assert!(transformed_moves.contains(&symmetry.transform_move(*mv)));
+        }
+    }
+
+    let mut other_position = position.clone();
+    other_position.do_move(other_position.move_from_san("f6").unwrap());
+    assert_eq!(position.is_symmetric_to(&other_position), None);
+}
+
+#[test]
+fn canonical_form_undo_test() {
+    let mut position = <Position<5>>::from_fen("x5/x5/x,2S,x3/x,1C,1,x2/x5 1 5").unwrap();
+    // The capstone and the black flat move back and forth, repeating the position after e5
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "b2>", "e5<", "c2<", "d5>"]);
+    assert_eq!(position.repetition_count(), 1);
+
+    // The repetition history is cleared in every orientation
+    let (mut canonical_position, symmetry) = position.canonical_form();
+    assert_ne!(symmetry.board_symmetry, BoardSymmetry::Identity);
+    assert_eq!(canonical_position.reversible_moves_count(), 0);
+    let (canonical_of_canonical, identity) = canonical_position.canonical_form();
+    assert_eq!(identity.board_symmetry, BoardSymmetry::Identity);
+    assert_eq!(canonical_of_canonical.reversible_moves_count(), 0);
+
+    // Flatten the wall, which cannot be seen from the board when taking the move back
+    let mv = position.move_from_san("b2+").unwrap();
+    position.do_move(mv);
+    canonical_position.do_move(symmetry.transform_move(mv));
+
+    loop {
+        assert_eq!(
+            canonical_position.to_fen(),
+            position.transform_board(symmetry.board_symmetry).to_fen()
+        );
+        assert_eq!(
+            canonical_position.hash(),
+            canonical_position.zobrist_hash_from_scratch()
+        );
+        let undone = position.undo_last_move();
+        assert_eq!(canonical_position.undo_last_move(), undone);
+        if !undone {
+            break;
+        }
+    }
+}
+
+#[test]
+fn bitboard_test() {
+    let a1 = <Square<5>>::parse_square("a1").unwrap();
+    let c3 = <Square<5>>::parse_square("c3").unwrap();
+    let e5 = <Square<5>>::parse_square("e5").unwrap();
+
+    assert_eq!(BitBoard::full::<5>().count(), 25);
+    assert_eq!(BitBoard::full::<8>().count(), 64);
+    assert!(BitBoard::empty().is_empty());
+
+    let board = BitBoard::from_squares([a1, c3]);
+    assert_eq!(board.count(), 2);
+    assert!(board.contains(a1));
+    assert!(!board.contains(e5));
+    assert_eq!(
+        board.set_square(e5).clear_square(a1),
+        BitBoard::from_squares([c3, e5])
+    );
+
+    let complement = !board & BitBoard::full::<5>();
+    assert_eq!(complement.count(), 23);
+    assert_eq!(complement | board, BitBoard::full::<5>());
+    assert!((complement & board).is_empty());
+
+    // Squares are iterated in the order of their bits
+    let squares: Vec<Square<5>> = board.into_iter().collect();
+    assert_eq!(squares, [a1, c3]);
+}
+
+#[test]
+fn stack_captives_and_reserves_test() {
+    let mut stack = Stack::default();
+    assert_eq!(stack.captives(Color::White), 0);
+    assert_eq!(stack.reserves_of(Color::Black), 0);
+
+    for piece in [
+        BlackFlat, BlackFlat, BlackFlat, WhiteFlat, WhiteFlat, WhiteFlat, BlackFlat, WhiteFlat,
+    ] {
+        stack.push(piece);
+    }
+    assert_eq!(stack.captives(Color::White), 4);
+    assert_eq!(stack.reserves_of(Color::White), 3);
+    assert_eq!(stack.captives(Color::Black), 0);
+    assert_eq!(stack.reserves_of(Color::Black), 0);
+
+    stack.push(BlackCap);
+    assert_eq!(stack.captives(Color::Black), 4);
+    assert_eq!(stack.reserves_of(Color::Black), 4);
+    assert_eq!(stack.captives(Color::White), 0);
+    assert_eq!(stack.reserves_of(Color::White), 0);
+}
+
+#[test]
+fn stack_iter_top_down_test() {
+    let mut stack = Stack::default();
+    assert_eq!(stack.iter_top_down().next(), None);
+
+    for piece in [WhiteWall, BlackFlat, BlackWall, WhiteCap] {
+        stack.push(piece);
+    }
+    let pieces: Vec<Piece> = stack.iter_top_down().collect();
+    assert_eq!(pieces, vec![WhiteCap, BlackFlat, BlackFlat, WhiteFlat]);
+    assert_eq!(pieces[0], stack.top_stone().unwrap());
+    for (i, piece) in pieces.iter().enumerate() {
+        assert_eq!(stack.get(stack.len() - 1 - i as u8), Some(*piece));
+    }
+
+    let mut bottom_up: Vec<Piece> = stack.into_iter().collect();
+    bottom_up.reverse();
+    assert_eq!(bottom_up, pieces);
+}
+
+#[test]
+fn stack_compositions_test() {
+    let position = <Position<5>>::from_fen("x4,21/x5/x2,1212121212121C,x2/x5/2S,x4 1 10").unwrap();
+    let compositions: Vec<(String, StackComposition)> = position
+        .stack_compositions()
+        .into_iter()
+        .map(|(square, composition)| (square.to_string(), composition))
+        .collect();
+    assert_eq!(
+        compositions,
+        vec![
+            (
+                "a1".to_string(),
+                StackComposition {
+                    top_stone: BlackWall,
+                    supports: 0,
+                    captives: 0
+                }
+            ),
+            (
+                "c3".to_string(),
+                StackComposition {
+                    top_stone: WhiteCap,
+                    supports: 6,
+                    captives: 6
+                }
+            ),
+            (
+                "e5".to_string(),
+                StackComposition {
+                    top_stone: WhiteFlat,
+                    supports: 0,
+                    captives: 1
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn only_capstone_in_reserve_test() {
+    let mut position =
+        <Position<5>>::from_fen("x5/x5/x5/x5/111111111111111111111,2,2,x2 1 12").unwrap();
+    assert_eq!(position.white_reserves_left(), 0);
+    assert_eq!(position.white_caps_left(), 1);
+    assert_eq!(position.game_result(), None);
+
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+    assert!(moves.contains(&position.move_from_san("Cc3").unwrap()));
+    assert!(!moves
+        .iter()
+        .any(|mv| matches!(mv.expand(), ExpMove::Place(Role::Flat | Role::Wall, _))));
+    assert!(position.move_is_legal(position.move_from_san("Cc3").unwrap()));
+    assert!(!position.move_is_legal(position.move_from_san("c3").unwrap()));
+    assert!(!position.move_is_legal(position.move_from_san("Sc3").unwrap()));
+
+    // The flat win features only look at the stones in reserve, so a lone capstone is handled like no reserves
+    let static_eval =
+        position.static_eval_with_params(<Position<5>>::value_params(position.komi()));
+    assert!(static_eval.is_finite());
+
+    // Placing the capstone ends the game, but does not count as a flat
+    position.do_move(position.move_from_san("Cc3").unwrap());
+    assert_eq!(position.game_result(), Some(BlackWin));
+}
+
+#[test]
+fn do_moves_checked_test() {
+    let moves: Vec<Move<5>> = ["e1", "a1", "a2", "e2", "a3", "e3", "a4", "e4", "a5", "e5"]
+        .iter()
+        .map(|move_string| Move::from_string(move_string).unwrap())
+        .collect();
+
+    let mut position = <Position<5>>::start_position();
+    assert_eq!(position.do_moves_checked(&moves[..9]), Ok(()));
+    assert_eq!(position.half_moves_played(), 9);
+    assert!(position.game_result().is_some());
+
+    // The game is over after white's road on the a-file
+    let mut position = <Position<5>>::start_position();
+    assert_eq!(position.do_moves_checked(&moves), Err(9));
+    assert_eq!(position.half_moves_played(), 9);
+
+    // Placing on an occupied square is illegal
+    let mut position = <Position<5>>::start_position();
+    let illegal_moves = [moves[0], moves[1], moves[0]];
+    assert_eq!(position.do_moves_checked(&illegal_moves), Err(2));
+    assert_eq!(position.half_moves_played(), 2);
+}
+
+#[test]
+fn reserve_pressure_assessment_test() {
+    let komi_0 = Komi::from_half_komi(0).unwrap();
+    let komi_2 = Komi::from_half_komi(4).unwrap();
+
+    // White is one flat ahead, which is not enough with 2 komi
+    let position = <Position<6>>::from_fen("1,1,2,x3/x6/x6/x6/x6/x6 1 3").unwrap();
+    assert_eq!(
+        position.reserve_pressure_assessment(komi_0),
+        ReservePressure {
+            our_reserves: 29,
+            their_reserves: 30,
+            komi_adjusted_flat_lead: 1,
+            filling_favors_us: true,
+        }
+    );
+    let assessment = position.reserve_pressure_assessment(komi_2);
+    assert_eq!(assessment.komi_adjusted_flat_lead, -1);
+    assert!(!assessment.filling_favors_us);
+
+    let position = <Position<6>>::from_fen("1,1,2,x3/x6/x6/x6/x6/x6 2 3").unwrap();
+    assert!(
+        !position
+            .reserve_pressure_assessment(komi_0)
+            .filling_favors_us
+    );
+    let assessment = position.reserve_pressure_assessment(komi_2);
+    assert_eq!(assessment.our_reserves, 30);
+    assert_eq!(assessment.komi_adjusted_flat_lead, 1);
+    assert!(assessment.filling_favors_us);
+
+    // Midgame position where white is two flats behind
+    let position = <Position<6>>::from_fen(
+        "x2,2,x2,1/x,2,2,1,1,x/x,2,2C,1,x2/x,2,1S,12,1C,x/x2,2,1,x2/x6 1 11",
+    )
+    .unwrap();
+    for (half_komi, flat_lead) in [(-3, -1), (-1, -2), (0, -2), (1, -3), (4, -4), (5, -5)] {
+        let assessment =
+            position.reserve_pressure_assessment(Komi::from_half_komi(half_komi).unwrap());
+        assert_eq!(
+            assessment.komi_adjusted_flat_lead, flat_lead,
+            "Half komi {}",
+            half_komi
+        );
+        assert!(!assessment.filling_favors_us);
+    }
+    // With negative komi, a tie on the board favors white
+    let position = <Position<6>>::from_fen("1,2,x4/x6/x6/x6/x6/x6 1 2").unwrap();
+    let assessment = position.reserve_pressure_assessment(Komi::from_half_komi(-1).unwrap());
+    assert_eq!(assessment.komi_adjusted_flat_lead, 1);
+    assert!(assessment.filling_favors_us);
+}
+
+#[test]
+fn komi_adjusted_flat_lead_test() {
+    for half_komi in Komi::half_komi_range() {
+        let komi = Komi::from_half_komi(half_komi).unwrap();
+        for flat_lead in -10..=10 {
+            let result = komi.game_result_with_flatcounts(15 + flat_lead, 15);
+            let white_lead = komi.komi_adjusted_flat_lead(Color::White, flat_lead);
+            let black_lead = komi.komi_adjusted_flat_lead(Color::Black, -flat_lead);
+            assert_eq!(white_lead, -black_lead);
+            let expected_sign = match result {
+                WhiteWin => 1,
+                BlackWin => -1,
+                Draw => 0,
+            };
+            assert_eq!(
+                white_lead.signum(),
+                expected_sign,
+                "{} lead with {} half komi",
+                flat_lead,
+                half_komi
+            );
+        }
+    }
+}
+
 #[test]
 fn parse_tps_test() {
     let tps_string = "x4,1/x5/x5/x5/2,x4 1 2";
@@ -462,3 +988,366 @@ fn join_too_many_groups_test() {
     let position = <Position<6>>::from_fen(tps).unwrap();
     search::mcts(position, 1000);
 }
+
+#[test]
+fn from_half_komi_test() {
+    for half_komi in [0, 2, 4, 5] {
+        assert_eq!(
+            Komi::from_half_komi(half_komi).unwrap().half_komi(),
+            half_komi
+        );
+    }
+    for half_komi in Komi::half_komi_range() {
+        assert!(Komi::from_half_komi(half_komi).is_ok());
+    }
+    for half_komi in [-11, 11, i8::MIN, i8::MAX] {
+        let err = Komi::from_half_komi(half_komi).unwrap_err();
+        assert!(err.contains("-10 and 10"), "{}", err);
+    }
+}
+
+#[test]
+fn eval_params_for_every_komi_test() {
+    assert_eq!(
+        <Position<5>>::value_params(Komi::from_half_komi(4).unwrap()),
+        <Position<5>>::value_params_2komi()
+    );
+    for half_komi in Komi::half_komi_range() {
+        let komi = Komi::from_half_komi(half_komi).unwrap();
+        assert_eq!(
+            <Position<5>>::value_params(komi).len(),
+            <Position<5>>::value_params_0komi().len()
+        );
+        assert_eq!(
+            <Position<5>>::policy_params(komi).len(),
+            <Position<5>>::policy_params_0komi().len()
+        );
+        let position =
+            <Position<5>>::from_fen_with_komi("x2,1,x2/x,2,1,x2/x,2,1C,x2/x,2,x2,2/x5 1 5", komi)
+                .unwrap();
+        search::mcts(position, 100);
+    }
+}
+
+#[test]
+fn explain_flat_result_test() {
+    let komi = |half_komi| Komi::from_half_komi(half_komi).unwrap();
+    assert_eq!(
+        komi(5).explain_flat_result(21, 18),
+        "White 21 vs Black 18 +2.5 komi → White wins by 0.5 flats"
+    );
+    assert_eq!(
+        komi(4).explain_flat_result(21, 19),
+        "White 21 vs Black 19 +2.0 komi → Draw by komi"
+    );
+    assert_eq!(
+        komi(5).explain_flat_result(21, 19),
+        "White 21 vs Black 19 +2.5 komi → Black wins by komi"
+    );
+    assert_eq!(
+        komi(-3).explain_flat_result(15, 15),
+        "White 15 vs Black 15 -1.5 komi → White wins by komi"
+    );
+    assert_eq!(
+        komi(0).explain_flat_result(15, 15),
+        "White 15 vs Black 15 +0.0 komi → Draw"
+    );
+    assert_eq!(
+        komi(0).explain_flat_result(12, 17),
+        "White 12 vs Black 17 +0.0 komi → Black wins by 5.0 flats"
+    );
+}
+
+#[test]
+fn flat_margin_test() {
+    let komi = |half_komi| Komi::from_half_komi(half_komi).unwrap();
+    assert_eq!(komi(5).flat_margin(16, 15), -1.5);
+    assert_eq!(komi(5).game_result_with_flatcounts(16, 15), BlackWin);
+    assert_eq!(komi(4).flat_margin(17, 15), 0.0);
+    assert_eq!(komi(0).flat_margin(12, 17), -5.0);
+    assert_eq!(komi(-3).flat_margin(15, 15), 1.5);
+
+    for half_komi in Komi::half_komi_range() {
+        for flat_lead in -10..=10 {
+            let margin = komi(half_komi).flat_margin(15 + flat_lead, 15);
+            let result_from_margin = if margin > 0.0 {
+                WhiteWin
+            } else if margin < 0.0 {
+                BlackWin
+            } else {
+                Draw
+            };
+            assert_eq!(
+                result_from_margin,
+                komi(half_komi).game_result_with_flatcounts(15 + flat_lead, 15)
+            );
+        }
+    }
+}
+
+#[test]
+fn tallest_stack_hash_test() {
+    let tps_with_stack = |stack: &str| format!("{},x7/x8/x8/x8/x8/x8/x8/x8 1 40", stack);
+    let tallest_stack = "12".repeat(32) + "1";
+    let mut position = <Position<8>>::from_fen(&tps_with_stack(&tallest_stack)).unwrap();
+    assert_eq!(
+        position.stack_heights()[Square::from_u8(0)],
+        Stack::MAX_HEIGHT
+    );
+    assert_eq!(position.hash(), position.zobrist_hash_from_scratch());
+
+    // Changing pieces in the bottom or top slice of the stack changes the hash
+    for i in [0, 62] {
+        let mut pieces = tallest_stack.clone().into_bytes();
+        pieces.swap(i, i + 1);
+        let other_position =
+            <Position<8>>::from_fen(&tps_with_stack(&String::from_utf8(pieces).unwrap())).unwrap();
+        assert_ne!(position.hash(), other_position.hash());
+    }
+
+    let original_hash = position.hash();
+    let mv = position.move_from_san("8a8>").unwrap();
+    let reverse_move = position.do_move(mv);
+    assert_eq!(position.hash(), position.zobrist_hash_from_scratch());
+    position.reverse_move(reverse_move);
+    assert_eq!(position.hash(), original_hash);
+
+    assert!(<Position<8>>::from_fen(&tps_with_stack(&("2".to_string() + &tallest_stack))).is_err());
+}
+
+#[test]
+fn has_road_test() {
+    let position = <Position<4>>::from_fen("1,1,1,1/2,2,2,2/x4/x4 1 5").unwrap();
+    assert!(position.has_road(Color::White));
+    assert!(position.has_road(Color::Black));
+
+    // Walls do not count towards a road, capstones do
+    let position = <Position<5>>::from_fen("1,1C,1,1,1/2,2S,2,2,2/x5/x5/x5 2 6").unwrap();
+    assert!(position.has_road(Color::White));
+    assert!(!position.has_road(Color::Black));
+
+    let position = <Position<4>>::from_fen("1,x3/1,2,x2/1,2,x2/x,2,x2 1 4").unwrap();
+    assert!(!position.has_road(Color::White));
+    assert!(!position.has_road(Color::Black));
+}
+
+#[test]
+fn tps_error_offset_test() {
+    let offset = |tps: &str| {
+        <Position<5>>::parse_tps(tps, Komi::default())
+            .unwrap_err()
+            .offset
+    };
+    assert_eq!(offset("x5/x5/x5/x5/x5 3 1"), 15);
+    assert_eq!(offset("x5/x5/x5/x5/x5 1 0"), 17);
+    assert_eq!(offset("x5/x5/x5/x5/x5 1 1 x"), 19);
+    assert_eq!(offset("x5/x5/x5/x5/x5 1"), 16);
+    assert_eq!(offset("x5/x5/x5/x5 1 1"), 0);
+    assert_eq!(offset("x5/x5/x2,1A,x2/x5/x5 1 1"), 10);
+    assert_eq!(offset("x5/x5/x5/x4/x5 1 1"), 11);
+    assert_eq!(offset("x5/x5/x5/x5,1/x5 1 1"), 12);
+    assert_eq!(offset("  x5/x5/x5/x5/x5 1 y"), 19);
+
+    assert!(<Position<5>>::parse_tps("x5/x5/x2,12C,x2/x5/x5 2 3", Komi::default()).is_ok());
+
+    // The offset can be recovered from the error returned by `from_fen`
+    let err = <Position<5>>::from_fen("x5/x5/x5/x5/x5 3 1").unwrap_err();
+    let tps_error = error::Error::source(&err)
+        .and_then(|source| source.downcast_ref::<TpsError>())
+        .unwrap();
+    assert_eq!(tps_error.offset, 15);
+}
+
+#[test]
+fn display_board_diagram_test() {
+    let position = <Position<5>>::from_fen("1,x4/x,2S,x3/x2,1C,x2/x5/x5 2 2").unwrap();
+    let diagram = position.to_string();
+    let board_lines: Vec<&str> = diagram.lines().take(6).collect();
+    assert_eq!(
+        board_lines,
+        [
+            "5 w . . . .",
+            "4 . B . . .",
+            "3 . . C . .",
+            "2 . . . . .",
+            "1 . . . . .",
+            "  a b c d e"
+        ]
+    );
+    assert!(diagram.contains("Stones left: 20/20, capstones left: 0/1."));
+    assert!(!diagram.contains("Hash"));
+}
+
+#[test]
+fn from_san_moves_test() {
+    let komi = Komi::from_half_komi(4).unwrap();
+    let position = <Position<5>>::from_san_moves(komi, &["a1", "e5", "Cc3", "a1+"]).unwrap();
+    let mut expected_position = <Position<5>>::start_position_with_komi(komi);
+    do_moves_and_check_validity(&mut expected_position, &["a1", "e5", "Cc3", "a1+"]);
+    assert_eq!(position, expected_position);
+    assert_eq!(position.komi(), komi);
+
+    // Unparsable move
+    assert!(<Position<5>>::from_san_moves(komi, &["a1", "f6"]).is_err());
+    // Occupied square
+    assert!(<Position<5>>::from_san_moves(komi, &["a1", "a1"]).is_err());
+    // Capstone placement on the first move
+    assert!(<Position<5>>::from_san_moves(komi, &["Ca1"]).is_err());
+}
+
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+#[test]
+fn serde_position_roundtrip_test() {
+    let mut position = <Position<5>>::start_position_with_komi(Komi::from_half_komi(4).unwrap());
+    position.set_repetition_draw_count(None).unwrap();
+    do_moves_and_check_validity(
+        &mut position,
+        &["a1", "e5", "Cc3", "d3", "c3>", "e4", "2d3<", "e4-"],
+    );
+    assert!(position.reversible_moves_count() > 0);
+
+    let json = serde_json::to_string(&position).unwrap();
+    let deserialized: Position<5> = serde_json::from_str(&json).unwrap();
+    assert_eq!(position, deserialized);
+    assert_eq!(position.moves(), deserialized.moves());
+    assert_eq!(position.hash(), deserialized.hash());
+    assert_eq!(
+        deserialized.hash(),
+        deserialized.zobrist_hash_from_scratch()
+    );
+    assert_eq!(position.repetition_count(), deserialized.repetition_count());
+    assert_eq!(deserialized.repetition_draw_count(), None);
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["hash"] = serde_json::json!(position.hash() ^ 1);
+    assert!(serde_json::from_value::<Position<5>>(value).is_err());
+}
+
+#[test]
+fn fcd_for_move_test() {
+    let position = <Position<5>>::from_fen("x5/x5/x2,1C,2S,x/x5/1,2,x3 1 10").unwrap();
+    let fcd =
+        |move_string: &str| position.fcd_for_move(position.move_from_san(move_string).unwrap());
+
+    assert_eq!(fcd("e1"), 1);
+    assert_eq!(fcd("Se1"), 0);
+    // White's flat leaves a1, but covers Black's flat on b1
+    assert_eq!(fcd("a1>"), 1);
+    // The capstone flattens the wall, which does not change the flat count
+    assert_eq!(fcd("c3>"), 0);
+
+    let position = <Position<5>>::start_position();
+    assert_eq!(
+        position.fcd_for_move(position.move_from_san("a1").unwrap()),
+        -1
+    );
+}
+
+#[test]
+fn road_winner_detailed_test() {
+    let cases = [
+        ("x5/x5/x5/2,2,2,2,x/x5 2 6", None, None),
+        (
+            "x5/x5/x5/2,2,2,2,x/1,1,1,1,1 2 6",
+            Some(RoadOutcome::SingleRoad(Color::White)),
+            Some(WhiteWin),
+        ),
+        (
+            "x5/x5/x5/2,2,2,2,2/1,1,1,1,1 2 7",
+            Some(RoadOutcome::DoubleRoad(Color::White)),
+            Some(WhiteWin),
+        ),
+        (
+            "x5/x5/x5/2,2,2,2,2/1,1,1,1,1 1 8",
+            Some(RoadOutcome::DoubleRoad(Color::Black)),
+            Some(BlackWin),
+        ),
+        (
+            "x5/x5/x5/2,2,2,2,x/1,1,1,1,1 1 7",
+            Some(RoadOutcome::Suicide(Color::White)),
+            Some(WhiteWin),
+        ),
+    ];
+    for (tps, road_outcome, game_result) in cases {
+        let position = <Position<5>>::from_fen(tps).unwrap();
+        assert_eq!(position.road_winner_detailed(), road_outcome, "{}", tps);
+        assert_eq!(position.game_result(), game_result, "{}", tps);
+    }
+}
+
+#[test]
+fn undo_last_move_test() {
+    let komi = Komi::from_half_komi(4).unwrap();
+    let mut position =
+        <Position<5>>::from_fen_with_komi("x5/x5/x,2S,x3/x,1C,1,x2/x5 1 5", komi).unwrap();
+
+    let mut history = vec![position.clone()];
+    // Includes a capstone spread onto a flat, and one that flattens a wall
+    for move_string in ["b2>", "a1", "c2<", "e5", "b2+", "e4"] {
+        do_moves_and_check_validity(&mut position, &[move_string]);
+        history.push(position.clone());
+    }
+
+    while let Some(expected) = history.pop() {
+        assert_eq!(position, expected);
+        assert_eq!(position.hash(), expected.hash());
+        assert_eq!(position.moves(), expected.moves());
+        assert_eq!(position.komi(), komi);
+        assert_eq!(position.undo_last_move(), !history.is_empty());
+    }
+    assert_eq!(
+        position.to_fen(),
+        "x,x,x,x,x/x,x,x,x,x/x,2S,x,x,x/x,1C,1,x,x/x,x,x,x,x 1 5"
+    );
+}
+
+#[test]
+fn critical_squares_for_both_colors_test() {
+    let position =
+        <Position<5>>::from_fen("x2,2,x2/x2,2,x2/1,1,x,1,1/x2,2,x2/x2,2,x2 1 5").unwrap();
+    let c3 = Square::parse_square("c3").unwrap();
+    assert_eq!(position.critical_squares(), (vec![c3], vec![c3]));
+
+    let position = <Position<5>>::start_position();
+    assert_eq!(position.critical_squares(), (vec![], vec![]));
+}
+
+#[test]
+fn hash_detects_transpositions_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "c3", "d3", "b2", "d4"]);
+    let mut transposed_position = <Position<5>>::start_position();
+    do_moves_and_check_validity(
+        &mut transposed_position,
+        &["a1", "e5", "b2", "d3", "c3", "d4"],
+    );
+
+    assert_eq!(position.hash(), transposed_position.hash());
+    assert_eq!(position.hash(), position.zobrist_hash_from_scratch());
+
+    do_moves_and_check_validity(&mut transposed_position, &["e4"]);
+    assert_ne!(position.hash(), transposed_position.hash());
+}
+
+#[test]
+fn is_placement_phase_test() {
+    let mut position = <Position<5>>::start_position();
+    assert!(position.is_placement_phase());
+    do_moves_and_check_validity(&mut position, &["a1"]);
+    assert!(position.is_placement_phase());
+    do_moves_and_check_validity(&mut position, &["e5"]);
+    assert!(!position.is_placement_phase());
+    assert_eq!(
+        position.top_stones()[Square::parse_square("a1").unwrap()],
+        Some(BlackFlat)
+    );
+}
+
+#[test]
+fn direction_to_char_test() {
+    let chars: String = Direction::all().iter().map(|dir| dir.to_char()).collect();
+    assert_eq!(chars, "+<>-");
+    for direction in Direction::all() {
+        assert_eq!(Direction::parse(direction.to_char()), Some(direction));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::tune::play_match::MatchResult;
+
+#[test]
+fn even_match_elo_test() {
+    let result = MatchResult {
+        wins: 30,
+        draws: 40,
+        losses: 30,
+    };
+    let (elo_diff, elo_error) = result.elo_diff();
+    assert!(elo_diff.abs() < 0.001);
+    assert!(elo_error > 0.0 && elo_error < 100.0);
+    assert!((result.likelihood_of_superiority() - 0.5).abs() < 0.001);
+}
+
+#[test]
+fn winning_match_elo_test() {
+    let result = MatchResult {
+        wins: 60,
+        draws: 20,
+        losses: 20,
+    };
+    let (elo_diff, elo_error) = result.elo_diff();
+    assert!((elo_diff - 147.2).abs() < 0.1, "Got {} Elo", elo_diff);
+    assert!(elo_error > 0.0 && elo_error < elo_diff);
+    assert!(result.likelihood_of_superiority() > 0.99);
+
+    let reversed = MatchResult {
+        wins: 20,
+        draws: 20,
+        losses: 60,
+    };
+    assert!((reversed.elo_diff().0 + elo_diff).abs() < 0.01);
+    assert!(reversed.likelihood_of_superiority() < 0.01);
+}
+
+#[test]
+fn no_games_elo_test() {
+    let result = MatchResult::default();
+    assert_eq!(result.elo_diff().0, 0.0);
+    assert_eq!(result.likelihood_of_superiority(), 0.5);
+}
@@ -1,10 +1,11 @@
-use crate::position::{Move, Position};
+use crate::position::{Komi, Move, Position};
 use crate::search::MctsSetting;
-use crate::search::{self, MonteCarloTree};
+use crate::search::{self, MonteCarloTree, PuzzleVerdict, StopReason};
 use crate::tests::TestPosition;
 use board_game_traits::Position as PositionTrait;
 use half::f16;
 use pgn_traits::PgnPosition;
+use std::cell::Cell;
 use std::time::Duration;
 
 #[test]
@@ -29,6 +30,17 @@ fn exclude_moves_test() {
     );
 }
 
+#[test]
+fn growable_arena_search_test() {
+    let settings = MctsSetting::default().growable_arena();
+    let mut tree = MonteCarloTree::new(<Position<5>>::start_position(), settings);
+
+    for _ in 0..10_000 {
+        tree.select().unwrap();
+    }
+    assert!(tree.best_move().is_some());
+}
+
 #[test]
 fn play_on_low_time() {
     let time = Duration::from_millis(5);
@@ -63,6 +75,58 @@ fn white_can_win_in_one_move_test() {
     test_position.plays_correct_move_short_prop::<5>(&["a2", "Ca2"]);
 }
 
+#[test]
+fn mcts_parallel_test() {
+    let mut position = <Position<5>>::start_position();
+    for move_string in ["b4", "c2", "d2", "c4", "b2", "d4", "e2", "c3"] {
+        position.do_move(position.move_from_san(move_string).unwrap());
+    }
+    let (best_move, score) = search::mcts_parallel(position.clone(), 1000, 4);
+    assert!(
+        ["a2", "Ca2"].contains(&best_move.to_string().as_str()),
+        "Expected winning move, got {}",
+        best_move
+    );
+    assert!(score > 0.9, "Expected winning score, got {}", score);
+
+    // The Dirichlet noise is seeded, so repeated searches give identical results
+    assert_eq!(
+        search::mcts_parallel(position.clone(), 300, 3),
+        search::mcts_parallel(position, 300, 3)
+    );
+}
+
+#[test]
+fn analyze_test() {
+    let position = <Position<5>>::start_position();
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+
+    let move_stats = search::analyze(position, 500).unwrap();
+    assert_eq!(move_stats.len(), legal_moves.len());
+    assert!(move_stats
+        .windows(2)
+        .all(|window| window[0].visits >= window[1].visits));
+    // `MonteCarloTree::new` visits the root twice more, and the first visit only creates the root node
+    assert_eq!(move_stats.iter().map(|stat| stat.visits).sum::<u32>(), 501);
+    for move_stat in move_stats.iter() {
+        assert!(legal_moves.contains(&move_stat.mv));
+        assert!(move_stat.policy > 0.0);
+    }
+}
+
+#[test]
+fn mcts_with_progress_test() {
+    let position = <Position<5>>::start_position();
+    let mut progress = vec![];
+    let (mv, score) = search::mcts_with_progress(position.clone(), 1000, 300, |visits, tree| {
+        assert_eq!(visits, tree.visits());
+        progress.push(visits);
+    });
+    assert_eq!(progress, vec![300, 600, 900]);
+    assert_eq!((mv, score), search::mcts(position, 1000));
+}
+
 #[test]
 fn black_avoid_loss_in_one_test() {
     let test_position =
@@ -193,3 +257,573 @@ fn best_move_temperature_test() {
     assert!(b1_selected > 75);
     assert!(b1_selected < 150);
 }
+
+#[test]
+fn mcts_training_with_value_test() {
+    // White has a road win on the a-file
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let (move_scores, value) = search::mcts_training_with_value(
+        position.clone(),
+        &search::TimeControl::FixedNodes(1000),
+        MctsSetting::default(),
+    );
+    assert_eq!(move_scores.len(), {
+        let mut moves = vec![];
+        position.generate_moves(&mut moves);
+        moves.len()
+    });
+    assert!(value > 0.9, "Expected winning value, got {}", value);
+}
+
+#[test]
+fn is_forced_move_test() {
+    // Black threatens to win on b1, both by placing there and with c1<.
+    // A flat on b1 can be captured by c1<, so only a wall holds
+    let position =
+        <Position<5>>::from_fen("x,2,x,1,1C/x,2,x2,1/x,2,x,1,x/x,2,2S,1,1/x2,2,x2 1 7").unwrap();
+    assert_eq!(
+        position.is_forced_move(20_000),
+        Some(position.move_from_san("Sb1").unwrap())
+    );
+
+    assert_eq!(<Position<5>>::start_position().is_forced_move(1000), None);
+}
+
+#[test]
+fn root_move_stats_test() {
+    let position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+    let mut tree = MonteCarloTree::new(
+        position.clone(),
+        MctsSetting::default().arena_size_for_nodes(1000),
+    );
+    for _ in 0..1000 {
+        tree.select().unwrap();
+    }
+    let move_stats = tree.root_move_stats();
+
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+    assert_eq!(move_stats.len(), legal_moves.len());
+    assert!(move_stats
+        .windows(2)
+        .all(|pair| pair[0].visits >= pair[1].visits));
+    assert_eq!(
+        Some((move_stats[0].mv, move_stats[0].mean_action_value)),
+        tree.best_move()
+    );
+    for move_stat in move_stats.iter() {
+        assert_eq!(move_stat.pv.first(), Some(&move_stat.mv));
+    }
+    assert_eq!(move_stats[0].pv, tree.pv().collect::<Vec<_>>());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn analysis_to_json_test() {
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let move_stats = vec![search::MoveStat {
+        mv: position.move_from_san("a1").unwrap(),
+        visits: 100,
+        mean_action_value: 1.0,
+        policy: 0.5,
+        pv: vec![position.move_from_san("a1").unwrap()],
+    }];
+    let json = search::analysis_to_json(&position, &move_stats);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["tps"], "1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5");
+    assert_eq!(value["moves"][0]["move"], "a1");
+    assert_eq!(value["moves"][0]["visits"], 100);
+    assert_eq!(value["moves"][0]["eval"], 1.0);
+    assert_eq!(value["moves"][0]["policy"], 0.5);
+    assert_eq!(value["moves"][0]["pv"], serde_json::json!(["a1"]));
+}
+
+#[test]
+fn komi_setting_test() {
+    // Filling the board with d1 wins 8-7 on flats, unless komi is at least 1
+    let position = <Position<4>>::from_fen("1,2,1,2/2,1,2,1/1,2,1,2/2,1,2S,x 1 9").unwrap();
+    let fill_move = position.move_from_san("d1").unwrap();
+    let search = |settings: MctsSetting<4>| {
+        let mut tree = MonteCarloTree::new(position.clone(), settings.arena_size_for_nodes(1000));
+        for _ in 0..1000 {
+            tree.select().unwrap();
+        }
+        tree.best_move().unwrap()
+    };
+
+    let (best_move, score) = search(MctsSetting::default().komi(Komi::from_half_komi(0).unwrap()));
+    assert_eq!(best_move, fill_move);
+    assert!(score > 0.9, "Expected winning score, got {}", score);
+
+    let (best_move, _) = search(MctsSetting::default().komi(Komi::from_half_komi(4).unwrap()));
+    assert_ne!(best_move, fill_move);
+
+    // The evaluation komi can be set separately from the komi that decides the game result
+    let (_, score) = search(
+        MctsSetting::default()
+            .komi(Komi::from_half_komi(2).unwrap())
+            .eval_komi(Komi::from_half_komi(4).unwrap()),
+    );
+    assert!(score < 0.9, "Expected non-winning score, got {}", score);
+}
+
+#[test]
+fn analysis_stream_test() {
+    let position = <Position<5>>::start_position();
+    let mut tree = MonteCarloTree::new(position, MctsSetting::default());
+    let results: Vec<(Move<5>, f32, u32)> =
+        tree.analysis_stream(Duration::from_millis(200)).collect();
+    assert!(!results.is_empty());
+    assert!(results.windows(2).all(|pair| pair[0].2 < pair[1].2));
+
+    let (last_move, last_score, last_visits) = *results.last().unwrap();
+    assert_eq!(tree.best_move(), Some((last_move, last_score)));
+    assert_eq!(tree.visits(), last_visits);
+}
+
+#[test]
+fn search_batch_size_test() {
+    let count_callbacks = |settings: MctsSetting<5>| {
+        let mut tree = MonteCarloTree::new(<Position<5>>::start_position(), settings);
+        let callbacks = Cell::new(0);
+        tree.search_for_time(Duration::from_millis(100), |_| {
+            callbacks.set(callbacks.get() + 1)
+        });
+        callbacks.get()
+    };
+    let small_batch_callbacks = count_callbacks(MctsSetting::default().search_batch_size(10, 1.0));
+    let large_batch_callbacks =
+        count_callbacks(MctsSetting::default().search_batch_size(5000, 1.0));
+    assert!(
+        small_batch_callbacks > large_batch_callbacks,
+        "{} callbacks with small batches, {} with large batches",
+        small_batch_callbacks,
+        large_batch_callbacks
+    );
+}
+
+#[test]
+fn search_until_confident_test() {
+    // White's only win is moving the capstone onto a1
+    let position = <Position<5>>::from_fen("1,x3,2/1,x3,2/1,2,x3/1,2,x3/2,1C,x3 1 6").unwrap();
+    let mut tree = MonteCarloTree::new(position, MctsSetting::default());
+    assert!(tree.search_until_confident(0.9, 100_000));
+    assert_eq!(
+        tree.best_move().unwrap().0,
+        Move::from_string("b1<").unwrap()
+    );
+    assert!(tree.visits() < 100_000);
+
+    // Confidence cannot be above 1, so the search always runs to the node limit
+    let mut tree = MonteCarloTree::new(<Position<5>>::start_position(), MctsSetting::default());
+    assert!(!tree.search_until_confident(1.1, 1000));
+    assert!(tree.visits() >= 1000);
+}
+
+#[test]
+fn select_n_test() {
+    let settings = MctsSetting::default().arena_size_for_nodes(1000);
+    let mut tree = MonteCarloTree::new(<Position<5>>::start_position(), settings);
+    assert_eq!(tree.select_n(100).unwrap(), 100);
+    assert_eq!(tree.visits(), 100);
+
+    // A tiny arena runs out of memory, which stops the search early
+    let settings = MctsSetting::default().arena_size(10_000);
+    let mut tree = MonteCarloTree::new(<Position<5>>::start_position(), settings);
+    let completed = tree.select_n(1_000_000).unwrap();
+    assert!(completed < 1_000_000);
+    assert_eq!(tree.visits() as u64, completed);
+}
+
+#[test]
+fn reroot_one_test() {
+    let settings = MctsSetting::default().arena_size_for_nodes(1000);
+    let mut tree = MonteCarloTree::new(<Position<5>>::start_position(), settings.clone());
+    tree.select_n(500).unwrap();
+    let best_move_stat = tree.root_move_stats()[0].clone();
+
+    let mut tree = tree.reroot_one(best_move_stat.mv).unwrap();
+    assert_eq!(tree.visits(), best_move_stat.visits);
+    assert!(tree.pv().eq(best_move_stat.pv.iter().skip(1).copied()));
+    assert_eq!(tree.select_n(100).unwrap(), 100);
+    assert_eq!(tree.visits(), best_move_stat.visits + 100);
+
+    // Only a single child of the root has been expanded in a new tree
+    let tree = MonteCarloTree::new(<Position<5>>::start_position(), settings);
+    let unexpanded_move = tree
+        .root_move_stats()
+        .into_iter()
+        .find(|move_stat| move_stat.visits == 0)
+        .unwrap()
+        .mv;
+    assert!(tree.reroot_one(unexpanded_move).is_none());
+}
+
+#[test]
+fn pv_detailed_test() {
+    let settings = MctsSetting::default().arena_size_for_nodes(1000);
+    let mut tree = MonteCarloTree::new(<Position<5>>::start_position(), settings);
+    tree.select_n(500).unwrap();
+
+    let pv_detailed: Vec<(Move<5>, u32, f32)> = tree.pv_detailed().collect();
+    assert!(!pv_detailed.is_empty());
+    assert!(tree.pv().eq(pv_detailed.iter().map(|(mv, _, _)| *mv)));
+
+    let best_move_stat = &tree.root_move_stats()[0];
+    assert_eq!(pv_detailed[0].0, best_move_stat.mv);
+    assert_eq!(pv_detailed[0].1, best_move_stat.visits);
+    assert_eq!(pv_detailed[0].2, best_move_stat.policy);
+
+    for window in pv_detailed.windows(2) {
+        assert!(window[1].1 < window[0].1);
+    }
+    for (_, _, policy) in pv_detailed {
+        assert!((0.0..=1.0).contains(&policy));
+    }
+}
+
+#[test]
+fn arena_size_bytes_test() {
+    let bytes = 1 << 20;
+    let settings = MctsSetting::default().arena_size_bytes(bytes);
+    let mut tree = MonteCarloTree::new(<Position<6>>::start_position(), settings);
+    let completed = tree.select_n(1_000_000).unwrap();
+    assert!(completed < 1_000_000);
+    assert!(tree.mem_usage() <= bytes);
+    // The liberal estimate from `arena_size_for_nodes` is a lower bound
+    assert!(completed as usize >= bytes / (48 * 6 * 6));
+
+    // Budgets that are too large for the arena are clamped
+    assert_eq!(
+        MctsSetting::<6>::default().arena_size_bytes(usize::MAX),
+        MctsSetting::default().arena_size(u32::MAX - 2)
+    );
+}
+
+#[test]
+fn evaluate_both_to_move_test() {
+    // White has a road win on the a-file, but Black can block it if given the move
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let (score, passed_score) = position.evaluate_both_to_move(1000);
+    assert!(score > 0.9, "Expected winning score, got {}", score);
+    assert!(score > passed_score, "{} <= {}", score, passed_score);
+}
+
+#[test]
+fn verify_puzzle_test() {
+    // White's only win is moving the capstone onto a1
+    let position = <Position<5>>::from_fen("1,x3,2/1,x3,2/1,2,x3/1,2,x3/2,1C,x3 1 6").unwrap();
+    let solve = |san: &str| position.verify_puzzle(&[position.move_from_san(san).unwrap()], 1000);
+    assert_eq!(solve("b1<"), PuzzleVerdict::UniqueSolution);
+    assert_eq!(solve("a2-"), PuzzleVerdict::SolutionNotBest { ply: 0 });
+    assert_eq!(solve("c1"), PuzzleVerdict::SolutionNotBest { ply: 0 });
+
+    // Both a flat and the capstone on a1 complete the road
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    assert_eq!(
+        position.verify_puzzle(&[position.move_from_san("a1").unwrap()], 1000),
+        PuzzleVerdict::MultipleSolutions { ply: 0 }
+    );
+}
+
+/// White's only win is c2, threatening both d1 and d2
+const EXACT_PUZZLE_TPS: &str = "2,2,x2/x4/1,1,x2/x,1S,1,x 1 4";
+
+fn verify_exact_puzzle(move_strings: &[&str]) -> PuzzleVerdict {
+    let position = <Position<4>>::from_fen(EXACT_PUZZLE_TPS).unwrap();
+    let solution: Vec<Move<4>> = move_strings
+        .iter()
+        .map(|move_string| Move::from_string(move_string).unwrap())
+        .collect();
+    position.verify_puzzle(&solution, 1000)
+}
+
+#[test]
+fn verify_puzzle_exact_test() {
+    assert_eq!(
+        verify_exact_puzzle(&["c2", "d2", "d1"]),
+        PuzzleVerdict::UniqueSolution
+    );
+    assert_eq!(
+        verify_exact_puzzle(&["c2", "d1", "d2"]),
+        PuzzleVerdict::UniqueSolution
+    );
+    // d2 only threatens c2, which a wall blocks
+    assert_eq!(
+        verify_exact_puzzle(&["d2", "c3", "c2"]),
+        PuzzleVerdict::SolutionNotBest { ply: 0 }
+    );
+}
+
+#[test]
+fn verify_puzzle_illegal_move_test() {
+    assert_eq!(
+        verify_exact_puzzle(&["a2"]),
+        PuzzleVerdict::IllegalMove { ply: 0 }
+    );
+    assert_eq!(
+        verify_exact_puzzle(&["c2+"]),
+        PuzzleVerdict::IllegalMove { ply: 0 }
+    );
+    assert_eq!(
+        verify_exact_puzzle(&["c2", "d2", "d2"]),
+        PuzzleVerdict::IllegalMove { ply: 2 }
+    );
+    // The game is over after d1
+    assert_eq!(
+        verify_exact_puzzle(&["c2", "d2", "d1", "a1"]),
+        PuzzleVerdict::IllegalMove { ply: 3 }
+    );
+}
+
+#[test]
+fn mcts_symmetry_averaged_test() {
+    let position = <Position<6>>::from_fen("2,x5/x6/x,1,2,x3/x6/x6/x5,1 1 3").unwrap();
+    let (mv, score) = search::mcts_symmetry_averaged(position.clone(), 1000);
+    for (symmetry, rotation) in position.symmetries().into_iter().enumerate() {
+        let (rotated_move, rotated_score) = search::mcts_symmetry_averaged(rotation, 1000);
+        assert_eq!(rotated_move, mv.apply_symmetry(symmetry));
+        assert_eq!(rotated_score, score);
+    }
+}
+
+#[test]
+fn analyze_komi_sweep_test() {
+    let position = <Position<5>>::start_position();
+    let komis: Vec<Komi> = [0, 3, 4, 8]
+        .into_iter()
+        .map(|half_komi| Komi::from_half_komi(half_komi).unwrap())
+        .collect();
+    let results = search::analyze_komi_sweep(position.clone(), &komis, 500);
+    assert_eq!(results.len(), komis.len());
+
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+    for ((komi, mv, score), expected_komi) in results.into_iter().zip(komis) {
+        assert_eq!(komi, expected_komi);
+        assert!(legal_moves.contains(&mv));
+        assert!((0.0..=1.0).contains(&score));
+    }
+}
+
+#[test]
+fn interpolated_params_test() {
+    let params_1komi = <Position<5>>::value_params(Komi::from_half_komi(2).unwrap());
+    for ((param, param_0komi), param_2komi) in params_1komi
+        .iter()
+        .zip(<Position<5>>::value_params_0komi())
+        .zip(<Position<5>>::value_params_2komi())
+    {
+        assert!((param - (param_0komi + param_2komi) / 2.0).abs() < 0.0001);
+    }
+    assert_eq!(
+        <Position<5>>::policy_params(Komi::from_half_komi(4).unwrap()),
+        <Position<5>>::policy_params_2komi()
+    );
+}
+
+#[test]
+fn explain_selection_test() {
+    let position = <Position<5>>::start_position();
+    let mut tree = MonteCarloTree::new(
+        position.clone(),
+        MctsSetting::default().arena_size_for_nodes(1000),
+    );
+    // `MonteCarloTree::new` already expands the root, so there is always a move to select
+    assert_eq!(tree.explain_selection().len(), 1);
+
+    for _ in 0..1000 {
+        tree.select().unwrap();
+    }
+    let path = tree.explain_selection();
+    assert!(!path.is_empty());
+    assert_eq!(tree.visits(), 1000);
+    assert_eq!(tree.explain_selection(), path);
+
+    let mut position = position;
+    for (mv, mean_action_value, policy, _) in path {
+        let mut legal_moves = vec![];
+        position.generate_moves(&mut legal_moves);
+        assert!(legal_moves.contains(&mv));
+        assert!((0.0..=1.0).contains(&mean_action_value));
+        assert!((0.0..=1.0).contains(&policy));
+        position.do_move(mv);
+    }
+}
+
+#[test]
+fn engine_fingerprint_test() {
+    assert_eq!(search::engine_fingerprint(), search::engine_fingerprint());
+    assert!(search::engine_fingerprint().starts_with("tiltak-"));
+
+    let default_fingerprint = MctsSetting::<5>::default().fingerprint();
+    assert_eq!(
+        default_fingerprint,
+        MctsSetting::<5>::default().fingerprint()
+    );
+    assert_ne!(
+        default_fingerprint,
+        MctsSetting::<5>::default()
+            .add_value_params(<Position<5>>::value_params_2komi())
+            .fingerprint()
+    );
+    assert_ne!(
+        default_fingerprint,
+        MctsSetting::<5>::default()
+            .add_search_params(vec![1.0, 2200.0, 0.61].into_boxed_slice())
+            .fingerprint()
+    );
+
+    let position = <Position<5>>::start_position();
+    let a1 = position.move_from_san("a1").unwrap();
+    let b1 = position.move_from_san("b1").unwrap();
+    let fingerprints = [
+        default_fingerprint.clone(),
+        MctsSetting::<5>::default().add_dirichlet(0.2).fingerprint(),
+        MctsSetting::<5>::default()
+            .add_dirichlet_seeded(0.2, 0)
+            .fingerprint(),
+        MctsSetting::<5>::default()
+            .add_dirichlet_seeded(0.2, 1)
+            .fingerprint(),
+        MctsSetting::<5>::default()
+            .exclude_moves(vec![a1])
+            .fingerprint(),
+        MctsSetting::<5>::default()
+            .exclude_moves(vec![b1])
+            .fingerprint(),
+        MctsSetting::<5>::default()
+            .exclude_moves(vec![a1, b1])
+            .fingerprint(),
+        MctsSetting::<5>::default()
+            .search_batch_size(100, 2.0_f32.powf(0.125))
+            .fingerprint(),
+        MctsSetting::<5>::default()
+            .search_batch_size(50, 2.0)
+            .fingerprint(),
+        MctsSetting::<5>::default().growable_arena().fingerprint(),
+        MctsSetting::<5>::default()
+            .arena_size(1 << 20)
+            .fingerprint(),
+    ];
+    for (i, fingerprint) in fingerprints.iter().enumerate() {
+        for other_fingerprint in fingerprints.iter().skip(i + 1) {
+            assert_ne!(fingerprint, other_fingerprint);
+        }
+    }
+}
+
+#[test]
+fn ignore_repetition_draws_test() {
+    // White can win with a5, but the players repeat the position instead
+    let mut position = <Position<5>>::from_fen("x2,2,x2/1,x4/1,x4/1,x4/1,x3,1 1 10").unwrap();
+    for move_string in ["e1+", "c5>", "e2-", "d5<", "e1+", "c5>", "e2-", "d5<"] {
+        position.do_move(position.move_from_san(move_string).unwrap());
+    }
+    assert_eq!(
+        position.game_result(),
+        Some(board_game_traits::GameResult::Draw)
+    );
+
+    let mut tree = MonteCarloTree::new(
+        position.clone(),
+        MctsSetting::default().arena_size_for_nodes(1000),
+    );
+    for _ in 0..100 {
+        tree.select().unwrap();
+    }
+    assert!(tree.best_move().is_none());
+
+    let settings = MctsSetting::default()
+        .arena_size_for_nodes(1000)
+        .ignore_repetition_draws(true);
+    let mut tree = MonteCarloTree::new(position.clone(), settings);
+    for _ in 0..1000 {
+        tree.select().unwrap();
+    }
+    let (best_move, score) = tree.best_move().unwrap();
+    assert_eq!(best_move, position.move_from_san("a5").unwrap());
+    assert!(score > 0.9, "Expected winning score, got {}", score);
+}
+
+#[test]
+fn seeded_dirichlet_noise_is_deterministic_test() {
+    let position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+    let root_stats = |seed: u64| {
+        let settings = MctsSetting::default()
+            .arena_size_for_nodes(1000)
+            .add_dirichlet_seeded(0.2, seed);
+        let mut tree = MonteCarloTree::new(position.clone(), settings);
+        tree.select_n(1000).unwrap();
+        tree.root_move_stats()
+            .into_iter()
+            .map(|move_stat| (move_stat.mv, move_stat.visits, move_stat.policy))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(root_stats(42), root_stats(42));
+    assert_ne!(root_stats(42), root_stats(43));
+}
+
+#[test]
+fn win_percentage_to_cp_test() {
+    for cp in [-5.0, -1.5, -0.3, 0.0, 0.2, 1.0, 4.0] {
+        let round_trip = search::win_percentage_to_cp(search::cp_to_win_percentage(cp));
+        assert!(
+            (round_trip - cp).abs() < 0.001,
+            "{} became {}",
+            cp,
+            round_trip
+        );
+    }
+    assert!(search::win_percentage_to_cp(0.0).is_finite());
+    assert!(search::win_percentage_to_cp(1.0).is_finite());
+    assert!(search::win_percentage_to_cp(0.0) < -1000.0);
+    assert!(search::win_percentage_to_cp(1.0) > 1000.0);
+}
+
+#[test]
+fn play_move_time_verbose_test() {
+    let position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+
+    let (_, _, report) = search::play_move_time_verbose(
+        position.clone(),
+        Duration::from_millis(5),
+        MctsSetting::default().arena_size_for_nodes(100_000),
+    );
+    assert_eq!(report.stop_reason, StopReason::OutOfTime);
+    assert_eq!(report.node_ratio, None);
+    assert!(report.visits > 0);
+
+    let (mv, score, report) = search::play_move_time_verbose(
+        position.clone(),
+        Duration::from_millis(200),
+        MctsSetting::default().arena_size_for_nodes(1_000_000),
+    );
+    assert!(position.move_is_legal(mv));
+    assert!((0.0..=1.0).contains(&score));
+    assert_ne!(report.stop_reason, StopReason::Error);
+    if report.stop_reason == StopReason::DominantMove {
+        assert!(report.node_ratio.is_some());
+        assert!(report.time_ratio < 1.0);
+    }
+}
+
+#[test]
+fn search_info_test() {
+    let position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+    let mut tree = MonteCarloTree::new(
+        position.clone(),
+        MctsSetting::default().arena_size_for_nodes(1000),
+    );
+    tree.select_n(1000).unwrap();
+
+    let info = tree.info();
+    assert_eq!(info.visits, tree.visits());
+    assert_eq!(info.mean_action_value, tree.mean_action_value());
+    assert_eq!(info.moves, tree.root_move_stats());
+    assert_eq!(
+        Some((info.moves[0].mv, info.moves[0].mean_action_value)),
+        tree.best_move()
+    );
+}
@@ -3,6 +3,8 @@ mod blunder_tests;
 mod board_generic_tests;
 mod board_tests;
 mod komi_policy_tests;
+#[cfg(feature = "constant-tuning")]
+mod match_result_tests;
 mod mcts_tests;
 mod move_gen_5s_tests;
 mod move_gen_6s_tests;
@@ -11,6 +13,8 @@ mod policy_tests;
 mod ptn_tests;
 mod tactics_tests_5s;
 mod tactics_tests_6s;
+#[cfg(feature = "constant-tuning")]
+mod training_tests;
 
 use crate::evaluation::parameters::{IncrementalPolicy, PolicyApplier};
 use crate::position::{Komi, Move, Position};
@@ -55,6 +59,17 @@ impl TestPosition {
         position
     }
 
+    /// Copies of this test position, one for each of the given komis
+    pub fn with_komi_range(&self, komis: &[Komi]) -> Vec<Self> {
+        komis
+            .iter()
+            .map(|komi| Self {
+                komi: *komi,
+                ..self.clone()
+            })
+            .collect()
+    }
+
     pub fn plays_correct_move_long_prop<const S: usize>(&self, correct_moves: &[&str]) {
         self.plays_correct_move_prop::<S>(correct_moves, 50_000)
     }
@@ -81,6 +96,55 @@ impl TestPosition {
         );
     }
 
+    /// Like `plays_correct_move_short_prop`, but for every komi in `komis`.
+    /// Komis without their own parameters are searched with interpolated parameters.
+    pub fn plays_correct_move_komi_range_prop<const S: usize>(
+        &self,
+        komis: &[Komi],
+        correct_moves: &[&str],
+    ) {
+        for test_position in self.with_komi_range(komis) {
+            let position: Position<S> = test_position.position();
+            let candidate_moves = check_candidate_moves(&position, correct_moves);
+
+            let (komi, best_move, score) =
+                search::analyze_komi_sweep(position.clone(), &[test_position.komi], 10_000)[0];
+
+            assert!(
+                candidate_moves.contains(&best_move),
+                "{} didn't play one of the correct moves {:?} with {} komi, {} {:.1}% played instead in position:\n{}\n{:?}",
+                position.side_to_move(),
+                correct_moves,
+                komi,
+                position.move_to_san(&best_move),
+                score * 100.0,
+                position.to_fen(),
+                position
+            );
+        }
+    }
+
+    /// Like `avoid_move_short_prop`, but for every komi in `komis`.
+    /// Komis without their own parameters are searched with interpolated parameters.
+    pub fn avoid_move_komi_range_prop<const S: usize>(&self, komis: &[Komi], avoid_moves: &[&str]) {
+        for test_position in self.with_komi_range(komis) {
+            let position: Position<S> = test_position.position();
+            let candidate_moves = check_candidate_moves(&position, avoid_moves);
+
+            let (komi, best_move, _) =
+                search::analyze_komi_sweep(position.clone(), &[test_position.komi], 10_000)[0];
+
+            assert!(
+                !candidate_moves.contains(&best_move),
+                "{} played wrong move {} with {} komi in position:\n{:?}",
+                position.side_to_move(),
+                position.move_to_san(&best_move),
+                komi,
+                position
+            );
+        }
+    }
+
     pub fn avoid_move_short_prop<const S: usize>(&self, avoid_moves: &[&str]) {
         let position: Position<S> = self.position();
         let candidate_moves = check_candidate_moves(&position, avoid_moves);
@@ -1,5 +1,7 @@
-use crate::position::ExpMove;
+use crate::move_gen::MoveFilter;
 use crate::position::Position;
+use crate::position::{squares_iterator, Role, Square};
+use crate::position::{ExpMove, IllegalMoveReason, Komi, Move};
 use crate::tests::do_moves_and_check_validity;
 use crate::tests::move_gen_generic_tests::perft_check_answers;
 use board_game_traits::Position as PositionTrait;
@@ -26,6 +28,47 @@ fn move_stack_test() {
     );
 }
 
+#[test]
+fn generate_captures_test() {
+    let position = <Position<5>>::from_fen("x5/x,x,2,x,x/x,2C,121C,2S,x/x5/x5 1 10").unwrap();
+    let mut captures = vec![];
+    position.generate_captures(&mut captures);
+
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+    for capture in captures.iter() {
+        assert!(legal_moves.contains(capture));
+    }
+
+    let mut capture_strings: Vec<String> = captures.iter().map(|mv| mv.to_string()).collect();
+    capture_strings.sort();
+    assert_eq!(
+        capture_strings,
+        ["2c3+", "2c3+11", "3c3+", "3c3+12", "3c3+21", "c3+", "c3>"]
+    );
+}
+
+#[test]
+fn crush_move_test() {
+    let position = <Position<5>>::from_fen("x5/x,x,2,x,x/x,2C,121C,2S,1/x5/x5 1 10").unwrap();
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+    let crush_moves: Vec<String> = legal_moves
+        .iter()
+        .filter(|mv| position.is_crush_move(**mv))
+        .map(|mv| mv.to_string())
+        .collect();
+    assert_eq!(crush_moves, ["c3>"]);
+
+    // Spreads onto the wall with flats, or with more than the capstone, are not legal
+    for move_string in ["e3<", "2c3>", "3c3>"] {
+        let mv = position.move_from_san(move_string).unwrap();
+        assert!(!position.move_is_legal(mv), "{} should be illegal", mv);
+        assert!(!legal_moves.contains(&mv));
+    }
+    assert!(!position.is_crush_move(position.move_from_san("c3+").unwrap()));
+}
+
 #[test]
 fn respect_carry_limit_test() {
     let mut position = <Position<5>>::default();
@@ -97,3 +140,297 @@ fn suicide_perf_test() {
     do_moves_and_check_validity(&mut position, &move_strings);
     perft_check_answers(&mut position, &[1, 85, 11_204, 956_736]);
 }
+
+#[test]
+fn declines_available_win_test() {
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    for move_string in ["a1", "Ca1"] {
+        let mv = position.move_from_san(move_string).unwrap();
+        assert!(!position.declines_available_win(mv));
+    }
+    for move_string in ["Sa1", "b4", "a2-"] {
+        let mv = position.move_from_san(move_string).unwrap();
+        assert!(position.declines_available_win(mv), "{}", move_string);
+    }
+
+    // Without a win available, no move declines it
+    let position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+    assert!(legal_moves
+        .iter()
+        .all(|mv| !position.declines_available_win(*mv)));
+}
+
+#[test]
+fn has_winning_move_test() {
+    // Placement win on a1
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    assert!(position.threat_summary().has_immediate_win);
+    assert!(position.has_winning_move());
+
+    // The only win is moving the capstone onto a1
+    let position = <Position<5>>::from_fen("1,x3,2/1,x3,2/1,2,x3/1,2,x3/2,1C,x3 1 6").unwrap();
+    assert!(position.threat_summary().has_immediate_win);
+    assert!(position.has_winning_move());
+
+    assert!(!<Position<5>>::start_position().has_winning_move());
+    let position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+    assert!(!position.has_winning_move());
+}
+
+#[test]
+fn generate_moves_for_square_test() {
+    let position = <Position<5>>::from_fen("x5/x,x,2,x,x/x,2C,121C,2S,1/x5/x5 1 10").unwrap();
+    let moves_for_square = |square: &str| {
+        let mut moves = vec![];
+        position.generate_moves_for_square(Square::parse_square(square).unwrap(), &mut moves);
+        moves
+    };
+
+    // White's capstone is already placed
+    assert_eq!(
+        moves_for_square("a1"),
+        vec![
+            position.move_from_san("a1").unwrap(),
+            position.move_from_san("Sa1").unwrap()
+        ]
+    );
+    assert!(moves_for_square("b3").is_empty());
+    assert!(moves_for_square("d3").is_empty());
+    // The wall on d3 blocks spreading west
+    assert_eq!(moves_for_square("e3").len(), 2);
+    assert!(moves_for_square("c3")
+        .iter()
+        .all(|mv| mv.origin_square() == Square::parse_square("c3").unwrap() && !mv.is_placement()));
+
+    // The moves for all squares together are all the legal moves
+    let mut all_moves = vec![];
+    for square in squares_iterator::<5>() {
+        position.generate_moves_for_square(square, &mut all_moves);
+    }
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+    assert_eq!(all_moves, legal_moves);
+
+    let position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+    let mut moves = vec![];
+    position.generate_moves_for_square(Square::parse_square("a1").unwrap(), &mut moves);
+    assert_eq!(moves.len(), 3);
+}
+
+#[test]
+fn check_move_legal_test() {
+    let position = <Position<5>>::from_fen("1C,x4/x5/x,2,1S,x2/x2,1,x2/1,x4 1 10").unwrap();
+    let check =
+        |move_string: &str| position.check_move_legal(Move::from_string(move_string).unwrap());
+
+    assert_eq!(check("b1"), Ok(()));
+    assert_eq!(check("Sb1"), Ok(()));
+    assert_eq!(check("a1>"), Ok(()));
+    assert_eq!(check("a1"), Err(IllegalMoveReason::SquareOccupied));
+    assert_eq!(check("Cb1"), Err(IllegalMoveReason::NoCapstoneLeft));
+    assert_eq!(check("b1+"), Err(IllegalMoveReason::NoStackAtOrigin));
+    assert_eq!(check("b3>"), Err(IllegalMoveReason::WrongTurn));
+    assert_eq!(check("c2+"), Err(IllegalMoveReason::CannotFlattenWall));
+    assert_eq!(check("2a1>"), Err(IllegalMoveReason::StackMovementInvalid));
+    assert_eq!(check("a5-"), Ok(()));
+
+    // Only a capstone moving alone can flatten the wall on c3
+    let position = <Position<5>>::from_fen("x5/x5/x,1C,2S,x2/x2,21,x2/x5 1 10").unwrap();
+    let check =
+        |move_string: &str| position.check_move_legal(Move::from_string(move_string).unwrap());
+    assert_eq!(check("b3>"), Ok(()));
+    assert_eq!(check("c2<"), Ok(()));
+    assert_eq!(check("c2+"), Err(IllegalMoveReason::CannotFlattenWall));
+    assert_eq!(check("2c2+"), Err(IllegalMoveReason::CannotFlattenWall));
+    assert_eq!(check("2c2+11"), Err(IllegalMoveReason::CannotFlattenWall));
+    assert_eq!(check("3c2+"), Err(IllegalMoveReason::StackMovementInvalid));
+
+    // All of White's stones are in the stack on e5
+    let position = <Position<5>>::from_fen("x4,111111111111111111111/x5/x5/x5/x5 1 12").unwrap();
+    assert_eq!(
+        position.check_move_legal(Move::from_string("Sa1").unwrap()),
+        Err(IllegalMoveReason::NoReservesLeft)
+    );
+    assert_eq!(
+        position.check_move_legal(Move::from_string("Ca1").unwrap()),
+        Ok(())
+    );
+}
+
+#[test]
+fn spread_destination_test() {
+    for (move_string, destination) in [
+        ("a1>", "b1"),
+        ("2a1>11", "c1"),
+        ("3c3+12", "c5"),
+        ("3c3+", "c4"),
+        ("5e5<122", "b5"),
+    ] {
+        match Move::<5>::from_string(move_string).unwrap().expand() {
+            ExpMove::Move(square, direction, movement) => assert_eq!(
+                Position::spread_destination(square, direction, &movement),
+                Some(Square::parse_square(destination).unwrap()),
+                "{}",
+                move_string
+            ),
+            ExpMove::Place(_, _) => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn spread_off_the_board_test() {
+    let mut position = <Position<5>>::start_position();
+    do_moves_and_check_validity(&mut position, &["a1", "e5", "Sb1", "a2"]);
+    for move_string in ["4b1>1111", "b1-", "a2<"] {
+        let mv = Move::<5>::from_string(move_string).unwrap();
+        let ExpMove::Move(square, direction, movement) = mv.expand() else {
+            unreachable!()
+        };
+        assert_eq!(
+            Position::spread_destination(square, direction, &movement),
+            None,
+            "{}",
+            move_string
+        );
+        assert!(!position.is_crush_move(mv), "{}", move_string);
+    }
+}
+
+#[test]
+fn generate_moves_filtered_test() {
+    let filters = [
+        MoveFilter::NONE,
+        MoveFilter::FLAT_PLACEMENTS,
+        MoveFilter::WALL_PLACEMENTS | MoveFilter::CAP_PLACEMENTS,
+        MoveFilter::PLACEMENTS,
+        MoveFilter::SPREADS,
+        MoveFilter::FLAT_PLACEMENTS | MoveFilter::SPREADS,
+        MoveFilter::ALL,
+    ];
+    for tps in [
+        "x5/x5/x5/x5/x5 1 1",
+        "x5/x,x,2,x,x/x,2C,121C,2S,x/x5/x5 1 10",
+        "x5/x,x,2,x,x/x,2C,121C,2S,1/x5/x5 2 10",
+    ] {
+        let position = <Position<5>>::from_fen(tps).unwrap();
+        let mut all_moves = vec![];
+        position.generate_moves(&mut all_moves);
+
+        for filter in filters {
+            let mut filtered_moves = vec![];
+            position.generate_moves_filtered(&mut filtered_moves, filter);
+
+            let expected_moves: Vec<Move<5>> = all_moves
+                .iter()
+                .copied()
+                .filter(|mv| {
+                    filter.contains(match mv.expand() {
+                        ExpMove::Place(Role::Flat, _) => MoveFilter::FLAT_PLACEMENTS,
+                        ExpMove::Place(Role::Wall, _) => MoveFilter::WALL_PLACEMENTS,
+                        ExpMove::Place(Role::Cap, _) => MoveFilter::CAP_PLACEMENTS,
+                        ExpMove::Move(_, _, _) => MoveFilter::SPREADS,
+                    })
+                })
+                .collect();
+            assert_eq!(filtered_moves, expected_moves, "{:?} in {}", filter, tps);
+        }
+    }
+}
+
+#[test]
+fn perft_sweep_from_tps_test() {
+    let mut position = <Position<5>>::default();
+    do_moves_and_check_validity(&mut position, &["d3", "c3", "c4", "1d3<", "1c4-", "Sc4"]);
+    let mut position_from_tps = <Position<5>>::from_fen(&position.to_fen()).unwrap();
+    assert_eq!(position_from_tps.perft_sweep(3), vec![1, 87, 6155, 461_800]);
+
+    // White has a road, so the game is over
+    let mut finished_position =
+        <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/1,2,x3 2 6").unwrap();
+    assert_eq!(finished_position.perft_sweep(3), vec![1, 0, 0, 0]);
+    assert_eq!(finished_position.perft_sweep(0), vec![1]);
+}
+
+#[test]
+fn move_is_immediate_win_test() {
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let original_position = position.clone();
+    for (move_string, wins) in [("a1", true), ("Ca1", true), ("Sa1", false), ("b4", false)] {
+        let mv = position.move_from_san(move_string).unwrap();
+        assert_eq!(position.move_is_immediate_win(mv), wins, "{}", move_string);
+    }
+    assert_eq!(position, original_position);
+
+    // Road win by moving the capstone onto a1
+    let position = <Position<5>>::from_fen("1,x3,2/1,x3,2/1,2,x3/1,2,x3/2,1C,x3 1 6").unwrap();
+    assert!(position.move_is_immediate_win(position.move_from_san("b1<").unwrap()));
+    assert!(!position.move_is_immediate_win(position.move_from_san("b1>").unwrap()));
+
+    // Filling the board wins on flats, unless the last piece is a wall
+    let position = <Position<4>>::from_fen("1S,2,1,2/2,1,2,1/1,2,1,2/2,1,x,1 2 8").unwrap();
+    assert!(position.move_is_immediate_win(position.move_from_san("c1").unwrap()));
+    assert!(!position.move_is_immediate_win(position.move_from_san("Sc1").unwrap()));
+}
+
+#[test]
+fn is_dead_drawn_test() {
+    assert!(!<Position<5>>::default().is_dead_drawn());
+
+    // Black draws by filling the board with a flat, and no spread can complete a road
+    let tps = "1,2,1,2/2,1,2,1/1,2,1,2/2,1,x,1 2 8";
+    assert!(<Position<4>>::from_fen(tps).unwrap().is_dead_drawn());
+
+    // With komi, black wins by filling the board instead
+    let position =
+        <Position<4>>::from_fen_with_komi(tps, Komi::from_half_komi(2).unwrap()).unwrap();
+    assert!(!position.is_dead_drawn());
+
+    // Black wins on flats, since one of white's stones is a wall
+    let position = <Position<4>>::from_fen("1S,2,1,2/2,1,2,1/1,2,1,2/2,1,x,1 2 8").unwrap();
+    assert!(!position.is_dead_drawn());
+}
+
+#[test]
+fn check_move_legal_placement_phase_test() {
+    let mut position = <Position<5>>::default();
+    for _ in 0..2 {
+        let check =
+            |move_string: &str| position.check_move_legal(Move::from_string(move_string).unwrap());
+        assert_eq!(check("c3"), Ok(()));
+        assert_eq!(
+            check("Sc3"),
+            Err(IllegalMoveReason::OpeningMoveNotFlatPlacement)
+        );
+        assert_eq!(
+            check("Cc3"),
+            Err(IllegalMoveReason::OpeningMoveNotFlatPlacement)
+        );
+
+        let mut moves = vec![];
+        position.generate_moves(&mut moves);
+        for mv in moves {
+            assert_eq!(position.check_move_legal(mv), Ok(()));
+        }
+        if position.half_moves_played() == 0 {
+            position.do_move(Move::from_string("a1").unwrap());
+        }
+    }
+
+    // Black cannot move the black flat that White placed on a1
+    let check =
+        |move_string: &str| position.check_move_legal(Move::from_string(move_string).unwrap());
+    assert_eq!(check("a1"), Err(IllegalMoveReason::SquareOccupied));
+    assert_eq!(
+        check("a1+"),
+        Err(IllegalMoveReason::OpeningMoveNotFlatPlacement)
+    );
+
+    position.do_move(Move::from_string("e5").unwrap());
+    let check =
+        |move_string: &str| position.check_move_legal(Move::from_string(move_string).unwrap());
+    assert_eq!(check("Sc3"), Ok(()));
+    assert_eq!(check("Cc3"), Ok(()));
+}
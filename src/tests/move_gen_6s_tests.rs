@@ -1,5 +1,7 @@
+use board_game_traits::Position as PositionTrait;
 use pgn_traits::PgnPosition;
 
+use crate::move_gen;
 use crate::position::Position;
 
 use super::move_gen_generic_tests::perft_check_answers;
@@ -24,3 +26,16 @@ fn endgame_perf_test() {
         // &[1, 140, 21_402, 2_774_593, 395_359_484, 48_986_506_534],
     );
 }
+
+#[test]
+fn move_gen_benchmark_test() {
+    for (name, position) in move_gen::benchmark_positions() {
+        let mut moves = vec![];
+        position.generate_moves(&mut moves);
+        assert!(!moves.is_empty(), "No moves in {} position", name);
+
+        let timing = move_gen::benchmark(&position, 10);
+        assert_eq!(timing.iterations, 10);
+        assert_eq!(timing.moves_generated, 10 * moves.len() as u64);
+    }
+}
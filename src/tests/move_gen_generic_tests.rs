@@ -1,5 +1,8 @@
-use crate::position::Position;
+use crate::move_gen::max_legal_moves;
+use crate::position::{GroupData, Move, Position};
+use arrayvec::ArrayVec;
 use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
 
 #[test]
 fn start_position_move_gen_test() {
@@ -38,3 +41,95 @@ fn start_position_move_gen_prop<const S: usize>() {
         position.reverse_move(reverse_move);
     }
 }
+
+#[test]
+fn perft_divide_test() {
+    perft_divide_prop::<4>();
+    perft_divide_prop::<5>();
+    perft_divide_prop::<6>();
+}
+
+fn perft_divide_prop<const S: usize>() {
+    let mut position = <Position<S>>::default();
+    // Start from opposite corners
+    let first_move = position.moves_iter().next().unwrap();
+    position.do_move(first_move);
+    let second_move = position.moves_iter().last().unwrap();
+    position.do_move(second_move);
+
+    assert!(position.perft_divide(0).is_empty());
+
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+    let divide = position.perft_divide(1);
+    assert_eq!(divide.iter().map(|(mv, _)| *mv).collect::<Vec<_>>(), moves);
+    assert!(divide.iter().all(|(_, count)| *count == 1));
+
+    for depth in 2..=3 {
+        let divide = position.perft_divide(depth);
+        assert_eq!(
+            divide.iter().map(|(_, count)| count).sum::<u64>(),
+            position.perft(depth)
+        );
+    }
+}
+
+#[test]
+fn group_data_after_move_sweep_test() {
+    // Tall stacks with long spreads, from a game on Playtak
+    let mut position = <Position<6>>::from_fen(
+        "2,2,22221C,2,x2/x,1,1,x,1,2/x,1,111212C,1212,x,2/x,112S,1,x,112S,2/11212,x,1,112S,1112,2/x2,1,1,1,1 1 40",
+    )
+    .unwrap();
+    let group_data = position.group_data();
+    group_data_after_move_sweep(&mut position, &group_data, 2);
+    let mut position = <Position<5>>::start_position();
+    let group_data = position.group_data();
+    group_data_after_move_sweep(&mut position, &group_data, 3);
+}
+
+/// Check the incremental group data against the full recomputation after every move sequence up to `depth`.
+/// The incremental group data is passed on to the next ply, so errors cannot hide behind a fresh recomputation.
+fn group_data_after_move_sweep<const S: usize>(
+    position: &mut Position<S>,
+    group_data: &GroupData<S>,
+    depth: u16,
+) {
+    if depth == 0 || position.game_result().is_some() {
+        return;
+    }
+    let mut moves = vec![];
+    position.generate_moves(&mut moves);
+    for mv in moves {
+        let reverse_move = position.do_move(mv);
+        let new_group_data = position.group_data_after_move(group_data, mv);
+        assert_eq!(
+            new_group_data,
+            position.group_data(),
+            "Wrong group data after {} on\n{:?}",
+            mv,
+            position
+        );
+        group_data_after_move_sweep(position, &new_group_data, depth - 1);
+        position.reverse_move(reverse_move);
+    }
+}
+
+#[test]
+fn max_legal_moves_test() {
+    assert_eq!(
+        [3, 4, 5, 6, 7, 8].map(max_legal_moves),
+        [108, 448, 1500, 4464, 12348, 32512]
+    );
+}
+
+#[test]
+fn generate_moves_into_arrayvec_test() {
+    let position = <Position<5>>::from_fen("x5/x,x,2,x,x/x,2C,121C,2S,x/x5/x5 1 10").unwrap();
+    let mut moves: ArrayVec<Move<5>, { max_legal_moves(5) }> = ArrayVec::new();
+    position.generate_moves_into_arrayvec(&mut moves);
+
+    let mut expected_moves = vec![];
+    position.generate_moves(&mut expected_moves);
+    assert_eq!(moves.as_slice(), expected_moves.as_slice());
+}
@@ -1,3 +1,9 @@
+use board_game_traits::Position as PositionTrait;
+use pgn_traits::PgnPosition;
+
+use crate::evaluation::parameters::{policy_indexes, IncrementalPolicy, Policy, PolicyApplier};
+use crate::position::{Komi, Position};
+
 use super::TestPosition;
 
 #[test]
@@ -97,3 +103,106 @@ fn create_double_tak_threat_test() {
     let test_position = TestPosition::from_tps("x2,1,21,x,2/1,x,212,1,212,2/1S,2,2,2C,21,2/21S,1,121C,x2,12/2,2,121,1,1,1/2,2,1,x2,22S 1 28");
     test_position.top_five_policy_move_prop::<6>(&["3c3-"]);
 }
+
+#[test]
+fn policy_prior_in_context_test() {
+    // White can win with a1, so every other move is penalized for declining the win
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let komi = Komi::default();
+    let mv = position.move_from_san("b4").unwrap();
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+
+    let mut isolated_policy = [IncrementalPolicy::<5>::new(<Position<5>>::policy_params(
+        komi,
+    ))];
+    position.features_for_moves(
+        &mut isolated_policy,
+        &[mv],
+        &mut vec![],
+        &position.group_data(),
+    );
+    let isolated_score = isolated_policy[0].finish(legal_moves.len()).to_f32();
+
+    let in_context_score = position.policy_prior_for_move(mv, komi);
+    assert!(
+        in_context_score < isolated_score,
+        "{} >= {}",
+        in_context_score,
+        isolated_score
+    );
+
+    let prior_sum: f32 = legal_moves
+        .iter()
+        .map(|legal_move| position.policy_prior_for_move(*legal_move, komi))
+        .sum();
+    assert!(
+        (prior_sum - 1.0).abs() < 0.01,
+        "Priors sum to {}",
+        prior_sum
+    );
+    // b1 is already occupied
+    let illegal_move = position.move_from_san("b1").unwrap();
+    assert_eq!(position.policy_prior_for_move(illegal_move, komi), 0.0);
+}
+
+#[test]
+fn two_flats_left_komi_test() {
+    // White has two flats left, and leads by two flats after placing d2
+    let tps = "222222,222222,x2/x4/x4/111111,111111,1,x 1 20";
+    for (half_komi, komi_adjusted_flat_lead) in [(-1, 3.0), (0, 2.0), (3, 1.0), (4, 0.0)] {
+        let komi = Komi::from_half_komi(half_komi).unwrap();
+        let position = <Position<4>>::from_fen_with_komi(tps, komi).unwrap();
+        let mv = position.move_from_san("d2").unwrap();
+
+        let mut policy = [Policy::<4>::new(<Position<4>>::policy_params(
+            Komi::default(),
+        ))];
+        position.features_for_moves(&mut policy, &[mv], &mut vec![], &position.group_data());
+        let features = policy_indexes::<4>()
+            .two_flats_left
+            .as_slice(&policy[0].features);
+        assert_eq!(features[0].to_f32(), 1.0);
+        assert_eq!(
+            features[1].to_f32(),
+            komi_adjusted_flat_lead,
+            "Half komi {}",
+            half_komi
+        );
+    }
+}
+
+#[test]
+fn moves_by_policy_test() {
+    let position = <Position<5>>::from_fen("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5").unwrap();
+    let moves = position.moves_by_policy(Komi::default());
+
+    let mut legal_moves = vec![];
+    position.generate_moves(&mut legal_moves);
+    assert_eq!(moves.len(), legal_moves.len());
+
+    // One of the winning moves should be the most likely
+    let winning_moves = [
+        position.move_from_san("a1").unwrap(),
+        position.move_from_san("Ca1").unwrap(),
+    ];
+    assert!(winning_moves.contains(&moves[0].0));
+    assert!(moves.windows(2).all(|window| window[0].1 >= window[1].1));
+}
+
+#[test]
+fn top_policy_moves_test() {
+    let position = <Position<5>>::from_fen("x5/x,x,2,x,x/x,2C,121C,2S,x/x5/x5 1 10").unwrap();
+    let all_moves = position.moves_by_policy(Komi::default());
+
+    for k in [0, 1, 5, all_moves.len(), all_moves.len() + 10] {
+        let top_moves = position.top_policy_moves(k, Komi::default());
+        assert_eq!(top_moves.len(), k.min(all_moves.len()));
+        assert!(top_moves
+            .windows(2)
+            .all(|window| window[0].1 >= window[1].1));
+        for ((_, top_prior), (_, prior)) in top_moves.iter().zip(all_moves.iter()) {
+            assert_eq!(top_prior.to_f32(), *prior);
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use crate::position::{Move, Position};
-use crate::ptn::{ptn_parser, Game, PtnMove};
+use crate::position::{Komi, Move, Position};
+use crate::ptn::{binary, ptn_parser, Game, GameCursor, PtnMove};
 use crate::tests::do_moves_and_check_validity;
 use board_game_traits::Position as PositionTrait;
+use half::f16;
 use pgn_traits::PgnPosition;
 use std::io::Cursor;
 
@@ -112,3 +113,219 @@ fn parse_ptn_without_result2() {
 fn parse_bad_direction_test() {
     assert!(<Move<6>>::from_string("a1d").is_err())
 }
+
+#[test]
+fn move_to_string_verbose_test() {
+    for (move_string, verbose_string) in [
+        ("a1", "a1"),
+        ("Sb2", "Sb2"),
+        ("Cc3", "Cc3"),
+        ("a1>", "1a1>1"),
+        ("3c3+", "3c3+3"),
+        ("3c3+21", "3c3+21"),
+        ("6a1>123", "6a1>123"),
+    ] {
+        let mv = <Move<6>>::from_string(move_string).unwrap();
+        assert_eq!(mv.to_string(), move_string);
+        assert_eq!(mv.to_string_verbose(), verbose_string);
+        assert_eq!(<Move<6>>::from_string(verbose_string).unwrap(), mv);
+    }
+}
+
+#[test]
+fn parse_move_from_str_test() {
+    let mv: Move<5> = "a1>".parse().unwrap();
+    assert_eq!(mv, Move::from_string("a1>").unwrap());
+    assert_eq!("3c3+12".parse::<Move<5>>().unwrap().to_string(), "3c3+12");
+    assert!("a1d".parse::<Move<5>>().is_err());
+    assert!("6a1>".parse::<Move<5>>().is_err());
+}
+
+#[test]
+fn game_cursor_test() {
+    let move_strings = [
+        "a1", "e5", "c3", "d3", "c2", "d2", "Cc4", "d4", "c4-", "d3+", "2c3-",
+    ];
+    let mut position = <Position<5>>::start_position();
+    let mut positions = vec![position.clone()];
+    for move_string in move_strings {
+        position.do_move(position.move_from_san(move_string).unwrap());
+        positions.push(position.clone());
+    }
+    let game: Game<Position<5>> = Game {
+        start_position: Position::start_position(),
+        moves: position
+            .moves()
+            .iter()
+            .map(|mv| PtnMove {
+                mv: *mv,
+                annotations: vec![],
+                comment: String::new(),
+            })
+            .collect(),
+        game_result_str: None,
+        tags: vec![],
+    };
+
+    let mut cursor = GameCursor::new(&game);
+    assert!(!cursor.back());
+    assert_eq!(*cursor.current_position(), positions[0]);
+
+    for (ply, position) in positions.iter().enumerate().skip(1) {
+        assert!(cursor.forward());
+        assert_eq!(cursor.ply(), ply);
+        assert_eq!(cursor.current_position(), position);
+    }
+    assert!(!cursor.forward());
+
+    for ply in [3, 0, 11, 7, 7, 2, 10] {
+        cursor.goto(ply);
+        assert_eq!(cursor.ply(), ply);
+        assert_eq!(*cursor.current_position(), positions[ply]);
+    }
+
+    assert!(cursor.back());
+    assert_eq!(*cursor.current_position(), positions[9]);
+}
+
+#[test]
+fn binary_game_round_trip_test() {
+    let mut position = <Position<6>>::start_position_with_komi(Komi::from_half_komi(4).unwrap());
+    do_moves_and_check_validity(
+        &mut position,
+        &[
+            "a1", "f6", "e6", "a2", "d6", "a3", "c6", "a4", "b6", "a5", "a6",
+        ],
+    );
+    let evals: Vec<f16> = (0..position.moves().len())
+        .map(|i| f16::from_f32(i as f32 / 11.0))
+        .collect();
+    let game: Game<Position<6>> = Game {
+        start_position: <Position<6>>::start_position_with_komi(Komi::from_half_komi(4).unwrap()),
+        moves: position
+            .moves()
+            .iter()
+            .map(|mv| PtnMove {
+                mv: *mv,
+                annotations: vec![],
+                comment: String::new(),
+            })
+            .collect(),
+        game_result_str: Some("R-0"),
+        tags: vec![],
+    };
+
+    let mut bytes = vec![];
+    binary::write_game(&mut bytes, &game, &evals).unwrap();
+    assert_eq!(bytes.len(), 13 + 4 * game.moves.len());
+
+    let (read_game, read_evals) = binary::read_game::<_, 6>(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(read_game, game);
+    assert_eq!(read_evals, evals);
+
+    // Reading as the wrong size fails
+    assert!(binary::read_game::<_, 5>(&mut Cursor::new(&bytes)).is_err());
+}
+
+#[test]
+fn binary_game_from_tps_round_trip_test() {
+    let start_position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 1 2").unwrap();
+    let mut position = start_position.clone();
+    do_moves_and_check_validity(&mut position, &["b3", "c4"]);
+    let game: Game<Position<5>> = Game {
+        start_position,
+        moves: position
+            .moves()
+            .iter()
+            .map(|mv| PtnMove {
+                mv: *mv,
+                annotations: vec![],
+                comment: String::new(),
+            })
+            .collect(),
+        game_result_str: None,
+        tags: vec![],
+    };
+    let evals = vec![f16::from_f32(0.52), f16::from_f32(-0.25)];
+
+    let mut bytes = vec![];
+    binary::write_game(&mut bytes, &game, &evals).unwrap();
+    let (read_game, read_evals) = binary::read_game::<_, 5>(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(read_game, game);
+    assert_eq!(read_evals, evals);
+
+    // Corrupting the last move makes it illegal
+    let len = bytes.len();
+    bytes[len - 4..len - 2].copy_from_slice(&game.moves[0].mv.to_u16().to_le_bytes());
+    assert!(binary::read_game::<_, 5>(&mut Cursor::new(&bytes)).is_err());
+}
+
+#[test]
+fn parse_ptn_stream_test() {
+    let ptn =
+        "[Size \"5\"]\n[Player1 \"a\"]\n\n1. a1 e5 2. c3 {A comment\n[with a bracket]} c2 R-0\n\n\
+        [Size \"5\"]\n\n1. a1 a1 0-1\n\n\
+        [Size \"5\"]\n[Player1 \"b\"]\n\n1. a1 e5\n2. d3 d4\n";
+    let games: Vec<_> = ptn_parser::parse_ptn_stream::<Position<5>, _>(Cursor::new(ptn)).collect();
+    assert_eq!(games.len(), 3);
+
+    let first_game = games[0].as_ref().unwrap();
+    assert_eq!(first_game.moves.len(), 4);
+    assert_eq!(first_game.moves[2].comment, "A comment\n[with a bracket]");
+    assert_eq!(first_game.game_result_str, Some("R-0"));
+    assert_eq!(
+        first_game.tags,
+        vec![
+            ("Size".to_string(), "5".to_string()),
+            ("Player1".to_string(), "a".to_string())
+        ]
+    );
+
+    // The illegal move in the second game does not stop the third game from being parsed
+    assert!(games[1].is_err());
+
+    let third_game = games[2].as_ref().unwrap();
+    assert_eq!(third_game.moves.len(), 4);
+    assert_eq!(third_game.game_result_str, None);
+    assert_eq!(
+        games[0].as_ref().unwrap(),
+        &ptn_parser::parse_ptn::<Position<5>>(ptn).unwrap()[0]
+    );
+}
+
+#[test]
+fn game_to_ptn_with_evals_test() {
+    let start_position = <Position<5>>::from_fen("x5/x5/x2,1,x2/x,2,x3/x5 2 2").unwrap();
+    let mut position = start_position.clone();
+    do_moves_and_check_validity(&mut position, &["b3", "c4", "d4"]);
+    let mut game: Game<Position<5>> = Game {
+        start_position,
+        moves: position
+            .moves()
+            .iter()
+            .map(|mv| PtnMove {
+                mv: *mv,
+                annotations: vec![],
+                comment: String::new(),
+            })
+            .collect(),
+        game_result_str: None,
+        tags: vec![],
+    };
+    game.moves[1].comment = "Best".to_string();
+
+    let mut ptn_writer = Vec::new();
+    game.game_to_ptn_with_evals(&mut ptn_writer, &[0.543, 0.5, 0.0])
+        .unwrap();
+    let ptn = String::from_utf8(ptn_writer).unwrap();
+
+    assert!(
+        ptn.contains("2... b3 {W: 54.3%} 3. c4 {Best, W: 50.0%} d4 {W: 0.0%} *"),
+        "Got ptn {}",
+        ptn
+    );
+
+    let parsed_game = ptn_parser::parse_ptn::<Position<5>>(&ptn).unwrap();
+    assert_eq!(parsed_game[0].moves.len(), 3);
+    assert_eq!(parsed_game[0].moves[0].comment, "W: 54.3%");
+}
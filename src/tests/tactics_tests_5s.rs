@@ -260,3 +260,14 @@ fn simple_capture_to_tinue() {
     };
     test_position.plays_correct_move_long_prop::<5>(&["d4-"]);
 }
+
+#[test]
+fn complete_road_with_any_komi() {
+    let test_position = TestPosition::from_tps("1,x4/1,x4/1,2,x3/1,2,x3/x,2,x3 1 5");
+    let komis: Vec<Komi> = [0.0, 1.0, 2.0, 2.5]
+        .into_iter()
+        .map(|komi| Komi::try_from(komi).unwrap())
+        .collect();
+    test_position.plays_correct_move_komi_range_prop::<5>(&komis, &["a1", "Ca1"]);
+    test_position.avoid_move_komi_range_prop::<5>(&komis, &["b4"]);
+}
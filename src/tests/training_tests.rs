@@ -0,0 +1,64 @@
+use std::sync::atomic::AtomicU64;
+
+use crate::position::{Komi, Move, Position};
+use crate::search::TimeControl;
+use crate::tune::training::{parse_opening, play_game_pair};
+
+#[test]
+fn fixed_opening_game_pair_test() {
+    let komi = Komi::default();
+    let opening_strings: Vec<String> = ["a1", "d4", "Sb2"].map(str::to_string).into();
+    let opening = parse_opening::<4>(&opening_strings, komi).unwrap();
+    assert_eq!(opening.len(), 3);
+
+    let value_params = <Position<4>>::value_params(komi);
+    let policy_params = <Position<4>>::policy_params(komi);
+    let current_params_wins = AtomicU64::new(0);
+    let last_params_wins = AtomicU64::new(0);
+
+    // Both games of the pair, one with each color assignment, start from the same opening
+    let games: Vec<_> = (0..2)
+        .map(|i| {
+            play_game_pair::<4>(
+                komi,
+                &opening,
+                &TimeControl::FixedNodes(100),
+                value_params,
+                policy_params,
+                value_params,
+                policy_params,
+                &current_params_wins,
+                &last_params_wins,
+                i,
+            )
+            .0
+        })
+        .collect();
+    for game in games.iter() {
+        assert_eq!(
+            game.start_position,
+            <Position<4>>::start_position_with_komi(komi)
+        );
+        let first_moves: Vec<Move<4>> = game
+            .moves
+            .iter()
+            .take(3)
+            .map(|ptn_move| ptn_move.mv)
+            .collect();
+        assert_eq!(first_moves, opening);
+    }
+}
+
+#[test]
+fn parse_opening_test() {
+    let komi = Komi::default();
+    let parse = |moves: &[&str]| {
+        let moves: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+        parse_opening::<5>(&moves, komi)
+    };
+    assert_eq!(parse(&[]).unwrap(), vec![]);
+    assert!(parse(&["a1", "e5", "c3"]).is_ok());
+    assert!(parse(&["a1", "a1"]).is_err());
+    assert!(parse(&["Ca1"]).is_err());
+    assert!(parse(&["a1", "f6"]).is_err());
+}
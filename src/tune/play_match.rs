@@ -17,6 +17,70 @@ use crate::search;
 use crate::search::MctsSetting;
 use crate::search::TimeControl;
 
+/// Win/draw/loss counts from a match between two players, from the first player's perspective
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MatchResult {
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+}
+
+impl MatchResult {
+    pub fn num_games(&self) -> u64 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average score per game, counting draws as half a win
+    pub fn score(&self) -> f32 {
+        (self.wins as f32 + self.draws as f32 / 2.0) / self.num_games() as f32
+    }
+
+    /// Estimated Elo difference, and the half-width of its 95% confidence interval.
+    /// The estimate is infinite if one player scored every point.
+    pub fn elo_diff(&self) -> (f32, f32) {
+        let num_games = self.num_games() as f32;
+        if num_games == 0.0 {
+            return (0.0, f32::INFINITY);
+        }
+        let score = self.score();
+        let variance = (self.wins as f32 * (1.0 - score).powi(2)
+            + self.draws as f32 * (0.5 - score).powi(2)
+            + self.losses as f32 * score.powi(2))
+            / num_games;
+        let std_error = (variance / num_games).sqrt();
+
+        let lower = score_to_elo((score - 1.96 * std_error).max(0.0));
+        let upper = score_to_elo((score + 1.96 * std_error).min(1.0));
+
+        (score_to_elo(score), (upper - lower) / 2.0)
+    }
+
+    /// Likelihood of superiority: The probability that the first player is stronger.
+    /// Draws carry no information, and are ignored.
+    pub fn likelihood_of_superiority(&self) -> f32 {
+        let decisive_games = (self.wins + self.losses) as f64;
+        if decisive_games == 0.0 {
+            return 0.5;
+        }
+        let diff = self.wins as f64 - self.losses as f64;
+        (0.5 * (1.0 + erf(diff / (2.0 * decisive_games).sqrt()))) as f32
+    }
+}
+
+fn score_to_elo(score: f32) -> f32 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Approximation of the error function, with a maximum error of 1.5e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let result = 1.0 - polynomial * (-x * x).exp();
+    result.copysign(x)
+}
+
 /// Play a single training game between two parameter sets
 pub fn play_game<const S: usize>(
     white_settings: &MctsSetting<S>,
@@ -19,6 +19,7 @@ use crate::search::TimeControl;
 use board_game_traits::GameResult;
 use board_game_traits::Position as PositionTrait;
 use half::f16;
+use pgn_traits::PgnPosition;
 use rand::prelude::*;
 use rayon::prelude::*;
 
@@ -30,6 +31,7 @@ use crate::search::MctsSetting;
 use crate::tune::gradient_descent;
 use crate::tune::gradient_descent::TrainingSample;
 use crate::tune::play_match::play_game;
+use crate::tune::play_match::MatchResult;
 
 // The score, or probability of being played, for a given move
 type MoveScore<const S: usize> = (Move<S>, f16);
@@ -42,6 +44,8 @@ pub struct TrainingOptions {
     pub batch_size: usize,
     pub num_games_for_tuning: usize,
     pub nodes_per_game: usize,
+    /// Moves in PTN notation that every selfplay game starts with. May be empty
+    pub fixed_opening: Vec<String>,
 }
 
 pub fn train_from_scratch<const S: usize, const N: usize, const M: usize>(
@@ -119,6 +123,10 @@ pub fn continue_training<const S: usize, const N: usize, const M: usize>(
     )
 }
 
+/// Play batches of games between the current and previous parameters, and train on them indefinitely.
+///
+/// Every game starts with the options' `fixed_opening`, which may be empty. Consecutive games are played as pairs
+/// with colors swapped, so both parameter sets play both sides of the opening.
 pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
     options: TrainingOptions,
     komi: Komi,
@@ -128,6 +136,8 @@ pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
     mut all_move_scores: Vec<MoveScoresForGame<S>>,
     mut batch_id: usize,
 ) -> Result<(), DynError> {
+    let fixed_opening = parse_opening::<S>(&options.fixed_opening, komi)?;
+
     let mut last_value_params: &'static [f32; N] = Box::leak(Box::new(initial_value_params));
     let mut last_policy_params: &'static [f32; M] = Box::leak(Box::new(initial_policy_params));
 
@@ -148,6 +158,8 @@ pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
             .map(|i| {
                 play_game_pair::<S>(
                     komi,
+                    &fixed_opening,
+                    &TimeControl::FixedNodes(50_000),
                     last_value_params,
                     last_policy_params,
                     value_params,
@@ -211,9 +223,16 @@ pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
         let wins = current_params_wins.into_inner();
         let losses = last_params_wins.into_inner();
         let draws = options.batch_size as u64 - wins - losses;
+        let match_result = MatchResult {
+            wins,
+            draws,
+            losses,
+        };
+        let (elo_diff, elo_error) = match_result.elo_diff();
 
-        println!("Finished playing batch of {} games. {} games played in total. {} white wins, {} draws, {} black wins, {} aborted. New vs old parameters was +{}-{}={}.",
-            games.len(), all_games.len(), game_stats.white_wins, game_stats.draws, game_stats.black_wins, game_stats.aborted, wins, losses, draws
+        println!("Finished playing batch of {} games. {} games played in total. {} white wins, {} draws, {} black wins, {} aborted. New vs old parameters was +{}-{}={}, {:+.1} ± {:.1} Elo, {:.1}% LOS.",
+            games.len(), all_games.len(), game_stats.white_wins, game_stats.draws, game_stats.black_wins, game_stats.aborted, wins, losses, draws,
+            elo_diff, elo_error, match_result.likelihood_of_superiority() * 100.0
         );
 
         // Only take the most recent half of the games, to avoid training on bad, old games
@@ -261,9 +280,32 @@ pub fn train_perpetually<const S: usize, const N: usize, const M: usize>(
     }
 }
 
+/// Parse and validate an opening written as PTN moves
+pub(crate) fn parse_opening<const S: usize>(
+    move_strings: &[String],
+    komi: Komi,
+) -> Result<Vec<Move<S>>, DynError> {
+    let mut position = Position::start_position_with_komi(komi);
+    let mut opening = vec![];
+    for move_string in move_strings {
+        let mv = position
+            .move_from_san(move_string)
+            .map_err(|err| format!("Invalid move {} in fixed opening: {}", move_string, err))?;
+        if !position.move_is_legal(mv) {
+            return Err(format!("Illegal move {} in fixed opening", move_string).into());
+        }
+        position.do_move(mv);
+        opening.push(mv);
+    }
+    Ok(opening)
+}
+
+/// Play game number `i` of a pair, with the current parameters as white for even `i`, and as black for odd `i`
 #[allow(clippy::too_many_arguments)]
-fn play_game_pair<const S: usize>(
+pub(crate) fn play_game_pair<const S: usize>(
     komi: Komi,
+    opening: &[Move<S>],
+    time_control: &TimeControl,
     last_value_params: &'static [f32],
     last_policy_params: &'static [f32],
     value_params: &'static [f32],
@@ -281,14 +323,7 @@ fn play_game_pair<const S: usize>(
         .add_policy_params(last_policy_params)
         .add_dirichlet(0.2);
     if i % 2 == 0 {
-        let game = play_game::<S>(
-            &settings,
-            &last_settings,
-            komi,
-            &[],
-            1.0,
-            &TimeControl::FixedNodes(50_000),
-        );
+        let game = play_game::<S>(&settings, &last_settings, komi, opening, 1.0, time_control);
         match game.0.game_result() {
             Some(GameResult::WhiteWin) => {
                 current_params_wins.fetch_add(1, Ordering::Relaxed);
@@ -300,14 +335,7 @@ fn play_game_pair<const S: usize>(
         };
         game
     } else {
-        let game = play_game::<S>(
-            &last_settings,
-            &settings,
-            komi,
-            &[],
-            1.0,
-            &TimeControl::FixedNodes(50_000),
-        );
+        let game = play_game::<S>(&last_settings, &settings, komi, opening, 1.0, time_control);
         match game.0.game_result() {
             Some(GameResult::BlackWin) => {
                 current_params_wins.fetch_add(1, Ordering::Relaxed);